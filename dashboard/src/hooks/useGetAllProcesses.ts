@@ -7,7 +7,9 @@ export const useAllProcesses = (): UseQueryResult<any, Error> => {
   return useQuery(
     "allProcesses",
     async () => {
-      const response = await fetch(`${apiBaseUrl}/api/allProcesses`);
+      const response = await fetch(`${apiBaseUrl}/api/allProcesses`, {
+        credentials: "include",
+      });
       if (!response.ok) {
         throw new Error("Failed to fetch all processes");
       }
@@ -7,7 +7,9 @@ export const useGetCpuData = (): UseQueryResult<any, Error> => {
   return useQuery(
     "GetCpuData",
     async () => {
-      const response = await fetch(`${apiBaseUrl}/api/cpu-info`);
+      const response = await fetch(`${apiBaseUrl}/api/cpu-info`, {
+        credentials: "include",
+      });
       if (!response.ok) {
         throw new Error("Failed to fetch cpu data");
       }
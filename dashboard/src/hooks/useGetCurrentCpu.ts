@@ -7,7 +7,9 @@ export const useGetCurrentCpuData = (): UseQueryResult<any, Error> => {
   return useQuery(
     "GetCurrentCpuData",
     async () => {
-      const response = await fetch(`${apiBaseUrl}/api/cpu-info-current`);
+      const response = await fetch(`${apiBaseUrl}/api/cpu-info-current`, {
+        credentials: "include",
+      });
       if (!response.ok) {
         throw new Error("Failed to fetch cpu data");
       }
@@ -7,7 +7,9 @@ export const useGetCurrentMemoryData = (): UseQueryResult<any, Error> => {
   return useQuery(
     "GetCurrentMemoryData",
     async () => {
-      const response = await fetch(`${apiBaseUrl}/api/memory-current`);
+      const response = await fetch(`${apiBaseUrl}/api/memory-current`, {
+        credentials: "include",
+      });
       if (!response.ok) {
         throw new Error("Failed to fetch disk data");
       }
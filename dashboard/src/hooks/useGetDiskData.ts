@@ -7,7 +7,9 @@ export const useGetDiskData = (): UseQueryResult<any, Error> => {
   return useQuery(
     "GetDiskData",
     async () => {
-      const response = await fetch(`${apiBaseUrl}/api/disks`);
+      const response = await fetch(`${apiBaseUrl}/api/disks`, {
+        credentials: "include",
+      });
       if (!response.ok) {
         throw new Error("Failed to fetch disk data");
       }
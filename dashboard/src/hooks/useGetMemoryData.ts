@@ -7,7 +7,9 @@ export const useGetMemoryData = (): UseQueryResult<any, Error> => {
   return useQuery(
     "GetMemoryData",
     async () => {
-      const response = await fetch(`${apiBaseUrl}/api/memory`);
+      const response = await fetch(`${apiBaseUrl}/api/memory`, {
+        credentials: "include",
+      });
       if (!response.ok) {
         throw new Error("Failed to fetch disk data");
       }
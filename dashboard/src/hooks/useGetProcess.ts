@@ -9,7 +9,9 @@ export const useGetProcessData = (
   return useQuery(
     ["GetProcessData", processId],
     async () => {
-      const response = await fetch(`${apiBaseUrl}/api/process/${processId}`);
+      const response = await fetch(`${apiBaseUrl}/api/process/${processId}`, {
+        credentials: "include",
+      });
       if (!response.ok) {
         throw new Error("Failed to fetch process data");
       }
@@ -0,0 +1,4 @@
+// Make sure that the embedded migrations are rebuilt when they change
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,95 @@
+-- Initial schema of Nebula Metrics
+--
+-- Databases created before migrations were introduced already have most of
+-- these tables, so they are only created when they do not exist yet.
+
+CREATE TABLE IF NOT EXISTS PROCESS (
+    PID            INT    NOT NULL CHECK(PID >= 1),
+    EXEC           TEXT   NOT NULL,
+    START_TIME     INT    NOT NULL CHECK(START_TIME >= 0),
+    IS_ALIVE       INT    NOT NULL CHECK(IS_ALIVE IN (0, 1)),
+    INIT_TOTAL_CPU REAL   NOT NULL CHECK(INIT_TOTAL_CPU >= 0),
+    PRIMARY KEY (PID)
+);
+
+CREATE TABLE IF NOT EXISTS CPU (
+    CPU_CORE     INT  NOT NULL CHECK(CPU_CORE >= 0),
+    MHZ          REAL NOT NULL CHECK(MHZ >= 0),
+    TOTAL_CACHE  INT  NOT NULL CHECK(TOTAL_CACHE >= 0),
+    PRIMARY KEY (CPU_CORE)
+);
+
+CREATE TABLE IF NOT EXISTS PROCSTAT (
+    PID              INT  NOT NULL,
+    TIMESTAMP        INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    TOTAL_CPU        REAL NOT NULL CHECK(TOTAL_CPU >= 0),
+    PERCENT_CPU      REAL          CHECK(PERCENT_CPU >= 0),
+    CPU_CORE         INT,
+    VIRTUAL_MEMORY   INT  NOT NULL CHECK(VIRTUAL_MEMORY >= 0),
+    RESIDENT_MEMORY  INT  NOT NULL CHECK(RESIDENT_MEMORY >= 0),
+    SHARED_MEMORY    INT  NOT NULL CHECK(SHARED_MEMORY >= 0),
+    PRIMARY KEY (PID, TIMESTAMP)
+    FOREIGN KEY (PID)       REFERENCES PROCESS(PID),
+    FOREIGN KEY (CPU_CORE)  REFERENCES CPU(CPU_CORE)
+);
+
+CREATE TABLE IF NOT EXISTS CPUSTAT (
+    CPU_CORE    INT  NOT NULL,
+    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    USAGE       REAL NOT NULL CHECK(USAGE >= 0),
+    PRIMARY KEY (CPU_CORE, TIMESTAMP)
+);
+
+CREATE TABLE IF NOT EXISTS MEMORY (
+    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    TOTAL       INT  NOT NULL CHECK(TOTAL >= 0),
+    FREE        INT  NOT NULL CHECK(FREE >= 0 AND FREE <= TOTAL),
+    SWAP_TOTAL  INT  NOT NULL CHECK(SWAP_TOTAL >= 0),
+    SWAP_FREE   INT  NOT NULL CHECK(SWAP_FREE >= 0 AND SWAP_FREE <= SWAP_TOTAL),
+    PRIMARY KEY (TIMESTAMP)
+);
+
+CREATE TABLE IF NOT EXISTS DISK (
+    DEVICE_NAME TEXT NOT NULL,
+    MOUNT       TEXT NOT NULL,
+    FS_TYPE     TEXT NOT NULL,
+    PRIMARY KEY (DEVICE_NAME)
+);
+
+CREATE TABLE IF NOT EXISTS DISKSTAT (
+    DEVICE_NAME TEXT NOT NULL,
+    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    USED        INT  NOT NULL CHECK(USED >= 0),
+    AVAILABLE   INT  NOT NULL CHECK(AVAILABLE >= 0),
+    PRIMARY KEY (DEVICE_NAME, TIMESTAMP),
+    FOREIGN KEY (DEVICE_NAME) REFERENCES DISK(DEVICE_NAME)
+);
+
+CREATE TABLE IF NOT EXISTS NETWORKINTERFACE (
+    NAME     TEXT NOT NULL,
+    IP_ADDR  TEXT,
+    PRIMARY KEY (NAME)
+);
+
+CREATE TABLE IF NOT EXISTS NETWORKSTAT (
+    NAME          TEXT NOT NULL,
+    TIMESTAMP     INT           CHECK(TIMESTAMP >= 0),
+    KB_RECV       INT  NOT NULL CHECK(KB_RECV >= 0),
+    KB_SENT       INT  NOT NULL CHECK(KB_SENT >= 0),
+    PACKETS_RECV  INT  NOT NULL CHECK(PACKETS_RECV >= 0),
+    PACKETS_SENT  INT  NOT NULL CHECK(PACKETS_SENT >= 0),
+    ERR_RECV      INT  NOT NULL CHECK(ERR_RECV >= 0),
+    ERR_SENT      INT  NOT NULL CHECK(ERR_SENT >= 0),
+    PRIMARY KEY (NAME, TIMESTAMP)
+    FOREIGN KEY (NAME) REFERENCES NETWORKINTERFACE(NAME)
+);
+
+CREATE TABLE IF NOT EXISTS COLLECTOR_HEALTH (
+    NAME                  TEXT NOT NULL,
+    INTERVAL              INT  NOT NULL CHECK(INTERVAL >= 0),
+    LAST_SUCCESS          INT           CHECK(LAST_SUCCESS >= 0),
+    LAST_FAILURE          INT           CHECK(LAST_FAILURE >= 0),
+    LAST_ERROR            TEXT,
+    CONSECUTIVE_FAILURES  INT  NOT NULL CHECK(CONSECUTIVE_FAILURES >= 0),
+    PRIMARY KEY (NAME)
+);
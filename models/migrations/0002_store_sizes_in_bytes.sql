@@ -0,0 +1,27 @@
+-- Stores all memory, disk, and network sizes in bytes
+--
+-- SQLite integers are already 64 bits, so only the units of the existing data
+-- have to change. Memory and network sizes were stored in KB (1000 bytes), and
+-- disk sizes were stored in MB (1024 * 1024 bytes).
+
+UPDATE PROCSTAT SET
+    VIRTUAL_MEMORY  = VIRTUAL_MEMORY * 1000,
+    RESIDENT_MEMORY = RESIDENT_MEMORY * 1000,
+    SHARED_MEMORY   = SHARED_MEMORY * 1000;
+
+UPDATE MEMORY SET
+    TOTAL      = TOTAL * 1000,
+    FREE       = FREE * 1000,
+    SWAP_TOTAL = SWAP_TOTAL * 1000,
+    SWAP_FREE  = SWAP_FREE * 1000;
+
+UPDATE DISKSTAT SET
+    USED      = USED * 1048576,
+    AVAILABLE = AVAILABLE * 1048576;
+
+UPDATE NETWORKSTAT SET
+    KB_RECV = KB_RECV * 1000,
+    KB_SENT = KB_SENT * 1000;
+
+ALTER TABLE NETWORKSTAT RENAME COLUMN KB_RECV TO BYTES_RECV;
+ALTER TABLE NETWORKSTAT RENAME COLUMN KB_SENT TO BYTES_SENT;
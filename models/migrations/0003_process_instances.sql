@@ -0,0 +1,56 @@
+-- Identifies processes by their PID and start time
+--
+-- PIDs get reused, so keying processes on the PID alone meant the history of
+-- the old process had to be deleted whenever its PID was recycled. SQLite
+-- cannot change the primary key of a table, so both process tables are rebuilt.
+
+CREATE TABLE PROCESS_NEW (
+    PID            INT    NOT NULL CHECK(PID >= 1),
+    EXEC           TEXT   NOT NULL,
+    START_TIME     INT    NOT NULL CHECK(START_TIME >= 0),
+    IS_ALIVE       INT    NOT NULL CHECK(IS_ALIVE IN (0, 1)),
+    INIT_TOTAL_CPU REAL   NOT NULL CHECK(INIT_TOTAL_CPU >= 0),
+    PRIMARY KEY (PID, START_TIME)
+);
+
+CREATE TABLE PROCSTAT_NEW (
+    PID              INT  NOT NULL,
+    START_TIME       INT  NOT NULL,
+    TIMESTAMP        INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    TOTAL_CPU        REAL NOT NULL CHECK(TOTAL_CPU >= 0),
+    PERCENT_CPU      REAL          CHECK(PERCENT_CPU >= 0),
+    CPU_CORE         INT,
+    VIRTUAL_MEMORY   INT  NOT NULL CHECK(VIRTUAL_MEMORY >= 0),
+    RESIDENT_MEMORY  INT  NOT NULL CHECK(RESIDENT_MEMORY >= 0),
+    SHARED_MEMORY    INT  NOT NULL CHECK(SHARED_MEMORY >= 0),
+    PRIMARY KEY (PID, START_TIME, TIMESTAMP),
+    FOREIGN KEY (PID, START_TIME) REFERENCES PROCESS_NEW(PID, START_TIME),
+    FOREIGN KEY (CPU_CORE)        REFERENCES CPU(CPU_CORE)
+);
+
+INSERT INTO PROCESS_NEW SELECT * FROM PROCESS;
+
+-- Every existing stat belongs to the only process that had its PID
+INSERT INTO PROCSTAT_NEW
+    SELECT
+        ps.PID,
+        p.START_TIME,
+        ps.TIMESTAMP,
+        ps.TOTAL_CPU,
+        ps.PERCENT_CPU,
+        ps.CPU_CORE,
+        ps.VIRTUAL_MEMORY,
+        ps.RESIDENT_MEMORY,
+        ps.SHARED_MEMORY
+    FROM PROCSTAT ps
+    INNER JOIN PROCESS p ON p.PID = ps.PID;
+
+DROP TABLE PROCSTAT;
+DROP TABLE PROCESS;
+
+-- Renaming also updates the foreign key of the new PROCSTAT table
+ALTER TABLE PROCESS_NEW RENAME TO PROCESS;
+ALTER TABLE PROCSTAT_NEW RENAME TO PROCSTAT;
+
+-- Every tick looks up the process stats by their timestamp
+CREATE INDEX PROCSTAT_TIMESTAMP ON PROCSTAT(TIMESTAMP);
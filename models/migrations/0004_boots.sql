@@ -0,0 +1,16 @@
+-- Records every boot of the system that the monitor has seen
+--
+-- Processes are tied to the boot they were running in. Processes from before
+-- boots were tracked are left without a boot unless the monitor finds that
+-- they started during the current boot.
+
+CREATE TABLE BOOT (
+    BOOT_ID       TEXT NOT NULL,
+    BOOT_TIME     INT  NOT NULL CHECK(BOOT_TIME >= 0),
+    FIRST_SAMPLE  INT           CHECK(FIRST_SAMPLE >= 0),
+    LAST_SAMPLE   INT           CHECK(LAST_SAMPLE >= 0),
+    DOWNTIME      INT           CHECK(DOWNTIME >= 0),
+    PRIMARY KEY (BOOT_ID)
+);
+
+ALTER TABLE PROCESS ADD COLUMN BOOT_ID TEXT REFERENCES BOOT(BOOT_ID);
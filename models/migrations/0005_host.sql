@@ -0,0 +1,24 @@
+-- Records the inventory of the host that the monitor is running on
+--
+-- There is only ever one host, so the table holds a single row that is
+-- updated whenever any of the host's details change.
+
+CREATE TABLE HOST (
+    ID              INT  NOT NULL CHECK(ID = 1),
+    HOSTNAME        TEXT NOT NULL,
+    KERNEL_RELEASE  TEXT NOT NULL,
+    OS_ID           TEXT,
+    OS_NAME         TEXT,
+    OS_VERSION      TEXT,
+    OS_PRETTY_NAME  TEXT,
+    CPU_MODEL       TEXT,
+    CPU_FLAGS       TEXT NOT NULL,
+    SOCKETS         INT  NOT NULL CHECK(SOCKETS >= 0),
+    CORES           INT  NOT NULL CHECK(CORES >= 0),
+    THREADS         INT  NOT NULL CHECK(THREADS >= 0),
+    TOTAL_MEMORY    INT  NOT NULL CHECK(TOTAL_MEMORY >= 0),
+    VIRTUALIZATION  TEXT,
+    CONTAINER       TEXT,
+    UPDATED_AT      INT  NOT NULL CHECK(UPDATED_AT >= 0),
+    PRIMARY KEY (ID)
+);
@@ -0,0 +1,52 @@
+-- Keeps the history of CPUs, disks, and network interfaces that go away
+--
+-- CPUs, mounts, and interfaces can come and go while the monitor is running,
+-- so instead of deleting everything about them once they are gone, they are
+-- marked as offline, unmounted, or missing until their metrics are pruned.
+--
+-- A device can also be mounted in several places, so disks are now identified
+-- by their device and mount point. SQLite cannot change the primary key of a
+-- table, so both disk tables are rebuilt.
+
+ALTER TABLE CPU ADD COLUMN IS_ONLINE INT NOT NULL DEFAULT 1 CHECK(IS_ONLINE IN (0, 1));
+
+ALTER TABLE NETWORKINTERFACE ADD COLUMN IS_PRESENT INT NOT NULL DEFAULT 1
+    CHECK(IS_PRESENT IN (0, 1));
+
+CREATE TABLE DISK_NEW (
+    DEVICE_NAME TEXT NOT NULL,
+    MOUNT       TEXT NOT NULL,
+    FS_TYPE     TEXT NOT NULL,
+    IS_MOUNTED  INT  NOT NULL CHECK(IS_MOUNTED IN (0, 1)),
+    PRIMARY KEY (DEVICE_NAME, MOUNT)
+);
+
+CREATE TABLE DISKSTAT_NEW (
+    DEVICE_NAME TEXT NOT NULL,
+    MOUNT       TEXT NOT NULL,
+    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    USED        INT  NOT NULL CHECK(USED >= 0),
+    AVAILABLE   INT  NOT NULL CHECK(AVAILABLE >= 0),
+    PRIMARY KEY (DEVICE_NAME, MOUNT, TIMESTAMP),
+    FOREIGN KEY (DEVICE_NAME, MOUNT) REFERENCES DISK_NEW(DEVICE_NAME, MOUNT)
+);
+
+INSERT INTO DISK_NEW SELECT DEVICE_NAME, MOUNT, FS_TYPE, 1 FROM DISK;
+
+-- Every existing stat belongs to the only mount that was recorded for its device
+INSERT INTO DISKSTAT_NEW
+    SELECT
+        ds.DEVICE_NAME,
+        d.MOUNT,
+        ds.TIMESTAMP,
+        ds.USED,
+        ds.AVAILABLE
+    FROM DISKSTAT ds
+    INNER JOIN DISK d ON d.DEVICE_NAME = ds.DEVICE_NAME;
+
+DROP TABLE DISKSTAT;
+DROP TABLE DISK;
+
+-- Renaming also updates the foreign key of the new DISKSTAT table
+ALTER TABLE DISK_NEW RENAME TO DISK;
+ALTER TABLE DISKSTAT_NEW RENAME TO DISKSTAT;
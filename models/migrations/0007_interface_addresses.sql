@@ -0,0 +1,25 @@
+-- Records the addresses and link details of each network interface
+--
+-- The IP address used to come from the ARP table, which holds the addresses
+-- of the interface's neighbours rather than its own. An interface can have any
+-- number of IPv4 and IPv6 addresses, so they are kept in their own table, and
+-- IP_ADDR is left as the interface's primary address.
+
+CREATE TABLE INTERFACEADDRESS (
+    NAME           TEXT NOT NULL,
+    ADDRESS        TEXT NOT NULL,
+    FAMILY         INT  NOT NULL CHECK(FAMILY IN (4, 6)),
+    PREFIX_LENGTH  INT  NOT NULL CHECK(PREFIX_LENGTH >= 0 AND PREFIX_LENGTH <= 128),
+    SCOPE          TEXT NOT NULL,
+    PRIMARY KEY (NAME, ADDRESS),
+    FOREIGN KEY (NAME) REFERENCES NETWORKINTERFACE(NAME)
+);
+
+ALTER TABLE NETWORKINTERFACE ADD COLUMN MAC_ADDRESS TEXT;
+ALTER TABLE NETWORKINTERFACE ADD COLUMN MTU INT CHECK(MTU >= 0);
+ALTER TABLE NETWORKINTERFACE ADD COLUMN OPERSTATE TEXT;
+ALTER TABLE NETWORKINTERFACE ADD COLUMN SPEED INT CHECK(SPEED >= 0);
+
+-- The old addresses belong to other hosts, so they are cleared until the
+-- monitor records the real ones
+UPDATE NETWORKINTERFACE SET IP_ADDR = NULL;
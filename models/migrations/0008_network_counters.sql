@@ -0,0 +1,68 @@
+-- Records every interface counter as the change since the previous sample
+--
+-- Only the byte, packet, and error totals used to be recorded, which hid the
+-- drops, FIFO overruns, and collisions that show a saturated interface. The
+-- totals also reset whenever an interface is recreated, so each sample now
+-- holds how much the counters went up since the last one.
+--
+-- The existing totals are turned into deltas, where the first sample of each
+-- interface has nothing to compare against and is dropped. The new counters
+-- were never recorded, so they are 0 for the existing samples.
+
+CREATE TABLE NETWORKSTAT_NEW (
+    NAME             TEXT NOT NULL,
+    TIMESTAMP        INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    BYTES_RECV       INT  NOT NULL CHECK(BYTES_RECV >= 0),
+    BYTES_SENT       INT  NOT NULL CHECK(BYTES_SENT >= 0),
+    PACKETS_RECV     INT  NOT NULL CHECK(PACKETS_RECV >= 0),
+    PACKETS_SENT     INT  NOT NULL CHECK(PACKETS_SENT >= 0),
+    ERR_RECV         INT  NOT NULL CHECK(ERR_RECV >= 0),
+    ERR_SENT         INT  NOT NULL CHECK(ERR_SENT >= 0),
+    DROP_RECV        INT  NOT NULL CHECK(DROP_RECV >= 0),
+    DROP_SENT        INT  NOT NULL CHECK(DROP_SENT >= 0),
+    FIFO_RECV        INT  NOT NULL CHECK(FIFO_RECV >= 0),
+    FIFO_SENT        INT  NOT NULL CHECK(FIFO_SENT >= 0),
+    FRAME_RECV       INT  NOT NULL CHECK(FRAME_RECV >= 0),
+    COMPRESSED_RECV  INT  NOT NULL CHECK(COMPRESSED_RECV >= 0),
+    COMPRESSED_SENT  INT  NOT NULL CHECK(COMPRESSED_SENT >= 0),
+    MULTICAST_RECV   INT  NOT NULL CHECK(MULTICAST_RECV >= 0),
+    COLLISIONS       INT  NOT NULL CHECK(COLLISIONS >= 0),
+    PRIMARY KEY (NAME, TIMESTAMP),
+    FOREIGN KEY (NAME) REFERENCES NETWORKINTERFACE(NAME)
+);
+
+-- A total that went down was reset, so everything since the reset is counted
+INSERT INTO NETWORKSTAT_NEW
+    SELECT
+        NAME,
+        TIMESTAMP,
+        CASE WHEN BYTES_RECV >= PREV_BYTES_RECV
+            THEN BYTES_RECV - PREV_BYTES_RECV ELSE BYTES_RECV END,
+        CASE WHEN BYTES_SENT >= PREV_BYTES_SENT
+            THEN BYTES_SENT - PREV_BYTES_SENT ELSE BYTES_SENT END,
+        CASE WHEN PACKETS_RECV >= PREV_PACKETS_RECV
+            THEN PACKETS_RECV - PREV_PACKETS_RECV ELSE PACKETS_RECV END,
+        CASE WHEN PACKETS_SENT >= PREV_PACKETS_SENT
+            THEN PACKETS_SENT - PREV_PACKETS_SENT ELSE PACKETS_SENT END,
+        CASE WHEN ERR_RECV >= PREV_ERR_RECV
+            THEN ERR_RECV - PREV_ERR_RECV ELSE ERR_RECV END,
+        CASE WHEN ERR_SENT >= PREV_ERR_SENT
+            THEN ERR_SENT - PREV_ERR_SENT ELSE ERR_SENT END,
+        0, 0, 0, 0, 0, 0, 0, 0, 0
+    FROM (
+        SELECT
+            *,
+            LAG(BYTES_RECV) OVER win AS PREV_BYTES_RECV,
+            LAG(BYTES_SENT) OVER win AS PREV_BYTES_SENT,
+            LAG(PACKETS_RECV) OVER win AS PREV_PACKETS_RECV,
+            LAG(PACKETS_SENT) OVER win AS PREV_PACKETS_SENT,
+            LAG(ERR_RECV) OVER win AS PREV_ERR_RECV,
+            LAG(ERR_SENT) OVER win AS PREV_ERR_SENT
+        FROM NETWORKSTAT
+        WINDOW win AS (PARTITION BY NAME ORDER BY TIMESTAMP)
+    )
+    WHERE TIMESTAMP IS NOT NULL AND PREV_BYTES_RECV IS NOT NULL;
+
+DROP TABLE NETWORKSTAT;
+
+ALTER TABLE NETWORKSTAT_NEW RENAME TO NETWORKSTAT;
@@ -0,0 +1,22 @@
+-- Records the kernel's network protocol counters from /proc/net/snmp and
+-- /proc/net/netstat
+--
+-- Like the interface counters, each sample holds how much the counters went up
+-- since the previous sample, along with the number of seconds between the two
+-- so that they can be turned into rates.
+
+CREATE TABLE PROTOCOLSTAT (
+    TIMESTAMP             INT NOT NULL CHECK(TIMESTAMP >= 0),
+    ELAPSED               INT NOT NULL CHECK(ELAPSED > 0),
+    TCP_RETRANS_SEGS      INT NOT NULL CHECK(TCP_RETRANS_SEGS >= 0),
+    TCP_OUT_RSTS          INT NOT NULL CHECK(TCP_OUT_RSTS >= 0),
+    TCP_ESTAB_RESETS      INT NOT NULL CHECK(TCP_ESTAB_RESETS >= 0),
+    TCP_ACTIVE_OPENS      INT NOT NULL CHECK(TCP_ACTIVE_OPENS >= 0),
+    TCP_PASSIVE_OPENS     INT NOT NULL CHECK(TCP_PASSIVE_OPENS >= 0),
+    TCP_LISTEN_OVERFLOWS  INT NOT NULL CHECK(TCP_LISTEN_OVERFLOWS >= 0),
+    TCP_LISTEN_DROPS      INT NOT NULL CHECK(TCP_LISTEN_DROPS >= 0),
+    UDP_RCVBUF_ERRORS     INT NOT NULL CHECK(UDP_RCVBUF_ERRORS >= 0),
+    UDP_SNDBUF_ERRORS     INT NOT NULL CHECK(UDP_SNDBUF_ERRORS >= 0),
+    IP_REASM_FAILS        INT NOT NULL CHECK(IP_REASM_FAILS >= 0),
+    PRIMARY KEY (TIMESTAMP)
+);
@@ -0,0 +1,25 @@
+-- Tracks inode usage and mount options for each disk
+--
+-- A file system can run out of inodes long before it runs out of space, so the
+-- inode counts are recorded next to the space used. They were never recorded
+-- before, so they are unknown for the existing stats.
+--
+-- A file system that the kernel remounts read-only has usually hit errors, so
+-- every time a mounted disk turns read-only an event is recorded.
+
+ALTER TABLE DISK ADD COLUMN MOUNT_OPTIONS TEXT NOT NULL DEFAULT '';
+ALTER TABLE DISK ADD COLUMN IS_READ_ONLY INT NOT NULL DEFAULT 0 CHECK(IS_READ_ONLY IN (0, 1));
+
+ALTER TABLE DISKSTAT ADD COLUMN INODES_TOTAL INT CHECK(INODES_TOTAL >= 0);
+ALTER TABLE DISKSTAT ADD COLUMN INODES_USED INT CHECK(INODES_USED >= 0);
+ALTER TABLE DISKSTAT ADD COLUMN INODES_FREE INT CHECK(INODES_FREE >= 0);
+
+CREATE TABLE DISKEVENT (
+    DEVICE_NAME    TEXT NOT NULL,
+    MOUNT          TEXT NOT NULL,
+    TIMESTAMP      INT  NOT NULL CHECK(TIMESTAMP >= 0),
+    EVENT          TEXT NOT NULL CHECK(EVENT IN ('remounted_read_only')),
+    MOUNT_OPTIONS  TEXT NOT NULL,
+    PRIMARY KEY (DEVICE_NAME, MOUNT, TIMESTAMP, EVENT),
+    FOREIGN KEY (DEVICE_NAME, MOUNT) REFERENCES DISK(DEVICE_NAME, MOUNT)
+);
@@ -0,0 +1,8 @@
+-- Records the user that owns each process
+--
+-- The process list can be filtered by user, so the UID of every process is
+-- stored along with the name it had in the host's passwd file when the process
+-- was first seen. Both are unknown for the existing processes.
+
+ALTER TABLE PROCESS ADD COLUMN UID INT CHECK(UID >= 0);
+ALTER TABLE PROCESS ADD COLUMN USER_NAME TEXT;
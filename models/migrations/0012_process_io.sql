@@ -0,0 +1,8 @@
+-- Records how much each process has read from and written to storage
+--
+-- Like the total CPU time, these are the running totals from /proc/<pid>/io, so
+-- the I/O rate of a process is the change between two of its stats. They are
+-- unknown for the existing stats and for processes the monitor cannot inspect.
+
+ALTER TABLE PROCSTAT ADD COLUMN IO_READ_BYTES INT CHECK(IO_READ_BYTES >= 0);
+ALTER TABLE PROCSTAT ADD COLUMN IO_WRITE_BYTES INT CHECK(IO_WRITE_BYTES >= 0);
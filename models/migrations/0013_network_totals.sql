@@ -0,0 +1,16 @@
+-- Records the kernel's running totals of the exported interface counters
+--
+-- The changes between samples cannot be added back up into totals, since old
+-- samples are pruned and the first sample of each interface is never recorded.
+-- The totals from /proc/net/dev are kept next to the changes, so that they can
+-- be exported as counters that only reset when the kernel's do. They are
+-- unknown for the existing samples.
+
+ALTER TABLE NETWORKSTAT ADD COLUMN BYTES_RECV_TOTAL INT CHECK(BYTES_RECV_TOTAL >= 0);
+ALTER TABLE NETWORKSTAT ADD COLUMN BYTES_SENT_TOTAL INT CHECK(BYTES_SENT_TOTAL >= 0);
+ALTER TABLE NETWORKSTAT ADD COLUMN PACKETS_RECV_TOTAL INT CHECK(PACKETS_RECV_TOTAL >= 0);
+ALTER TABLE NETWORKSTAT ADD COLUMN PACKETS_SENT_TOTAL INT CHECK(PACKETS_SENT_TOTAL >= 0);
+ALTER TABLE NETWORKSTAT ADD COLUMN ERR_RECV_TOTAL INT CHECK(ERR_RECV_TOTAL >= 0);
+ALTER TABLE NETWORKSTAT ADD COLUMN ERR_SENT_TOTAL INT CHECK(ERR_SENT_TOTAL >= 0);
+ALTER TABLE NETWORKSTAT ADD COLUMN DROP_RECV_TOTAL INT CHECK(DROP_RECV_TOTAL >= 0);
+ALTER TABLE NETWORKSTAT ADD COLUMN DROP_SENT_TOTAL INT CHECK(DROP_SENT_TOTAL >= 0);
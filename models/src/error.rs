@@ -1,5 +1,8 @@
 use procfs::ProcError;
 
+use std::fmt;
+use std::io;
+
 /// Enum for representing the different errors within the monitor
 #[derive(Debug)]
 pub enum NebulaError {
@@ -7,8 +10,26 @@ pub enum NebulaError {
     Procfs(ProcError),
     /// Error from the sqlx crate
     Sql(sqlx::Error),
+    /// Error from reading files or running commands
+    Io(io::Error),
+    /// System data that was not in the expected format
+    Parse(String),
+}
+
+impl fmt::Display for NebulaError {
+    /// Formats the error with the source it came from
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NebulaError::Procfs(err) => write!(f, "procfs error: {}", err),
+            NebulaError::Sql(err) => write!(f, "database error: {}", err),
+            NebulaError::Io(err) => write!(f, "io error: {}", err),
+            NebulaError::Parse(msg) => write!(f, "parse error: {}", msg),
+        }
+    }
 }
 
+impl std::error::Error for NebulaError {}
+
 impl From<ProcError> for NebulaError {
     /// Converts from ProcError to NebulaError::Procfs
     fn from(item: ProcError) -> Self {
@@ -22,3 +43,10 @@ impl From<sqlx::Error> for NebulaError {
         NebulaError::Sql(item)
     }
 }
+
+impl From<io::Error> for NebulaError {
+    /// Converts from io::Error to NebulaError::Io
+    fn from(item: io::Error) -> Self {
+        NebulaError::Io(item)
+    }
+}
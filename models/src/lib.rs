@@ -1,2 +1,8 @@
 pub mod error;
 pub mod tables;
+
+use sqlx::migrate::Migrator;
+
+/// Versioned migrations for the database schema, which are embedded into the
+/// binary and applied by the monitor at start up
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
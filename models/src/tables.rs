@@ -15,6 +15,15 @@ pub struct Process {
     pub is_alive: bool,
     /// Amount of CPU time in seconds the process has on the first encounter
     pub init_total_cpu: f32,
+    /// Boot the process was running in, which is unknown for processes
+    /// recorded before boots were tracked
+    pub boot_id: Option<String>,
+    /// UID of the user that owns the process, which is unknown for processes
+    /// recorded before owners were tracked
+    pub uid: Option<u32>,
+    /// Name of the user that owns the process, if the UID is in the host's
+    /// passwd file
+    pub user_name: Option<String>,
 }
 
 /// Struct for the CPU table
@@ -27,6 +36,8 @@ pub struct Cpu {
     pub mhz: f32,
     /// Amount of cache in MB
     pub total_cache: u32,
+    /// Whether or not the core is currently online
+    pub is_online: bool,
 }
 
 /// Struct for the PROCSTAT table
@@ -35,6 +46,8 @@ pub struct Cpu {
 pub struct ProcStat {
     /// PID of the process
     pub pid: u32,
+    /// Start time of the process, which tells apart processes with the same PID
+    pub start_time: i64,
     /// Unix epoch timestamp at which the entry was recorded
     pub timestamp: i64,
     /// Total CPU time for the process in seconds
@@ -43,12 +56,16 @@ pub struct ProcStat {
     pub percent_cpu: Option<f32>,
     /// CPU core the process is running on
     pub cpu_core: Option<u32>,
-    /// Amount of virtual memory for the process in KB
-    pub virtual_memory: u32,
-    /// Amount of space the process actively has in memory in KB
-    pub resident_memory: u32,
-    /// Amount of memory the process is sharing with other processes in KB
-    pub shared_memory: u32,
+    /// Amount of virtual memory for the process in bytes
+    pub virtual_memory: i64,
+    /// Amount of space the process actively has in memory in bytes
+    pub resident_memory: i64,
+    /// Amount of memory the process is sharing with other processes in bytes
+    pub shared_memory: i64,
+    /// Total bytes the process has read from storage, if it could be inspected
+    pub io_read_bytes: Option<i64>,
+    /// Total bytes the process has written to storage, if it could be inspected
+    pub io_write_bytes: Option<i64>,
 }
 
 /// Struct for the CPUSTAT table
@@ -69,14 +86,14 @@ pub struct CpuStat {
 pub struct Memory {
     /// Unix epoch timestamp at which the entry was recorded
     pub timestamp: i64,
-    /// Total amount of memory in KB
-    pub total: u32,
-    /// Amount of memory that is free in KB
-    pub free: u32,
-    /// Total amount of swap space in KB
-    pub swap_total: u32,
-    /// Amount of swap space that is free in KB
-    pub swap_free: u32,
+    /// Total amount of memory in bytes
+    pub total: i64,
+    /// Amount of memory that is free in bytes
+    pub free: i64,
+    /// Total amount of swap space in bytes
+    pub swap_total: i64,
+    /// Amount of swap space that is free in bytes
+    pub swap_free: i64,
 }
 
 /// Struct for the DISK table
@@ -89,6 +106,12 @@ pub struct Disk {
     pub mount: String,
     /// Type of file system used by the disk
     pub fs_type: String,
+    /// Whether or not the device is currently mounted there
+    pub is_mounted: bool,
+    /// Comma separated options the device is mounted with
+    pub mount_options: String,
+    /// Whether or not the device is mounted read-only
+    pub is_read_only: bool,
 }
 
 /// Struct for the DISKSTAT table
@@ -97,12 +120,36 @@ pub struct Disk {
 pub struct DiskStat {
     /// Name of the device
     pub device_name: String,
+    /// Folder the device is mounted to
+    pub mount: String,
     /// Unix epoch timestamp at which the entry was recorded
     pub timestamp: i64,
-    /// Amount of disk space used in MB
-    pub used: u32,
-    /// Amount of disk space available in MB
-    pub available: u32,
+    /// Amount of disk space used in bytes
+    pub used: i64,
+    /// Amount of disk space available in bytes
+    pub available: i64,
+    /// Total number of inodes, if it was recorded
+    pub inodes_total: Option<i64>,
+    /// Number of inodes in use, if it was recorded
+    pub inodes_used: Option<i64>,
+    /// Number of free inodes, if it was recorded
+    pub inodes_free: Option<i64>,
+}
+
+/// Struct for the DISKEVENT table
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct DiskEvent {
+    /// Name of the device
+    pub device_name: String,
+    /// Folder the device is mounted to
+    pub mount: String,
+    /// Unix epoch timestamp at which the event was noticed
+    pub timestamp: i64,
+    /// What happened to the disk (ex. remounted_read_only)
+    pub event: String,
+    /// Comma separated options the device was mounted with afterwards
+    pub mount_options: String,
 }
 
 /// Struct for the NETWORKINTERFACE table
@@ -111,11 +158,40 @@ pub struct DiskStat {
 pub struct NetworkInterface {
     /// Logical name of the interface
     pub name: String,
-    /// IP address of the interface
+    /// Primary IP address of the interface, preferring global IPv4 addresses
     pub ip_addr: Option<String>,
+    /// Whether or not the interface currently exists
+    pub is_present: bool,
+    /// Hardware address of the interface
+    pub mac_address: Option<String>,
+    /// Maximum transmission unit in bytes
+    pub mtu: Option<i64>,
+    /// Operational state of the interface (ex. up, down, or unknown)
+    pub operstate: Option<String>,
+    /// Link speed in Mbps, which is unknown for virtual interfaces and links
+    /// that are down
+    pub speed: Option<i64>,
 }
 
-/// Struct for the NETWORKSTAT table
+/// Struct for the INTERFACEADDRESS table
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct InterfaceAddress {
+    /// Logical name of the interface
+    pub name: String,
+    /// IPv4 or IPv6 address assigned to the interface
+    pub address: String,
+    /// IP version of the address (4 or 6)
+    pub family: i64,
+    /// Number of bits in the network prefix
+    pub prefix_length: i64,
+    /// Where the address is valid (global, site, link, or host)
+    pub scope: String,
+}
+
+/// Struct for the NETWORKSTAT table, where every counter is the change since
+/// the previous sample of the interface, next to the kernel's running totals
+/// of the exported ones
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 #[sqlx(rename_all = "UPPERCASE")]
 pub struct NetworkStat {
@@ -123,16 +199,155 @@ pub struct NetworkStat {
     pub name: String,
     /// Unix epoch timestamp at which the entry was recorded
     pub timestamp: i64,
-    /// Change in total KB received from the last record
-    pub kb_recv: u32,
-    /// Change in total KB sent from the last record
-    pub kb_sent: u32,
-    /// Change in total packets received from the last record
-    pub packets_recv: u32,
-    /// Change in total packets sent from the last record
-    pub packets_sent: u32,
-    /// Change in total transmission errors received from the last record
-    pub err_recv: u32,
-    /// Change in total transmission errors sent from the last record
-    pub err_sent: u32,
+    /// Bytes received
+    pub bytes_recv: i64,
+    /// Bytes sent
+    pub bytes_sent: i64,
+    /// Packets received
+    pub packets_recv: i64,
+    /// Packets sent
+    pub packets_sent: i64,
+    /// Receive errors
+    pub err_recv: i64,
+    /// Transmit errors
+    pub err_sent: i64,
+    /// Received packets that were dropped
+    pub drop_recv: i64,
+    /// Packets that were dropped before being sent
+    pub drop_sent: i64,
+    /// Receive FIFO buffer overruns
+    pub fifo_recv: i64,
+    /// Transmit FIFO buffer overruns
+    pub fifo_sent: i64,
+    /// Received packets with framing errors
+    pub frame_recv: i64,
+    /// Compressed packets received
+    pub compressed_recv: i64,
+    /// Compressed packets sent
+    pub compressed_sent: i64,
+    /// Multicast packets received
+    pub multicast_recv: i64,
+    /// Collisions while sending
+    pub collisions: i64,
+    /// Total bytes received, which is unknown for samples recorded before
+    /// totals were kept
+    pub bytes_recv_total: Option<i64>,
+    /// Total bytes sent
+    pub bytes_sent_total: Option<i64>,
+    /// Total packets received
+    pub packets_recv_total: Option<i64>,
+    /// Total packets sent
+    pub packets_sent_total: Option<i64>,
+    /// Total receive errors
+    pub err_recv_total: Option<i64>,
+    /// Total transmit errors
+    pub err_sent_total: Option<i64>,
+    /// Total received packets that were dropped
+    pub drop_recv_total: Option<i64>,
+    /// Total packets that were dropped before being sent
+    pub drop_sent_total: Option<i64>,
+}
+
+/// Struct for the PROTOCOLSTAT table, where every counter is the change since
+/// the previous sample
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct ProtocolStat {
+    /// Unix epoch timestamp at which the entry was recorded
+    pub timestamp: i64,
+    /// Number of seconds since the previous sample
+    pub elapsed: i64,
+    /// TCP segments that were retransmitted
+    pub tcp_retrans_segs: i64,
+    /// TCP segments sent with the RST flag
+    pub tcp_out_rsts: i64,
+    /// Established TCP connections that were reset
+    pub tcp_estab_resets: i64,
+    /// TCP connections opened by the host
+    pub tcp_active_opens: i64,
+    /// TCP connections opened by a peer
+    pub tcp_passive_opens: i64,
+    /// Times a TCP listen queue was full
+    pub tcp_listen_overflows: i64,
+    /// TCP connection requests that were dropped while listening
+    pub tcp_listen_drops: i64,
+    /// UDP datagrams dropped because the receive buffer was full
+    pub udp_rcvbuf_errors: i64,
+    /// UDP datagrams dropped because the send buffer was full
+    pub udp_sndbuf_errors: i64,
+    /// IP packets that could not be reassembled from their fragments
+    pub ip_reasm_fails: i64,
+}
+
+/// Struct for the COLLECTOR_HEALTH table
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct CollectorHealth {
+    /// Name of the collector
+    pub name: String,
+    /// Number of seconds between runs of the collector when it is healthy
+    pub interval: u32,
+    /// Unix epoch timestamp of the last successful run
+    pub last_success: Option<i64>,
+    /// Unix epoch timestamp of the last failed run
+    pub last_failure: Option<i64>,
+    /// Error message from the last failed run
+    pub last_error: Option<String>,
+    /// Number of failed runs since the last successful run
+    pub consecutive_failures: u32,
+}
+
+/// Struct for the BOOT table
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct Boot {
+    /// Random ID the kernel generated for the boot
+    pub boot_id: String,
+    /// Unix epoch timestamp that the system booted at
+    pub boot_time: i64,
+    /// Unix epoch timestamp of the monitor's first sample during the boot
+    pub first_sample: Option<i64>,
+    /// Unix epoch timestamp of the monitor's last sample during the boot
+    pub last_sample: Option<i64>,
+    /// Number of seconds between the last sample of the previous boot and
+    /// this boot, if there was a previous boot
+    pub downtime: Option<i64>,
+}
+
+/// Struct for the HOST table
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct Host {
+    /// Always 1, since the table only holds the current host
+    pub id: i64,
+    /// Name of the host
+    pub hostname: String,
+    /// Release of the running kernel (ex. 6.1.0-18-amd64)
+    pub kernel_release: String,
+    /// ID of the distribution from os-release (ex. debian)
+    pub os_id: Option<String>,
+    /// Name of the distribution from os-release
+    pub os_name: Option<String>,
+    /// Version of the distribution from os-release
+    pub os_version: Option<String>,
+    /// Human readable name and version of the distribution from os-release
+    pub os_pretty_name: Option<String>,
+    /// Model name of the CPU
+    pub cpu_model: Option<String>,
+    /// Space separated feature flags of the CPU
+    pub cpu_flags: String,
+    /// Number of physical CPU packages
+    pub sockets: i64,
+    /// Number of physical cores across all sockets
+    pub cores: i64,
+    /// Number of hardware threads (logical CPUs)
+    pub threads: i64,
+    /// Total amount of memory in bytes
+    pub total_memory: i64,
+    /// Hypervisor the host is running on, if it is a virtual machine
+    pub virtualization: Option<String>,
+    /// Container runtime the monitor is running in, if any
+    pub container: Option<String>,
+    /// Unix epoch timestamp of the last time any of the host details changed
+    pub updated_at: i64,
 }
@@ -0,0 +1,139 @@
+//! Times full monitor ticks on a host with many processes
+//!
+//! The recorded system from the test fixtures is copied along with thousands
+//! of made up processes, and the monitor collects and persists it a few times
+//! like it would on a busy host. Run it with `cargo bench -p monitor`.
+
+use monitor::{Monitor, SystemRoot, TICK_INTERVAL};
+use sqlx::SqlitePool;
+use tokio::runtime::Runtime;
+use tracing::span::Id;
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of processes on the benchmarked host
+const NUM_PROCESSES: u32 = 10_000;
+/// Number of ticks that are timed
+const NUM_TICKS: usize = 5;
+/// First PID of the made up processes, which is above the fixture's
+const FIRST_PID: u32 = 1000;
+
+/// Copies a directory with everything in it, keeping symbolic links as links
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry: fs::DirEntry = entry?;
+        let file_type: fs::FileType = entry.file_type()?;
+        let target: PathBuf = to.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            symlink(fs::read_link(entry.path())?, &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the procfs files of a made up process, based on the fixture's
+/// process 42
+fn write_process(proc_dir: &Path, pid: u32, stat: &str, statm: &str, io: &str) -> io::Result<()> {
+    let process_dir: PathBuf = proc_dir.join(pid.to_string());
+    fs::create_dir_all(&process_dir)?;
+    fs::write(
+        process_dir.join("stat"),
+        stat.replacen("42 (test-exe)", &format!("{} (bench-{})", pid, pid % 50), 1),
+    )?;
+    fs::write(process_dir.join("statm"), statm)?;
+    fs::write(process_dir.join("io"), io)?;
+    symlink(
+        format!("/usr/bin/bench-{}", pid % 50),
+        process_dir.join("exe"),
+    )
+}
+
+/// Creates a copy of the fixture system with many processes in it
+fn create_system(bench_dir: &Path) -> io::Result<SystemRoot> {
+    let fixture_dir: PathBuf =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/monitor/fixtures/system");
+    copy_dir(&fixture_dir, bench_dir)?;
+
+    let root: SystemRoot = SystemRoot {
+        proc: bench_dir.join("proc"),
+        sys: bench_dir.join("sys"),
+        host: bench_dir.join("host"),
+    };
+    let template_dir: PathBuf = root.proc_path("42");
+    let stat: String = fs::read_to_string(template_dir.join("stat"))?;
+    let statm: String = fs::read_to_string(template_dir.join("statm"))?;
+    let io: String = fs::read_to_string(template_dir.join("io"))?;
+    for pid in FIRST_PID..FIRST_PID + NUM_PROCESSES {
+        write_process(&root.proc, pid, &stat, &statm, &io)?;
+    }
+    Ok(root)
+}
+
+/// Counts the process stats that have been recorded
+async fn count_proc_stats(db_file: &Path) -> Result<i64, sqlx::Error> {
+    let pool: SqlitePool = SqlitePool::connect(&format!("sqlite://{}", db_file.display())).await?;
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM PROCSTAT;")
+        .fetch_one(&pool)
+        .await?;
+    pool.close().await;
+    Ok(count)
+}
+
+fn main() {
+    let bench_dir: PathBuf =
+        std::env::temp_dir().join(format!("nebula-bench-tick-{}", process::id()));
+    let _ = fs::remove_dir_all(&bench_dir);
+    let root: SystemRoot =
+        create_system(&bench_dir.join("system")).expect("System should be created");
+    let db_file: PathBuf = bench_dir.join("nebulaMetrics.db");
+
+    let runtime: Runtime = Runtime::new().expect("Runtime should start");
+    let mut monitor: Monitor = runtime
+        .block_on(Monitor::open(&db_file, root))
+        .expect("Monitor should be created");
+    runtime.block_on(monitor.setup_init_data());
+
+    let mut durations: Vec<Duration> = Vec::with_capacity(NUM_TICKS);
+    for _ in 0..NUM_TICKS {
+        // Stats are keyed by the second they were recorded in, so ticks have to
+        // be at least a second apart
+        thread::sleep(Duration::from_millis(1100));
+        let start: Instant = Instant::now();
+        runtime.block_on(monitor.update(Id::from_u64(1)));
+        durations.push(start.elapsed());
+    }
+
+    // A failing collector is only logged, so make sure every tick was recorded
+    let proc_stats: i64 = runtime
+        .block_on(count_proc_stats(&db_file))
+        .expect("Process stats should be counted");
+    assert!(
+        proc_stats >= NUM_TICKS as i64 * NUM_PROCESSES as i64,
+        "Only {} process stats were recorded",
+        proc_stats
+    );
+    fs::remove_dir_all(&bench_dir).expect("Bench directory should be removed");
+
+    durations.sort();
+    let median: Duration = durations[durations.len() / 2];
+    println!(
+        "tick with {} processes: min {:?}, median {:?}, max {:?} ({:.1}% of the {:?} interval)",
+        NUM_PROCESSES,
+        durations[0],
+        median,
+        durations[durations.len() - 1],
+        median.as_secs_f64() / TICK_INTERVAL.as_secs_f64() * 100.0,
+        TICK_INTERVAL
+    );
+}
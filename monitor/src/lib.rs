@@ -0,0 +1,5 @@
+//! Polls the system's metrics and records them in the database that the
+//! server reads from
+mod monitor;
+
+pub use monitor::{Monitor, SystemRoot, TICK_INTERVAL};
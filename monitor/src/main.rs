@@ -1,5 +1,4 @@
-mod monitor;
-use monitor::Monitor;
+use monitor::{Monitor, SystemRoot, TICK_INTERVAL};
 
 extern crate tokio;
 use tokio::sync::Mutex;
@@ -21,18 +20,24 @@ async fn main() {
         .with_max_level(Level::TRACE)
         .init();
 
-    let monitor: Monitor = Monitor::new().await.expect("Monitor should be created");
-    monitor
-        .setup_init_data()
-        .await
-        .expect("Data should be initialized");
+    let root: SystemRoot = SystemRoot::from_env();
+    event!(
+        Level::INFO,
+        "Reading system data from procfs at {:?}, sysfs at {:?}, and the host at {:?}",
+        root.proc,
+        root.sys,
+        root.host
+    );
+
+    let mut monitor: Monitor = Monitor::new(root).await.expect("Monitor should be created");
+    monitor.setup_init_data().await;
 
     let mut task_set = JoinSet::new();
     let shared_monitor: Arc<Mutex<Monitor>> = Arc::new(Mutex::new(monitor));
 
     let monitor_update_mutex: Arc<Mutex<Monitor>> = Arc::clone(&shared_monitor);
     task_set.spawn(async move {
-        let mut interval = interval(Duration::from_secs(4));
+        let mut interval = interval(TICK_INTERVAL);
         // In the event that a tick takes longer than the duration, then we
         // should reschedule future ticks to be based off this time
         interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
@@ -0,0 +1,257 @@
+use async_trait::async_trait;
+use sqlx::SqliteConnection;
+use std::fs;
+use tracing::{event, instrument, Level};
+
+use models::error::NebulaError;
+
+use super::collector::Collector;
+use super::system::SystemRoot;
+
+/// Collector that keeps track of the boots of the system and when the monitor
+/// was sampling during each of them
+#[derive(Debug, Default)]
+pub struct BootCollector {
+    /// Where to read the boot information from
+    root: SystemRoot,
+    /// ID of the current boot once it has been recorded
+    boot_id: Option<String>,
+}
+
+impl BootCollector {
+    /// Creates a new boot collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        BootCollector {
+            root,
+            boot_id: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for BootCollector {
+    fn name(&self) -> &'static str {
+        "boot"
+    }
+
+    /// Records the current boot if it has not been seen before, along with
+    /// how long the system was down since the previous boot
+    #[instrument(skip(self, conn))]
+    async fn init(&mut self, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to initialize boot data");
+        let boot_id: String = get_boot_id(&self.root)?;
+        let boot_time: u64 = get_boot_time(&self.root)?;
+
+        // The downtime is NULL when there is no previous boot to compare to
+        sqlx::query(
+            "INSERT INTO BOOT (BOOT_ID, BOOT_TIME, DOWNTIME)
+                SELECT ?1, ?2, MAX(?2 - MAX(LAST_SAMPLE), 0) FROM BOOT WHERE BOOT_ID != ?1
+                ON CONFLICT (BOOT_ID) DO NOTHING;",
+        )
+        .bind(&boot_id)
+        .bind(boot_time as i64)
+        .execute(&mut *conn)
+        .await?;
+
+        // Processes recorded before boots were tracked that started after the
+        // system booted have to be from the current boot
+        sqlx::query("UPDATE PROCESS SET BOOT_ID = ? WHERE BOOT_ID IS NULL AND START_TIME >= ?;")
+            .bind(&boot_id)
+            .bind(boot_time as i64)
+            .execute(&mut *conn)
+            .await?;
+
+        event!(Level::INFO, "Current boot is {:?}", boot_id);
+        self.boot_id = Some(boot_id);
+        Ok(())
+    }
+
+    /// The boot does not change while the monitor is running, so there is
+    /// nothing to gather from the system
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        Ok(())
+    }
+
+    /// Marks the current time as the latest sample of the current boot
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        let boot_id: &String = match &self.boot_id {
+            Some(boot_id) => boot_id,
+            None => {
+                event!(Level::WARN, "The current boot has not been recorded");
+                return Ok(());
+            }
+        };
+
+        sqlx::query(
+            "UPDATE BOOT SET FIRST_SAMPLE = COALESCE(FIRST_SAMPLE, ?1), LAST_SAMPLE = ?1
+                WHERE BOOT_ID = ?2;",
+        )
+        .bind(cur_time as i64)
+        .bind(boot_id)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes the boots that ended before the cutoff once none of their
+    /// processes are left
+    #[instrument(skip(self, conn))]
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        sqlx::query(
+            "DELETE FROM BOOT WHERE COALESCE(LAST_SAMPLE, BOOT_TIME) < ?
+                AND NOT EXISTS (SELECT 1 FROM PROCESS p WHERE p.BOOT_ID = BOOT.BOOT_ID);",
+        )
+        .bind(cutoff as i64)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Gets the random ID that the kernel generated for the current boot
+pub fn get_boot_id(root: &SystemRoot) -> Result<String, NebulaError> {
+    let boot_id: String = fs::read_to_string(root.proc_path("sys/kernel/random/boot_id"))?;
+    Ok(boot_id.trim().to_string())
+}
+
+/// Gets the Unix epoch timestamp that the system booted at from /proc/stat
+pub fn get_boot_time(root: &SystemRoot) -> Result<u64, NebulaError> {
+    let stat: String = fs::read_to_string(root.proc_path("stat"))?;
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|btime| btime.trim().parse::<u64>().ok())
+        .ok_or_else(|| NebulaError::Parse("Missing btime in /proc/stat".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::tables::{Boot, Process};
+    use sqlx::SqlitePool;
+    use std::io;
+
+    #[test]
+    fn test_get_boot_info() -> Result<(), NebulaError> {
+        assert_eq!(
+            get_boot_id(&SystemRoot::fixture())?,
+            "3f2a6c1e-8d4b-4c6e-9a1f-5b7d2e0c4a91"
+        );
+        assert_eq!(get_boot_time(&SystemRoot::fixture())?, 1700000000);
+        assert!(get_boot_id(&SystemRoot::with_host_root("/does/not/exist")).is_err());
+        assert!(get_boot_time(&SystemRoot::with_host_root("/does/not/exist")).is_err());
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_init_boot_data(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // The previous boot was last sampled 100 seconds before the current boot
+        sqlx::query(
+            "INSERT INTO BOOT VALUES (\"old-boot\", 1600000000, 1600000010, 1699999900, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+        // Processes from before boots were tracked
+        sqlx::query(
+            "INSERT INTO PROCESS VALUES (1, \"old-exe\", 1600000001, 0, 1, NULL, NULL, NULL);
+            INSERT INTO PROCESS VALUES (2, \"new-exe\", 1700000001, 1, 1, NULL, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+
+        let mut collector: BootCollector = BootCollector::new(SystemRoot::fixture());
+        collector.init(&mut *pool.acquire().await?).await?;
+        // Initializing again for the same boot does not change anything
+        collector.init(&mut *pool.acquire().await?).await?;
+
+        let boots: Vec<Boot> = sqlx::query_as::<_, Boot>("SELECT * FROM BOOT ORDER BY BOOT_TIME;")
+            .fetch_all(&pool)
+            .await?;
+        assert_eq!(boots.len(), 2);
+        assert_eq!(boots[0].downtime, None);
+        assert_eq!(boots[1].boot_id, "3f2a6c1e-8d4b-4c6e-9a1f-5b7d2e0c4a91");
+        assert_eq!(boots[1].boot_time, 1700000000);
+        assert_eq!(boots[1].downtime, Some(100));
+        assert_eq!(boots[1].first_sample, None);
+
+        // Only the process that started during the current boot is tied to it
+        let processes: Vec<Process> =
+            sqlx::query_as::<_, Process>("SELECT * FROM PROCESS ORDER BY PID;")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(processes[0].boot_id, None);
+        assert_eq!(
+            processes[1].boot_id.as_deref(),
+            Some("3f2a6c1e-8d4b-4c6e-9a1f-5b7d2e0c4a91")
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_update_boot_data(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let mut collector: BootCollector = BootCollector::new(SystemRoot::fixture());
+        collector.init(&mut *pool.acquire().await?).await?;
+        collector.collect()?;
+        collector
+            .persist(1700000100, &mut *pool.acquire().await?)
+            .await?;
+        collector.collect()?;
+        collector
+            .persist(1700000104, &mut *pool.acquire().await?)
+            .await?;
+
+        let boot: Boot = sqlx::query_as::<_, Boot>("SELECT * FROM BOOT;")
+            .fetch_one(&pool)
+            .await?;
+        // The first sample stays put while the last sample moves forward
+        assert_eq!(boot.first_sample, Some(1700000100));
+        assert_eq!(boot.last_sample, Some(1700000104));
+        assert_eq!(boot.downtime, None);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_prune_boot_data(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        sqlx::query(
+            "INSERT INTO BOOT VALUES (\"ended\", 100, 110, 200, NULL);
+            INSERT INTO BOOT VALUES (\"ended-with-process\", 300, 310, 400, 100);
+            INSERT INTO BOOT VALUES (\"current\", 500, 510, 600, 100);
+            INSERT INTO PROCESS VALUES (1, \"test-exe\", 301, 0, 1, \"ended-with-process\", NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+
+        BootCollector::new(SystemRoot::fixture())
+            .prune(500, &mut *pool.acquire().await?)
+            .await?;
+
+        let boot_ids: Vec<String> =
+            sqlx::query_scalar("SELECT BOOT_ID FROM BOOT ORDER BY BOOT_TIME;")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(boot_ids, vec!["ended-with-process", "current"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+use async_trait::async_trait;
+use models::error::NebulaError;
+use sqlx::{Connection, Sqlite, SqliteConnection, Transaction};
+use tracing::{event, instrument, Level};
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Amount of time between each tick of the monitor
+pub const TICK_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Longest amount of time a failing collector waits before being retried
+pub const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Most rows to write with a single batched statement, which keeps the number
+/// of bound parameters under SQLite's limit
+pub const MAX_ROWS_PER_STATEMENT: usize = 1000;
+
+/// Common interface for every source of metrics that the monitor polls
+///
+/// The monitor calls `init` once at start up, and then `collect` followed by
+/// `persist` every time the collector is due to run based on its `interval`.
+/// Collectors hold on to the data from `collect` until it is persisted.
+///
+/// Every collector is collected before any of them are persisted, and all of
+/// the writes for a tick share one transaction, so the connection passed to
+/// the collectors is already inside of a transaction.
+#[async_trait]
+pub trait Collector: Debug + Send + Sync {
+    /// Name of the collector to use in logs
+    fn name(&self) -> &'static str;
+
+    /// Amount of time between collections, which is rounded to the nearest
+    /// multiple of the monitor's tick interval
+    fn interval(&self) -> Duration {
+        TICK_INTERVAL
+    }
+
+    /// Verifies and cleans up the pre-existing data at monitor start up
+    async fn init(&mut self, conn: &mut SqliteConnection) -> Result<(), NebulaError>;
+
+    /// Gathers the current metrics from the system
+    fn collect(&mut self) -> Result<(), NebulaError>;
+
+    /// Writes the most recently collected metrics to the database
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError>;
+
+    /// Removes all of the collector's data recorded before the cutoff time
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError>;
+
+    /// Called once the tick that the last `persist` wrote to has been
+    /// committed
+    ///
+    /// Collectors that compare each sample against the last one they recorded
+    /// only move on to the new sample here, since a tick that is rolled back
+    /// leaves the last sample in the database as the one to compare against.
+    fn committed(&mut self) {}
+}
+
+/// Wrapper around a collector that keeps track of when it is next due to run
+/// and how healthy it is
+#[derive(Debug)]
+pub struct ScheduledCollector {
+    /// The collector to run
+    pub collector: Box<dyn Collector>,
+    /// Whether or not the collector has been successfully initialized
+    pub initialized: bool,
+    /// Number of ticks left until the collector should run again
+    ticks_until_run: u32,
+    /// Number of failed runs since the last successful run
+    consecutive_failures: u32,
+}
+
+impl ScheduledCollector {
+    /// Creates a new scheduled collector that will run on the next tick
+    pub fn new(collector: Box<dyn Collector>) -> Self {
+        ScheduledCollector {
+            collector,
+            initialized: false,
+            ticks_until_run: 0,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Number of monitor ticks between runs of the collector
+    pub fn ticks_per_run(&self) -> u32 {
+        let ticks: f64 = self.collector.interval().as_secs_f64() / TICK_INTERVAL.as_secs_f64();
+        (ticks.round() as u32).max(1)
+    }
+
+    /// Number of ticks to wait until the next run, which doubles with every
+    /// consecutive failure up to the maximum backoff
+    fn ticks_until_next_run(&self) -> u32 {
+        let max_ticks: u32 = (MAX_BACKOFF.as_secs() / TICK_INTERVAL.as_secs()) as u32;
+        let backoff: u32 = 1 << self.consecutive_failures.min(16);
+        self.ticks_per_run()
+            .saturating_mul(backoff)
+            .min(max_ticks.max(self.ticks_per_run()))
+    }
+
+    /// Advances the schedule by one tick and returns whether the collector
+    /// should run on this tick
+    pub fn tick(&mut self) -> bool {
+        if self.ticks_until_run == 0 {
+            self.ticks_until_run = self.ticks_until_next_run() - 1;
+            true
+        } else {
+            self.ticks_until_run -= 1;
+            false
+        }
+    }
+
+    /// Gathers the collector's metrics from the system
+    pub fn collect(&mut self) -> Result<(), NebulaError> {
+        self.collector.collect()
+    }
+
+    /// Initializes the collector if it has not been yet
+    ///
+    /// The collector's writes are made within a savepoint, so a failure rolls
+    /// back only this collector's changes to the surrounding transaction.
+    pub async fn init(&mut self, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        if self.initialized {
+            return Ok(());
+        }
+        let mut savepoint: Transaction<Sqlite> = conn.begin().await?;
+        self.collector.init(&mut savepoint).await?;
+        savepoint.commit().await?;
+        self.initialized = true;
+        Ok(())
+    }
+
+    /// Writes the collected metrics, initializing the collector first if it
+    /// has not been yet
+    ///
+    /// The collector's writes are made within a savepoint, so a failure rolls
+    /// back only this collector's changes to the surrounding transaction.
+    pub async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        self.init(conn).await?;
+        let mut savepoint: Transaction<Sqlite> = conn.begin().await?;
+        self.collector.persist(cur_time, &mut savepoint).await?;
+        savepoint.commit().await?;
+        Ok(())
+    }
+
+    /// Lets the collector know that its last persisted writes were committed
+    pub fn committed(&mut self) {
+        self.collector.committed();
+    }
+
+    /// Removes the collector's data from before the cutoff
+    ///
+    /// The collector's writes are made within a savepoint, so a failure rolls
+    /// back only this collector's changes to the surrounding transaction.
+    pub async fn prune(
+        &mut self,
+        cutoff: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        let mut savepoint: Transaction<Sqlite> = conn.begin().await?;
+        self.collector.prune(cutoff, &mut savepoint).await?;
+        savepoint.commit().await?;
+        Ok(())
+    }
+
+    /// Updates the health of the collector based on the result of its last
+    /// run and records it in the database
+    #[instrument(skip(self, result, conn), fields(collector = self.collector.name()))]
+    pub async fn record_result(
+        &mut self,
+        cur_time: u64,
+        result: &Result<(), NebulaError>,
+        conn: &mut SqliteConnection,
+    ) {
+        match result {
+            Ok(()) => {
+                if self.consecutive_failures > 0 {
+                    event!(
+                        Level::INFO,
+                        "Collector recovered after {:?} failures",
+                        self.consecutive_failures
+                    );
+                }
+                self.consecutive_failures = 0;
+            }
+            Err(err) => {
+                self.consecutive_failures += 1;
+                // The current tick already reset the schedule, so back off
+                // from the failure
+                self.ticks_until_run = self.ticks_until_next_run() - 1;
+                event!(
+                    Level::ERROR,
+                    "Collector failed {:?} times in a row, retrying in {:?} ticks: {}",
+                    self.consecutive_failures,
+                    self.ticks_until_run + 1,
+                    err
+                );
+            }
+        }
+
+        let health_res: Result<_, sqlx::Error> = match result {
+            Ok(()) => {
+                sqlx::query(
+                    "INSERT INTO COLLECTOR_HEALTH VALUES (?, ?, ?, NULL, NULL, 0)
+                    ON CONFLICT (NAME) DO UPDATE SET
+                        INTERVAL = excluded.INTERVAL,
+                        LAST_SUCCESS = excluded.LAST_SUCCESS,
+                        CONSECUTIVE_FAILURES = 0;",
+                )
+                .bind(self.collector.name())
+                .bind(self.collector.interval().as_secs() as u32)
+                .bind(cur_time as i64)
+                .execute(&mut *conn)
+                .await
+            }
+            Err(err) => {
+                sqlx::query(
+                    "INSERT INTO COLLECTOR_HEALTH VALUES (?, ?, NULL, ?, ?, ?)
+                    ON CONFLICT (NAME) DO UPDATE SET
+                        INTERVAL = excluded.INTERVAL,
+                        LAST_FAILURE = excluded.LAST_FAILURE,
+                        LAST_ERROR = excluded.LAST_ERROR,
+                        CONSECUTIVE_FAILURES = excluded.CONSECUTIVE_FAILURES;",
+                )
+                .bind(self.collector.name())
+                .bind(self.collector.interval().as_secs() as u32)
+                .bind(cur_time as i64)
+                .bind(err.to_string())
+                .bind(self.consecutive_failures)
+                .execute(&mut *conn)
+                .await
+            }
+        };
+
+        if let Err(err) = health_res {
+            event!(Level::ERROR, "Unable to record collector health: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::pool::PoolConnection;
+    use sqlx::SqlitePool;
+
+    /// Collector that does nothing, but runs on a custom interval
+    #[derive(Debug)]
+    struct IdleCollector {
+        interval: Duration,
+    }
+
+    #[async_trait]
+    impl Collector for IdleCollector {
+        fn name(&self) -> &'static str {
+            "idle"
+        }
+
+        fn interval(&self) -> Duration {
+            self.interval
+        }
+
+        async fn init(&mut self, _conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        fn collect(&mut self) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        async fn persist(
+            &mut self,
+            _cur_time: u64,
+            _conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        async fn prune(
+            &mut self,
+            _cutoff: u64,
+            _conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_scheduled_collector() {
+        let mut every_tick: ScheduledCollector = ScheduledCollector::new(Box::new(IdleCollector {
+            interval: TICK_INTERVAL,
+        }));
+        assert_eq!(every_tick.ticks_per_run(), 1);
+        for _ in 0..3 {
+            assert!(every_tick.tick());
+        }
+
+        let mut every_third_tick: ScheduledCollector =
+            ScheduledCollector::new(Box::new(IdleCollector {
+                interval: TICK_INTERVAL * 3,
+            }));
+        let runs: Vec<bool> = (0..7).map(|_| every_third_tick.tick()).collect();
+        assert_eq!(runs, vec![true, false, false, true, false, false, true]);
+
+        // Intervals shorter than a tick still run once per tick
+        let mut too_fast: ScheduledCollector = ScheduledCollector::new(Box::new(IdleCollector {
+            interval: Duration::from_millis(10),
+        }));
+        assert_eq!(too_fast.ticks_per_run(), 1);
+        assert!(too_fast.tick());
+        assert!(too_fast.tick());
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_backoff_after_failures(pool: SqlitePool) -> Result<(), NebulaError> {
+        let mut conn: PoolConnection<Sqlite> = pool.acquire().await?;
+        let mut scheduled: ScheduledCollector = ScheduledCollector::new(Box::new(IdleCollector {
+            interval: TICK_INTERVAL,
+        }));
+        assert_eq!(scheduled.ticks_until_next_run(), 1);
+
+        // Every failure doubles the amount of time until the next run
+        let failure: Result<(), NebulaError> = Err(NebulaError::Parse("bad data".to_string()));
+        for expected_ticks in [2, 4, 8, 16] {
+            scheduled.record_result(42, &failure, &mut conn).await;
+            assert_eq!(scheduled.ticks_until_next_run(), expected_ticks);
+        }
+
+        // The backoff is capped
+        for _ in 0..20 {
+            scheduled.record_result(42, &failure, &mut conn).await;
+        }
+        assert_eq!(
+            scheduled.ticks_until_next_run() as u64,
+            MAX_BACKOFF.as_secs() / TICK_INTERVAL.as_secs()
+        );
+
+        // A success goes right back to the normal schedule
+        scheduled.record_result(43, &Ok(()), &mut conn).await;
+        assert_eq!(scheduled.ticks_until_next_run(), 1);
+
+        Ok(())
+    }
+}
@@ -1,253 +1,393 @@
 use std::collections::HashMap;
 
-use procfs::{CpuInfo, Current};
-use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
+use async_trait::async_trait;
+use procfs::{CpuInfo, FromRead};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
 use tracing::{event, instrument, Level};
 
-use models::{
-    error::NebulaError,
-    tables::{CpuStat, ProcStat},
-};
-
-/// Initializes the database with the approprate CPU data
-#[instrument(skip(conn))]
-pub async fn init_cpu_data(conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to initialize CPU data");
-    let cpu_info: CpuInfo = CpuInfo::current()?;
-
-    event!(Level::DEBUG, "Inserting the current CPUs");
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-
-    // Update the CPU table by replacing the existing data with updated info
-    let mut cpu_insert: QueryBuilder<Sqlite> = QueryBuilder::new("INSERT OR REPLACE INTO CPU ");
-
-    cpu_insert.push_values(cpu_info.cpus.clone().into_iter(), |mut builder, cpu| {
-        let core_number: i32 = cpu.get("processor").unwrap().parse::<i32>().unwrap();
-        let core_info: HashMap<&str, &str> = cpu_info
-            .get_info(core_number as usize)
-            .expect("Should be able to get the core's specific info");
-        event!(
-            Level::DEBUG,
-            "Adding CPU {:?} to the insert query",
-            core_number
-        );
-        builder
-            .push_bind(core_number)
-            .push_bind(core_info.get("cpu MHz").unwrap().parse::<f32>().unwrap())
-            .push_bind(
-                core_info
-                    .get("cache size")
-                    .unwrap()
-                    .split(' ')
-                    .next()
-                    .unwrap()
-                    .parse::<i32>()
-                    .unwrap(),
-            );
-    });
-    cpu_insert.push(";");
-    cpu_insert.build().execute(conn).await?;
-    event!(Level::DEBUG, "Successfully inserted current CPU info");
-
-    event!(Level::DEBUG, "Cleaning up old CPU data");
-    // Old process statistics should be set to NULL as the cpu core
-    sqlx::query("UPDATE PROCSTAT SET CPU_CORE = NULL WHERE CPU_CORE >= ?;")
-        .bind(cpu_info.num_cores() as u32)
-        .execute(conn)
-        .await?;
+use models::{error::NebulaError, tables::Cpu};
+
+use super::collector::Collector;
+use super::system::SystemRoot;
+
+/// Collector for the CPU information and the aggregated usage of each core
+///
+/// Usage is aggregated from the process metrics of the same timestamp, so this
+/// collector has to be persisted after the process collector.
+#[derive(Debug, Default)]
+pub struct CpuCollector {
+    /// Where to read the CPU information from
+    root: SystemRoot,
+    /// Online CPUs found during the last collection
+    cpus: Option<Vec<Cpu>>,
+}
 
-    // Old CPU aggregated stats can be wiped
-    sqlx::query("DELETE FROM CPUSTAT WHERE CPU_CORE >= ?;")
-        .bind(cpu_info.num_cores() as u32)
-        .execute(conn)
-        .await?;
+impl CpuCollector {
+    /// Creates a new CPU collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        CpuCollector { root, cpus: None }
+    }
+}
 
-    // Delete extraneous rows from the cpu table
-    sqlx::query("DELETE FROM CPU WHERE CPU_CORE >= ?;")
-        .bind(cpu_info.num_cores() as u32)
-        .execute(conn)
-        .await?;
-    event!(Level::DEBUG, "Finished cleaning up old CPU data");
+#[async_trait]
+impl Collector for CpuCollector {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
 
-    trans.commit().await?;
-    event!(Level::INFO, "Successfully initialized CPU data");
-    Ok(())
-}
+    /// Initializes the database with the approprate CPU data
+    #[instrument(skip(self, conn))]
+    async fn init(&mut self, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to initialize CPU data");
+        sync_cpus(conn, &get_all_cpus(&self.root)?).await?;
+        event!(Level::INFO, "Successfully initialized CPU data");
+        Ok(())
+    }
 
-#[instrument(skip(conn))]
-pub async fn update_cpu_data(cur_time: u64, conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to update CPU usage metrics");
+    /// Reads the cores that are currently online from procfs
+    #[instrument(skip(self))]
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        self.cpus = Some(get_all_cpus(&self.root)?);
+        Ok(())
+    }
 
-    // Get the last CPU timestamp
-    let last_cpu_stat_res: Result<CpuStat, sqlx::Error> =
-        sqlx::query_as("SELECT * FROM CPUSTAT ORDER BY TIMESTAMP DESC;")
-            .fetch_one(conn)
-            .await;
+    /// Aggregates the process metrics into the usage of each CPU core
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to update CPU usage metrics");
+        // Cores can be brought online or offline at any time, so the CPUs are
+        // brought up to date before their usage is computed
+        match self.cpus.take() {
+            Some(cpus) => sync_cpus(conn, &cpus).await?,
+            None => event!(Level::WARN, "No CPU information has been collected"),
+        }
+
+        // Get the last CPU timestamp
+        let last_cpu_time_res: Option<i64> =
+            sqlx::query_scalar("SELECT MAX(TIMESTAMP) FROM CPUSTAT;")
+                .fetch_one(&mut *conn)
+                .await?;
 
-    let last_cpu_time: i64 = if let Ok(last_cpu_stat) = last_cpu_stat_res {
-        // Use the old time if we have it
-        last_cpu_stat.timestamp
-    } else {
         // Otherwise just use the current time, which would require 2 updates
         // to properly set CPU usage
-        cur_time as i64
-    };
+        let last_cpu_time: i64 = last_cpu_time_res.unwrap_or(cur_time as i64);
+
+        let d_time: i64 = cur_time as i64 - last_cpu_time;
+        // If this is true, we have old data to aggregate from
+        if d_time > 0 {
+            event!(Level::DEBUG, "Computing the percent CPU usage of processes");
+            // Take the difference in total cpu from the old timestamp, or just
+            // use what we have for newer processes
+            sqlx::query(
+                "UPDATE PROCSTAT SET PERCENT_CPU = (TOTAL_CPU - COALESCE(
+                    (SELECT LAST.TOTAL_CPU FROM PROCSTAT AS LAST
+                        WHERE LAST.PID = PROCSTAT.PID AND LAST.START_TIME = PROCSTAT.START_TIME
+                            AND LAST.TIMESTAMP = ?), 0)) / ?
+                WHERE TIMESTAMP = ? AND CPU_CORE IS NOT NULL;",
+            )
+            .bind(last_cpu_time)
+            .bind(d_time as f64)
+            .bind(cur_time as i64)
+            .execute(&mut *conn)
+            .await?;
+            event!(Level::DEBUG, "Finished computing process CPU usage");
+        } else {
+            event!(
+                Level::WARN,
+                "No CPU data to work from, so inserting all 0s for usage"
+            );
+        }
 
-    let num_cpus: usize = sqlx::query("SELECT * FROM CPU;")
-        .fetch_all(conn)
-        .await?
-        .len();
-    let mut cpu_usage: Vec<f32> = Vec::with_capacity(num_cpus);
-    for _i in 0..num_cpus {
-        cpu_usage.push(0.0);
-    }
+        // Add up the usage for each online core, where cores without any
+        // processes have no usage
+        event!(Level::DEBUG, "Inserting updated CPU usage");
+        sqlx::query(
+            "INSERT INTO CPUSTAT
+                SELECT CPU.CPU_CORE, ?, COALESCE(SUM(PROCSTAT.PERCENT_CPU), 0) FROM CPU
+                LEFT JOIN PROCSTAT ON PROCSTAT.CPU_CORE = CPU.CPU_CORE AND PROCSTAT.TIMESTAMP = ?
+                WHERE CPU.IS_ONLINE
+                GROUP BY CPU.CPU_CORE;",
+        )
+        .bind(cur_time as i64)
+        .bind(cur_time as i64)
+        .execute(&mut *conn)
+        .await?;
 
-    let d_time: i64 = cur_time as i64 - last_cpu_time;
+        event!(Level::INFO, "Finished updating CPU usage metrics");
+        Ok(())
+    }
 
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-    // If this is true, we have old data to aggregate from
-    if d_time > 0 {
-        event!(Level::DEBUG, "Beginning to fetch existing process data");
+    /// Removes the aggregated CPU usage from before the cutoff along with any
+    /// offline cores that no longer have any metrics
+    #[instrument(skip(self, conn))]
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        sqlx::query("DELETE FROM CPUSTAT WHERE TIMESTAMP < ?;")
+            .bind(cutoff as i64)
+            .execute(&mut *conn)
+            .await?;
 
-        // Start with the current process stats
-        let cur_proc_stats: Vec<ProcStat> =
-            sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT WHERE TIMESTAMP = ?;")
-                .bind(cur_time as i64)
-                .fetch_all(conn)
-                .await?;
+        // Process metrics are pruned first, so they only hold on to cores that
+        // were in use after the cutoff
+        sqlx::query(
+            "DELETE FROM CPU WHERE NOT IS_ONLINE
+                AND NOT EXISTS (SELECT 1 FROM CPUSTAT cs WHERE cs.CPU_CORE = CPU.CPU_CORE)
+                AND NOT EXISTS (SELECT 1 FROM PROCSTAT ps WHERE ps.CPU_CORE = CPU.CPU_CORE);",
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+}
 
-        // And get the matching stats from the old timestamp
-        let mut last_proc_stats_query: QueryBuilder<Sqlite> =
-            QueryBuilder::new("SELECT * FROM PROCSTAT WHERE TIMESTAMP = ");
-        last_proc_stats_query.push_bind(last_cpu_time);
-        last_proc_stats_query.push(" AND PID IN (");
-        let mut last_proc_stats_query_separated = last_proc_stats_query.separated(", ");
-        for proc in cur_proc_stats.iter() {
-            last_proc_stats_query_separated.push_bind(proc.pid);
-        }
-        last_proc_stats_query_separated.push_unseparated(");");
-        let last_proc_stats: Vec<ProcStat> = last_proc_stats_query
-            .build_query_as::<ProcStat>()
-            .fetch_all(conn)
-            .await?;
+/// Gets every online core from /proc/cpuinfo
+#[instrument]
+fn get_all_cpus(root: &SystemRoot) -> Result<Vec<Cpu>, NebulaError> {
+    let cpu_info: CpuInfo = CpuInfo::from_file(root.proc_path("cpuinfo"))?;
+    (0..cpu_info.num_cores())
+        .map(|cpu_index| {
+            // Fields shared by all cores (including the processor number on
+            // single core systems) are only available through get_info
+            let core_info: HashMap<&str, &str> = cpu_info
+                .get_info(cpu_index)
+                .ok_or_else(|| NebulaError::Parse(format!("Missing info for CPU {}", cpu_index)))?;
+            parse_core_info(&core_info)
+        })
+        .collect::<Result<Vec<Cpu>, NebulaError>>()
+}
 
-        event!(Level::DEBUG, "Starting to aggregate CPU usage");
-        for cur_stat in cur_proc_stats.iter() {
-            // Try to get the matching old stat
-            let matching_last_stat: Vec<ProcStat> = last_proc_stats
-                .clone()
-                .into_iter()
-                .filter(|last_stat| last_stat.pid == cur_stat.pid)
-                .collect();
-
-            let proc_cpu_time = if !matching_last_stat.is_empty() {
-                // Take the difference in total cpu since we have old data
-                cur_stat.total_cpu - matching_last_stat[0].total_cpu
-            } else {
-                // Otherwise just use what we have because it is a newer process
-                cur_stat.total_cpu
-            };
-
-            if let Some(core) = cur_stat.cpu_core {
-                // Add the usage for the respective core
-                let proc_cpu_percent_usage: f32 = proc_cpu_time / d_time as f32;
-                cpu_usage[core as usize] += proc_cpu_percent_usage;
-
-                // Update the process in the DB to have the correct percent usage
-                sqlx::query("UPDATE PROCSTAT SET PERCENT_CPU = ? WHERE PID = ? AND TIMESTAMP = ?;")
-                    .bind(proc_cpu_percent_usage)
-                    .bind(cur_stat.pid)
-                    .bind(cur_stat.timestamp)
-                    .execute(conn)
-                    .await?;
-            }
-        }
-        event!(Level::DEBUG, "Finished aggregating CPU usage");
-    } else {
-        event!(
-            Level::WARN,
-            "No CPU data to work from, so inserting all 0s for usage"
+/// Brings the CPU table up to date with the online cores, where cores that
+/// are no longer online are kept along with their history
+#[instrument(skip(conn, cur_cpus))]
+async fn sync_cpus(conn: &mut SqliteConnection, cur_cpus: &[Cpu]) -> Result<(), NebulaError> {
+    event!(Level::DEBUG, "Updating {:?} online CPUs", cur_cpus.len());
+    if !cur_cpus.is_empty() {
+        let mut cpu_upsert: QueryBuilder<Sqlite> = QueryBuilder::new("INSERT INTO CPU ");
+        cpu_upsert.push_values(cur_cpus.iter(), |mut builder, cpu| {
+            builder
+                .push_bind(cpu.cpu_core)
+                .push_bind(cpu.mhz)
+                .push_bind(cpu.total_cache)
+                .push_bind(true);
+        });
+        cpu_upsert.push(
+            " ON CONFLICT (CPU_CORE) DO UPDATE SET
+                MHZ = excluded.MHZ,
+                TOTAL_CACHE = excluded.TOTAL_CACHE,
+                IS_ONLINE = excluded.IS_ONLINE;",
         );
+        cpu_upsert.build().execute(&mut *conn).await?;
     }
 
-    event!(Level::DEBUG, "Inserting updated CPU usage");
-    let mut cpu_stat_query: QueryBuilder<Sqlite> = QueryBuilder::new("INSERT INTO CPUSTAT ");
-    cpu_stat_query.push_values(0..num_cpus, |mut builder, core_num| {
-        builder
-            .push_bind(core_num as u32)
-            .push_bind(cur_time as i64)
-            .push_bind(cpu_usage[core_num]);
-    });
-    cpu_stat_query.push(";").build().execute(conn).await?;
-    trans.commit().await?;
-
-    event!(Level::INFO, "Finished updating CPU usage metrics");
+    let mut offline_update: QueryBuilder<Sqlite> =
+        QueryBuilder::new("UPDATE CPU SET IS_ONLINE = FALSE WHERE IS_ONLINE AND CPU_CORE NOT IN (");
+    let mut offline_separated = offline_update.separated(", ");
+    for cpu in cur_cpus.iter() {
+        offline_separated.push_bind(cpu.cpu_core);
+    }
+    offline_separated.push_unseparated(");");
+    let offline: u64 = offline_update
+        .build()
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    if offline > 0 {
+        event!(Level::INFO, "{:?} CPUs went offline", offline);
+    }
     Ok(())
 }
 
+/// Parses the information of a single core from /proc/cpuinfo
+fn parse_core_info(core_info: &HashMap<&str, &str>) -> Result<Cpu, NebulaError> {
+    let get_field = |field: &str| -> Result<&str, NebulaError> {
+        core_info
+            .get(field)
+            .copied()
+            .ok_or_else(|| NebulaError::Parse(format!("Missing {:?} in cpuinfo", field)))
+    };
+
+    let cpu_core: &str = get_field("processor")?;
+    let mhz: &str = get_field("cpu MHz")?;
+    // Cache size is formatted like "512 KB"
+    let total_cache: &str = get_field("cache size")?
+        .split(' ')
+        .next()
+        .unwrap_or_default();
+
+    Ok(Cpu {
+        cpu_core: cpu_core
+            .parse::<u32>()
+            .map_err(|_| NebulaError::Parse(format!("Invalid processor {:?}", cpu_core)))?,
+        mhz: mhz
+            .parse::<f32>()
+            .map_err(|_| NebulaError::Parse(format!("Invalid cpu MHz {:?}", mhz)))?,
+        total_cache: total_cache
+            .parse::<u32>()
+            .map_err(|_| NebulaError::Parse(format!("Invalid cache size {:?}", total_cache)))?,
+        // Only online cores are listed in cpuinfo
+        is_online: true,
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use models::tables::{Cpu, CpuStat, ProcStat};
-
     use super::*;
+    use models::tables::{CpuStat, ProcStat};
+    use sqlx::SqlitePool;
     use std::io;
 
-    #[sqlx::test(fixtures("cpuTest"))]
+    #[test]
+    fn test_parse_core_info() {
+        let core_info: HashMap<&str, &str> = HashMap::from([
+            ("processor", "3"),
+            ("cpu MHz", "2100.000"),
+            ("cache size", "512 KB"),
+        ]);
+        let cpu: Cpu = parse_core_info(&core_info).expect("Should parse valid core info");
+        assert_eq!(cpu.cpu_core, 3);
+        assert_eq!(cpu.mhz, 2100.0);
+        assert_eq!(cpu.total_cache, 512);
+
+        // Malformed or missing fields are errors rather than panics
+        let bad_mhz: HashMap<&str, &str> = HashMap::from([
+            ("processor", "3"),
+            ("cpu MHz", "fast"),
+            ("cache size", "512 KB"),
+        ]);
+        assert!(matches!(
+            parse_core_info(&bad_mhz),
+            Err(NebulaError::Parse(_))
+        ));
+        let missing_cache: HashMap<&str, &str> =
+            HashMap::from([("processor", "3"), ("cpu MHz", "2100.000")]);
+        assert!(matches!(
+            parse_core_info(&missing_cache),
+            Err(NebulaError::Parse(_))
+        ));
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("cpuTest"))]
     async fn test_init_cpu_data(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        init_cpu_data(&pool).await?;
-        let real_cpu_data: CpuInfo = CpuInfo::current()?;
+        CpuCollector::new(SystemRoot::fixture())
+            .init(&mut *pool.acquire().await?)
+            .await?;
 
-        let cur_cpus: Vec<Cpu> = sqlx::query_as::<_, Cpu>("SELECT * FROM CPU;")
+        let cur_cpus: Vec<Cpu> = sqlx::query_as::<_, Cpu>("SELECT * FROM CPU ORDER BY CPU_CORE;")
             .fetch_all(&pool)
             .await?;
-        assert_eq!(cur_cpus.len(), real_cpu_data.num_cores());
-        // Make sure our "old" cpu is no longer in the CPU table
-        for cpu in cur_cpus.iter() {
-            assert_ne!(cpu.cpu_core, 99);
+        // The recorded system has 2 cores, so our "old" cpu is offline
+        assert_eq!(cur_cpus.len(), 3);
+        assert_eq!(cur_cpus[0].cpu_core, 0);
+        assert_eq!(cur_cpus[0].mhz, 2100.0);
+        assert_eq!(cur_cpus[1].cpu_core, 1);
+        assert_eq!(cur_cpus[1].mhz, 2400.5);
+        for cpu in cur_cpus[..2].iter() {
+            assert_eq!(cpu.total_cache, 512);
+            assert!(cpu.is_online);
         }
+        assert_eq!(cur_cpus[2].cpu_core, 99);
+        assert!(!cur_cpus[2].is_online);
 
-        let proc_stats: Vec<ProcStat> = sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT;")
+        // The history of the offline cpu is kept
+        let proc_stats: Vec<ProcStat> =
+            sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT ORDER BY TIMESTAMP;")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(proc_stats.len(), 2);
+        assert_eq!(proc_stats[1].cpu_core, Some(99));
+        let cpu_stats: Vec<CpuStat> = sqlx::query_as::<_, CpuStat>("SELECT * FROM CPUSTAT;")
             .fetch_all(&pool)
             .await?;
-        // The length should not have changed
-        assert_eq!(proc_stats.len(), 2);
-        for proc_stat in proc_stats.iter() {
-            // If the core is still not null, make sure it is in the valid cpu range
-            if let Some(core) = proc_stat.cpu_core {
-                assert!(core < real_cpu_data.num_cores() as u32);
-            }
-        }
+        assert_eq!(cpu_stats.len(), 2);
 
-        let cpu_stats: Vec<CpuStat> = sqlx::query_as::<_, CpuStat>("SELECT * FROM CPUSTAT;")
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("cpuTest"))]
+    async fn test_cpu_hotplug(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let mut collector: CpuCollector = CpuCollector::new(SystemRoot::fixture());
+        collector.init(&mut *pool.acquire().await?).await?;
+
+        // A core comes online while the monitor is running
+        let mut cpus: Vec<Cpu> = get_all_cpus(&SystemRoot::fixture())?;
+        cpus.push(Cpu {
+            cpu_core: 2,
+            mhz: 1800.0,
+            total_cache: 512,
+            is_online: true,
+        });
+        collector.cpus = Some(cpus);
+        collector
+            .persist(1234567895, &mut *pool.acquire().await?)
+            .await?;
+        let online: Vec<u32> = sqlx::query_scalar(
+            "SELECT CPU_CORE FROM CPUSTAT WHERE TIMESTAMP = ? ORDER BY CPU_CORE;",
+        )
+        .bind(1234567895)
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(online, vec![0, 1, 2]);
+
+        // Then goes offline again, so it no longer gets any usage
+        collector.collect()?;
+        collector
+            .persist(1234567899, &mut *pool.acquire().await?)
+            .await?;
+        let online: Vec<u32> = sqlx::query_scalar(
+            "SELECT CPU_CORE FROM CPUSTAT WHERE TIMESTAMP = ? ORDER BY CPU_CORE;",
+        )
+        .bind(1234567899)
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(online, vec![0, 1]);
+
+        // Offline cores are removed once all of their metrics are pruned
+        collector
+            .prune(1234567896, &mut *pool.acquire().await?)
+            .await?;
+        let cores: Vec<u32> = sqlx::query_scalar("SELECT CPU_CORE FROM CPU ORDER BY CPU_CORE;")
             .fetch_all(&pool)
             .await?;
-        // Make sure our "old" cpu is no longer in the CPUSTAT table
-        assert_eq!(cpu_stats.len(), 1);
-        for cpu_stat in cpu_stats.iter() {
-            assert!(cpu_stat.cpu_core < real_cpu_data.num_cores() as u32);
-        }
+        // Core 99 is still used by a process metric
+        assert_eq!(cores, vec![0, 1, 99]);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("cpuUpdateTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("cpuUpdateTest"))]
     async fn test_update_cpu_data(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        update_cpu_data(123456790, &pool).await?;
-        let output_stat: CpuStat =
-            sqlx::query_as::<_, CpuStat>("SELECT * FROM CPUSTAT WHERE TIMESTAMP = 123456790;")
-                .fetch_one(&pool)
-                .await?;
-        assert_eq!(output_stat.usage, 0.154);
+        let mut collector: CpuCollector = CpuCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector
+            .persist(123456790, &mut *pool.acquire().await?)
+            .await?;
+        let output_stats: Vec<CpuStat> = sqlx::query_as::<_, CpuStat>(
+            "SELECT * FROM CPUSTAT WHERE TIMESTAMP = 123456790 ORDER BY CPU_CORE;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        // The second core was only found by the collection and has no processes
+        assert_eq!(output_stats.len(), 2);
+        assert_eq!(output_stats[0].usage, 0.154);
+        assert_eq!(output_stats[1].usage, 0.0);
 
         let proc_stats: Vec<ProcStat> = sqlx::query_as::<_, ProcStat>(
             "SELECT * FROM PROCSTAT WHERE TIMESTAMP = 123456790 ORDER BY PID ASC;",
@@ -267,14 +407,16 @@ mod tests {
         Ok(())
     }
 
-    #[sqlx::test(fixtures("cpuUpdateTestEmpty"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("cpuUpdateTestEmpty"))]
     async fn test_update_cpu_data_empty(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        update_cpu_data(123456790, &pool).await?;
+        CpuCollector::new(SystemRoot::fixture())
+            .persist(123456790, &mut *pool.acquire().await?)
+            .await?;
         let output_stat: CpuStat =
             sqlx::query_as::<_, CpuStat>("SELECT * FROM CPUSTAT WHERE TIMESTAMP = 123456790;")
                 .fetch_one(&pool)
@@ -1,8 +1,13 @@
-use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
-use std::process::{Command, Output};
+use async_trait::async_trait;
+use nix::sys::statvfs::{statvfs, Statvfs};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use std::fs;
 use tracing::{event, instrument, Level};
 
-use models::{error::NebulaError, tables::Disk};
+use models::error::NebulaError;
+
+use super::collector::Collector;
+use super::system::SystemRoot;
 
 /// Struct to represent disk data
 #[derive(Debug, Clone)]
@@ -13,281 +18,567 @@ pub struct DiskMetrics {
     mount: String,
     /// Type of the file system
     file_system_type: String,
-    /// Used space in MB
-    used: u32,
-    /// Available space in MB
-    available: u32,
+    /// Used space in bytes
+    used: u64,
+    /// Available space in bytes
+    available: u64,
+    /// Comma separated options the disk is mounted with
+    mount_options: String,
+    /// Whether or not the disk is mounted read-only
+    is_read_only: bool,
+    /// Total number of inodes
+    inodes_total: u64,
+    /// Number of inodes in use
+    inodes_used: u64,
+    /// Number of free inodes
+    inodes_free: u64,
 }
 
-/// Initializes the database with up-to-date disk info at monitor start up
-#[instrument(skip(conn))]
-pub async fn init_disk_data(conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to initialize disk data");
-    let disks: Vec<DiskMetrics> = get_all_disk_data();
-
-    event!(Level::DEBUG, "Starting to insert updated disk information");
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-    let mut disk_insert: QueryBuilder<Sqlite> = QueryBuilder::new("INSERT OR REPLACE INTO DISK ");
+/// Struct to represent a single entry of /proc/mounts
+#[derive(Debug, PartialEq)]
+struct MountEntry {
+    /// Name of the mounted device
+    device: String,
+    /// Path the device is mounted to
+    mount: String,
+    /// Type of the file system
+    file_system_type: String,
+    /// Comma separated mount options
+    options: String,
+}
 
-    disk_insert.push_values(disks.iter(), |mut builder, disk| {
-        event!(
-            Level::DEBUG,
-            "Inserting disk information for device: {:?}",
-            disk.name
-        );
-        builder
-            .push_bind(&disk.name)
-            .push_bind(&disk.mount)
-            .push_bind(&disk.file_system_type);
-    });
+/// Collector for the space used on each disk
+#[derive(Debug, Default)]
+pub struct DiskCollector {
+    /// Where to read the mounted disks from
+    root: SystemRoot,
+    /// Disks found during the last collection
+    disks: Option<Vec<DiskMetrics>>,
+}
 
-    disk_insert.push(";");
-    disk_insert.build().execute(conn).await?;
-    event!(Level::DEBUG, "Finished inserting updated disk information");
+impl DiskCollector {
+    /// Creates a new disk collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        DiskCollector { root, disks: None }
+    }
+}
 
-    clean_up_old_disk_data(conn, &disks).await?;
+#[async_trait]
+impl Collector for DiskCollector {
+    fn name(&self) -> &'static str {
+        "disk"
+    }
 
-    trans.commit().await?;
-    event!(Level::INFO, "Successfully initialized disk info");
-    Ok(())
-}
+    /// Initializes the database with up-to-date disk info at monitor start up
+    #[instrument(skip(self, conn))]
+    async fn init(&mut self, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to initialize disk data");
+        sync_disks(conn, &get_all_disk_data(&self.root)?).await?;
+        event!(Level::INFO, "Successfully initialized disk info");
+        Ok(())
+    }
 
-/// Updates the disk information in the database
-#[instrument(skip(conn))]
-pub async fn update_disk_data(cur_time: u64, conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to update disk information");
-    // Get the current disk information and the db disks for comparisons
-    let cur_disks: Vec<DiskMetrics> = get_all_disk_data();
-    let db_disks: Vec<Disk> = sqlx::query_as::<_, Disk>("SELECT * FROM DISK;")
-        .fetch_all(conn)
-        .await?;
+    /// Gets the current disk usage from the system
+    #[instrument(skip(self))]
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        self.disks = Some(get_all_disk_data(&self.root)?);
+        Ok(())
+    }
 
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-    for disk in cur_disks.iter() {
-        let matching_db_disk: Vec<Disk> = db_disks
-            .clone()
-            .into_iter()
-            .filter(|db_disk| db_disk.device_name == disk.name)
-            .collect();
-        if matching_db_disk.is_empty() {
-            // This is a new disk, so have to insert its information
-            event!(Level::DEBUG, "Found new disk to insert: {:?}", disk.name);
-            sqlx::query("INSERT INTO DISK VALUES (?, ?, ?);")
-                .bind(&disk.name)
-                .bind(&disk.mount)
-                .bind(&disk.file_system_type)
-                .execute(conn)
-                .await?;
+    /// Updates the disk information in the database
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to update disk information");
+        let cur_disks: Vec<DiskMetrics> = match self.disks.take() {
+            Some(disks) => disks,
+            None => {
+                event!(Level::WARN, "No disk information has been collected");
+                return Ok(());
+            }
+        };
+        // Remounts are found by comparing against the disks from before they
+        // are brought up to date
+        record_read_only_remounts(conn, cur_time, &cur_disks).await?;
+        // Disks can be mounted and unmounted at any time
+        sync_disks(conn, &cur_disks).await?;
+
+        if !cur_disks.is_empty() {
+            let mut insert_disk_stats_query: QueryBuilder<Sqlite> =
+                QueryBuilder::new("INSERT INTO DISKSTAT ");
+            insert_disk_stats_query.push_values(cur_disks.iter(), |mut builder, disk| {
+                builder
+                    .push_bind(&disk.name)
+                    .push_bind(&disk.mount)
+                    .push_bind(cur_time as i64)
+                    .push_bind(disk.used as i64)
+                    .push_bind(disk.available as i64)
+                    .push_bind(disk.inodes_total as i64)
+                    .push_bind(disk.inodes_used as i64)
+                    .push_bind(disk.inodes_free as i64);
+            });
+            insert_disk_stats_query.push(";");
+            insert_disk_stats_query.build().execute(&mut *conn).await?;
         }
+
+        event!(Level::INFO, "Finished updating disk information");
+        Ok(())
     }
 
-    let mut insert_disk_stats_query: QueryBuilder<Sqlite> =
-        QueryBuilder::new("INSERT INTO DISKSTAT ");
-    insert_disk_stats_query.push_values(cur_disks.iter(), |mut builder, disk| {
-        builder
-            .push_bind(&disk.name)
-            .push_bind(cur_time as i64)
-            .push_bind(disk.used)
-            .push_bind(disk.available);
-    });
+    /// Removes the disk usage and events from before the cutoff along with any
+    /// unmounted disks that no longer have any history
+    #[instrument(skip(self, conn))]
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        sqlx::query("DELETE FROM DISKSTAT WHERE TIMESTAMP < ?;")
+            .bind(cutoff as i64)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("DELETE FROM DISKEVENT WHERE TIMESTAMP < ?;")
+            .bind(cutoff as i64)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            "DELETE FROM DISK WHERE NOT IS_MOUNTED
+                AND NOT EXISTS (SELECT 1 FROM DISKSTAT ds
+                    WHERE ds.DEVICE_NAME = DISK.DEVICE_NAME AND ds.MOUNT = DISK.MOUNT)
+                AND NOT EXISTS (SELECT 1 FROM DISKEVENT de
+                    WHERE de.DEVICE_NAME = DISK.DEVICE_NAME AND de.MOUNT = DISK.MOUNT);",
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+}
 
-    insert_disk_stats_query.push(";");
-    insert_disk_stats_query.build().execute(conn).await?;
+/// Brings the disk table up to date with the current mounts, where disks that
+/// are no longer mounted are kept along with their history
+#[instrument(skip(conn, cur_disks))]
+async fn sync_disks(
+    conn: &mut SqliteConnection,
+    cur_disks: &[DiskMetrics],
+) -> Result<(), NebulaError> {
+    event!(Level::DEBUG, "Starting to update the mounted disks");
+    if !cur_disks.is_empty() {
+        let mut disk_upsert: QueryBuilder<Sqlite> = QueryBuilder::new("INSERT INTO DISK ");
+        disk_upsert.push_values(cur_disks.iter(), |mut builder, disk| {
+            builder
+                .push_bind(&disk.name)
+                .push_bind(&disk.mount)
+                .push_bind(&disk.file_system_type)
+                .push_bind(true)
+                .push_bind(&disk.mount_options)
+                .push_bind(disk.is_read_only);
+        });
+        disk_upsert.push(
+            " ON CONFLICT (DEVICE_NAME, MOUNT) DO UPDATE SET
+                FS_TYPE = excluded.FS_TYPE,
+                IS_MOUNTED = excluded.IS_MOUNTED,
+                MOUNT_OPTIONS = excluded.MOUNT_OPTIONS,
+                IS_READ_ONLY = excluded.IS_READ_ONLY;",
+        );
+        disk_upsert.build().execute(&mut *conn).await?;
+    }
 
-    // Any removed disks have to be removed from the db
-    clean_up_old_disk_data(conn, &cur_disks).await?;
+    let mut unmounted_update: QueryBuilder<Sqlite> =
+        QueryBuilder::new("UPDATE DISK SET IS_MOUNTED = FALSE WHERE IS_MOUNTED");
+    if !cur_disks.is_empty() {
+        unmounted_update.push(" AND (DEVICE_NAME, MOUNT) NOT IN (VALUES ");
+        let mut unmounted_separated = unmounted_update.separated(", ");
+        for disk in cur_disks.iter() {
+            unmounted_separated.push("(");
+            unmounted_separated.push_bind_unseparated(&disk.name);
+            unmounted_separated.push_unseparated(", ");
+            unmounted_separated.push_bind_unseparated(&disk.mount);
+            unmounted_separated.push_unseparated(")");
+        }
+        unmounted_separated.push_unseparated(")");
+    }
+    unmounted_update.push(";");
+    let unmounted: u64 = unmounted_update
+        .build()
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    if unmounted > 0 {
+        event!(Level::INFO, "{:?} disks were unmounted", unmounted);
+    }
 
-    trans.commit().await?;
-    event!(Level::INFO, "Finished updating disk information");
+    event!(Level::DEBUG, "Finished updating the mounted disks");
     Ok(())
 }
 
-/// Removes all data of disks that no longer exist within the system
-#[instrument(skip(conn))]
-pub async fn clean_up_old_disk_data(
-    conn: &SqlitePool,
-    cur_disks: &Vec<DiskMetrics>,
+/// Records an event for every mounted disk that was writable and is now
+/// read-only, which the kernel does when a file system runs into errors
+#[instrument(skip(conn, cur_disks))]
+async fn record_read_only_remounts(
+    conn: &mut SqliteConnection,
+    cur_time: u64,
+    cur_disks: &[DiskMetrics],
 ) -> Result<(), NebulaError> {
-    event!(Level::DEBUG, "Starting to clean up old disk data");
-
-    // Start by clearing the DISKSTAT table
-    let mut disk_stat_delete: QueryBuilder<Sqlite> =
-        QueryBuilder::new("DELETE FROM DISKSTAT WHERE DEVICE_NAME NOT IN (");
-    let mut disk_stat_separated = disk_stat_delete.separated(", ");
-    for disk in cur_disks.iter() {
-        disk_stat_separated.push_bind(&disk.name);
-    }
-    disk_stat_separated.push_unseparated(");");
-    disk_stat_delete.build().execute(conn).await?;
-
-    // Next clear out the DISK table now that the foreign keys are taken care of
-    let mut disk_delete: QueryBuilder<Sqlite> =
-        QueryBuilder::new("DELETE FROM DISK WHERE DEVICE_NAME NOT IN (");
-    let mut disk_separated = disk_delete.separated(", ");
-    for disk in cur_disks.iter() {
-        disk_separated.push_bind(&disk.name);
+    let read_only_disks: Vec<&DiskMetrics> =
+        cur_disks.iter().filter(|disk| disk.is_read_only).collect();
+    if read_only_disks.is_empty() {
+        return Ok(());
     }
-    disk_separated.push_unseparated(");");
-    disk_delete.build().execute(conn).await?;
 
-    event!(Level::DEBUG, "Finished cleaning up old disk data");
+    let mut event_insert: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "INSERT INTO DISKEVENT
+            SELECT d.DEVICE_NAME, d.MOUNT, ",
+    );
+    event_insert.push_bind(cur_time as i64);
+    event_insert.push(", 'remounted_read_only', v.column3 FROM (");
+    event_insert.push_values(read_only_disks.iter(), |mut builder, disk| {
+        builder
+            .push_bind(&disk.name)
+            .push_bind(&disk.mount)
+            .push_bind(&disk.mount_options);
+    });
+    event_insert.push(
+        ") AS v INNER JOIN DISK d ON d.DEVICE_NAME = v.column1 AND d.MOUNT = v.column2
+            WHERE d.IS_MOUNTED AND NOT d.IS_READ_ONLY;",
+    );
+    let remounted: u64 = event_insert
+        .build()
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    if remounted > 0 {
+        event!(
+            Level::WARN,
+            "{:?} disks were remounted read-only, which usually means disk errors",
+            remounted
+        );
+    }
     Ok(())
 }
 
-/// Runs `df -hT -BM and collects the data
+/// Gets the usage of every disk mounted on the host
+///
+/// Mounts are read from procfs and the usage of each one comes from statvfs,
+/// where every place a device is mounted is reported separately.
 #[instrument]
-pub fn get_all_disk_data() -> Vec<DiskMetrics> {
+pub fn get_all_disk_data(root: &SystemRoot) -> Result<Vec<DiskMetrics>, NebulaError> {
     event!(Level::DEBUG, "Starting to fetch disk data");
 
-    // df gets information about the disk file systems
-    // -h returns the data in a human-readable format
-    // -T returns adds the type of file system to the output
-    // -BM scales all measurements to MB for consistency
-    let output: Output = Command::new("df")
-        .args(["-h", "-T", "-BM"])
-        .output()
-        .expect("Should be able to run the df command");
-    let output_string: String =
-        String::from_utf8(output.stdout).expect("Should be valid utf8 bytes");
+    // Prefer the host's mount namespace, but fall back to our own if PID 1 is
+    // not accessible
+    let mounts: String = fs::read_to_string(root.host_ns_path("mounts"))
+        .or_else(|_| fs::read_to_string(root.proc_path("self/mounts")))?;
 
     let mut disk_vec: Vec<DiskMetrics> = Vec::new();
-    for row in output_string.lines().skip(1) {
-        // Split still returns empty strings from the split
-        // So use filter to only work with real data
-        let row_vec: Vec<&str> = row.split(' ').filter(|word| !word.is_empty()).collect();
-
-        // If it doesn't start with /, it is a temp file system
-        if !row_vec[0].starts_with('/') {
+    for line in mounts.lines() {
+        let entry: MountEntry = match parse_mount_line(line) {
+            Some(entry) => entry,
+            None => {
+                event!(Level::TRACE, "Skipping mount: {:?}", line);
+                continue;
+            }
+        };
+        // A device mounted over itself only has the usage of the top mount
+        if disk_vec
+            .iter()
+            .any(|disk| disk.name == entry.device && disk.mount == entry.mount)
+        {
+            event!(Level::TRACE, "Skipping duplicate mount: {:?}", line);
             continue;
         }
 
-        // Order: Filesystem, Type, Size, Used, Avail, Use%, Mounted on
+        let stats: Statvfs = match statvfs(&root.host_path(&entry.mount)) {
+            Ok(stats) => stats,
+            Err(err) => {
+                event!(
+                    Level::WARN,
+                    "Unable to get the usage of {:?}: {}",
+                    entry.mount,
+                    err
+                );
+                continue;
+            }
+        };
+
+        let fragment_size: u64 = stats.fragment_size() as u64;
         let disk: DiskMetrics = DiskMetrics {
-            name: row_vec[0].to_string(),
-            mount: row_vec[6].to_string(),
-            file_system_type: row_vec[1].to_string(),
-            used: row_vec[3][0..row_vec[3].len() - 1].parse::<u32>().unwrap(),
-            available: row_vec[4][0..row_vec[4].len() - 1].parse::<u32>().unwrap(),
+            name: entry.device,
+            mount: entry.mount,
+            file_system_type: entry.file_system_type,
+            used: (stats.blocks() - stats.blocks_free()) as u64 * fragment_size,
+            available: stats.blocks_available() as u64 * fragment_size,
+            is_read_only: entry.options.split(',').any(|option| option == "ro"),
+            mount_options: entry.options,
+            // Some file systems (ex. btrfs) allocate inodes as they go and
+            // report 0 for all of them
+            inodes_total: stats.files() as u64,
+            inodes_used: stats.files().saturating_sub(stats.files_free()) as u64,
+            inodes_free: stats.files_free() as u64,
         };
         event!(Level::DEBUG, "Found disk: {:?}", &disk);
         disk_vec.push(disk);
     }
 
     event!(Level::DEBUG, "Finished fetching disk data");
-    disk_vec
+    Ok(disk_vec)
+}
+
+/// Parses a single line of /proc/mounts, returning None for virtual file
+/// systems and lines that are not in the expected format
+fn parse_mount_line(line: &str) -> Option<MountEntry> {
+    // Order: device, mount point, type, options, dump, pass
+    let mut fields = line.split_whitespace();
+    let device: &str = fields.next()?;
+    let mount: &str = fields.next()?;
+    let file_system_type: &str = fields.next()?;
+    let options: &str = fields.next()?;
+
+    // If it doesn't start with /, it is not backed by a device
+    if !device.starts_with('/') {
+        return None;
+    }
+
+    Some(MountEntry {
+        device: unescape_mount_field(device),
+        mount: unescape_mount_field(mount),
+        file_system_type: file_system_type.to_string(),
+        options: options.to_string(),
+    })
+}
+
+/// Decodes the octal escapes (ex. `\040` for a space) that the kernel uses for
+/// whitespace and backslashes in /proc/mounts
+fn unescape_mount_field(field: &str) -> String {
+    let mut unescaped: String = String::with_capacity(field.len());
+    let mut rest: &str = field;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let escape: &str = rest.get(index + 1..index + 4).unwrap_or_default();
+        match u8::from_str_radix(escape, 8) {
+            Ok(byte) if escape.len() == 3 => {
+                unescaped.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            _ => {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use models::tables::{Disk, DiskStat};
+    use models::tables::{Disk, DiskEvent, DiskStat};
+    use sqlx::SqlitePool;
     use std::io;
-    use std::time::{SystemTime, UNIX_EPOCH};
 
     #[test]
-    fn test_get_disk_data() {
+    fn test_get_disk_data() -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let output: Vec<DiskMetrics> = get_all_disk_data();
+        let output: Vec<DiskMetrics> = get_all_disk_data(&SystemRoot::fixture())?;
+
+        // Virtual file systems and mounts that do not exist are skipped
+        assert_eq!(output.len(), 3);
+        assert_eq!(output[0].name, "/dev/vda1");
+        assert_eq!(output[0].mount, "/");
+        assert_eq!(output[0].file_system_type, "ext4");
+        assert_eq!(output[1].name, "/dev/vdb1");
+        assert_eq!(output[1].mount, "/data");
+        assert_eq!(output[1].file_system_type, "xfs");
+        assert_eq!(output[1].mount_options, "ro,nosuid,nodev,relatime");
+        assert!(output[1].is_read_only);
+        assert!(!output[0].is_read_only);
+        assert!(output
+            .iter()
+            .all(|disk| disk.inodes_used + disk.inodes_free == disk.inodes_total));
+        // Every mount of the same device is reported
+        assert_eq!(output[2].name, "/dev/vda1");
+        assert_eq!(output[2].mount, "/srv");
+        Ok(())
+    }
 
-        assert!(!output.is_empty());
-        for disk in output.iter() {
-            assert_eq!(&disk.name[0..1], "/");
-        }
+    #[test]
+    fn test_parse_mount_line() {
+        assert_eq!(
+            parse_mount_line("/dev/sda1 /mnt/my\\040disk ext4 rw,relatime 0 0"),
+            Some(MountEntry {
+                device: "/dev/sda1".to_string(),
+                mount: "/mnt/my disk".to_string(),
+                file_system_type: "ext4".to_string(),
+                options: "rw,relatime".to_string(),
+            })
+        );
+
+        // Virtual file systems and malformed lines are skipped
+        assert!(parse_mount_line("tmpfs /tmp tmpfs rw 0 0").is_none());
+        assert!(parse_mount_line("/dev/sda1 / ext4").is_none());
+
+        assert_eq!(unescape_mount_field("a\\011b\\134c"), "a\tb\\c");
+        assert_eq!(unescape_mount_field("trailing\\04"), "trailing\\04");
     }
 
-    #[sqlx::test(fixtures("diskTest"))]
-    async fn test_clean_up_disks(pool: SqlitePool) -> Result<(), NebulaError> {
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("diskTest"))]
+    async fn test_sync_disks(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let cur_disks: Vec<DiskMetrics> = vec![DiskMetrics {
-            name: "/test/disk".to_string(),
-            file_system_type: "ext4".to_string(),
-            mount: "/test/folder".to_string(),
-            available: 42,
-            used: 21,
-        }];
-
-        // The current disk is already in the db plus an old disk
-        clean_up_old_disk_data(&pool, &cur_disks).await?;
+        // The test disk is still mounted and was also mounted somewhere else
+        let cur_disks: Vec<DiskMetrics> = ["/test/folder", "/test/other"]
+            .iter()
+            .map(|mount| DiskMetrics {
+                name: "/test/disk".to_string(),
+                file_system_type: "ext4".to_string(),
+                mount: mount.to_string(),
+                available: 42,
+                used: 21,
+                mount_options: "rw".to_string(),
+                is_read_only: false,
+                inodes_total: 10,
+                inodes_used: 4,
+                inodes_free: 6,
+            })
+            .collect();
+        sync_disks(&mut *pool.acquire().await?, &cur_disks).await?;
 
-        let disk_vec: Vec<Disk> = sqlx::query_as::<_, Disk>("SELECT * FROM DISK;")
+        let disk_vec: Vec<Disk> =
+            sqlx::query_as::<_, Disk>("SELECT * FROM DISK ORDER BY DEVICE_NAME, MOUNT;")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(disk_vec.len(), 3);
+        // The old disk is kept as unmounted along with its history
+        assert_eq!(disk_vec[0].device_name, "/old/device");
+        assert!(!disk_vec[0].is_mounted);
+        assert_eq!(disk_vec[1].mount, "/test/folder");
+        assert!(disk_vec[1].is_mounted);
+        assert_eq!(disk_vec[2].mount, "/test/other");
+        assert!(disk_vec[2].is_mounted);
+        let disk_stat_vec: Vec<DiskStat> = sqlx::query_as::<_, DiskStat>("SELECT * FROM DISKSTAT;")
             .fetch_all(&pool)
             .await?;
-        // There should only be the current disk left
-        assert_eq!(disk_vec.len(), 1);
+        assert_eq!(disk_stat_vec.len(), 1);
 
-        // The stats for the old disk should be removed, leaving nothing left
-        let disk_stat_vec: Vec<DiskStat> = sqlx::query_as::<_, DiskStat>("SELECT * FROM DISKSTAT;")
-            .fetch_all(&pool)
+        // Everything is unmounted when there are no disks
+        sync_disks(&mut *pool.acquire().await?, &[]).await?;
+        let mounted: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM DISK WHERE IS_MOUNTED;")
+            .fetch_one(&pool)
             .await?;
-        assert_eq!(disk_stat_vec.len(), 0);
+        assert_eq!(mounted, 0);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("diskTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("diskTest"))]
     async fn test_init_disk_data(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        // Get the system's current disks for the example
-        let cur_disks: Vec<DiskMetrics> = get_all_disk_data();
-
-        // All of the disks in the db are test disks, which should be wiped
-        // and replaced with the current disks
-        init_disk_data(&pool).await?;
+        // All of the disks in the db are test disks, which should be marked
+        // as unmounted next to the current disks
+        DiskCollector::new(SystemRoot::fixture())
+            .init(&mut *pool.acquire().await?)
+            .await?;
 
-        let disk_vec: Vec<Disk> = sqlx::query_as::<_, Disk>("SELECT * FROM DISK;")
-            .fetch_all(&pool)
+        let mounted: Vec<(String, String)> = sqlx::query_as(
+            "SELECT DEVICE_NAME, MOUNT FROM DISK WHERE IS_MOUNTED ORDER BY DEVICE_NAME, MOUNT;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(
+            mounted,
+            vec![
+                ("/dev/vda1".to_string(), "/".to_string()),
+                ("/dev/vda1".to_string(), "/srv".to_string()),
+                ("/dev/vdb1".to_string(), "/data".to_string()),
+            ]
+        );
+        let unmounted: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM DISK WHERE NOT IS_MOUNTED;")
+            .fetch_one(&pool)
             .await?;
-        // There should only be the current disks left
-        assert_eq!(disk_vec.len(), cur_disks.len());
+        assert_eq!(unmounted, 2);
 
-        // The stats for the old disks should be removed, leaving nothing left
+        // The stats for the old disks are kept until they are pruned
         let disk_stat_vec: Vec<DiskStat> = sqlx::query_as::<_, DiskStat>("SELECT * FROM DISKSTAT;")
             .fetch_all(&pool)
             .await?;
-        assert_eq!(disk_stat_vec.len(), 0);
+        assert_eq!(disk_stat_vec.len(), 1);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("diskTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("diskTest"))]
     async fn test_update_disk_data(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        // Get the system's current disks for the example
-        let cur_disks: Vec<DiskMetrics> = get_all_disk_data();
-
-        let cur_time: u64 = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        // The data disk used to be writable and is now mounted read-only
+        sqlx::query(
+            "INSERT INTO DISK VALUES ('/dev/vdb1', '/data', 'xfs', TRUE, 'rw,relatime', FALSE);",
+        )
+        .execute(&pool)
+        .await?;
 
-        update_disk_data(cur_time, &pool).await?;
+        let mut collector: DiskCollector = DiskCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector
+            .persist(123456789, &mut *pool.acquire().await?)
+            .await?;
 
-        // All old data should be removed and replaced with the current disk data
-        let disk_db: Vec<Disk> = sqlx::query_as::<_, Disk>("SELECT * FROM DISK;")
+        let events: Vec<DiskEvent> = sqlx::query_as::<_, DiskEvent>("SELECT * FROM DISKEVENT;")
             .fetch_all(&pool)
             .await?;
-        assert_eq!(disk_db.len(), cur_disks.len());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].device_name, "/dev/vdb1");
+        assert_eq!(events[0].mount, "/data");
+        assert_eq!(events[0].timestamp, 123456789);
+        assert_eq!(events[0].event, "remounted_read_only");
+        assert_eq!(events[0].mount_options, "ro,nosuid,nodev,relatime");
+
+        // Staying read-only is not another remount
+        collector.collect()?;
+        collector
+            .persist(123456790, &mut *pool.acquire().await?)
+            .await?;
+        let event_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM DISKEVENT;")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(event_count, 1);
 
-        let disk_stat_db: Vec<DiskStat> = sqlx::query_as::<_, DiskStat>("SELECT * FROM DISKSTAT;")
-            .fetch_all(&pool)
+        let disk_stat_db: Vec<DiskStat> = sqlx::query_as::<_, DiskStat>(
+            "SELECT * FROM DISKSTAT WHERE TIMESTAMP = 123456789 ORDER BY DEVICE_NAME, MOUNT;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(disk_stat_db.len(), 3);
+        assert_eq!(disk_stat_db[1].device_name, "/dev/vda1");
+        assert_eq!(disk_stat_db[1].mount, "/srv");
+        assert!(disk_stat_db[1].inodes_total.is_some());
+
+        // Unmounted disks are removed once all of their usage is pruned
+        collector
+            .prune(123456789, &mut *pool.acquire().await?)
             .await?;
-        assert_eq!(disk_stat_db.len(), cur_disks.len());
+        let disk_db: Vec<Disk> =
+            sqlx::query_as::<_, Disk>("SELECT * FROM DISK ORDER BY DEVICE_NAME, MOUNT;")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(disk_db.len(), 3);
+        assert!(disk_db.iter().all(|disk| disk.is_mounted));
+        assert_eq!(disk_db[2].mount, "/data");
+        assert!(disk_db[2].is_read_only);
 
         Ok(())
     }
@@ -1,4 +1,4 @@
-PRAGMA journal_mode=WAL;
+-- Schema that was installed by the Debian package before migrations existed
 
 CREATE TABLE PROCESS (
     PID            INT    NOT NULL CHECK(PID >= 1),
@@ -1,8 +0,0 @@
-CREATE TABLE MEMORY (
-    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    TOTAL       INT  NOT NULL CHECK(TOTAL >= 0),
-    FREE        INT  NOT NULL CHECK(FREE >= 0 AND FREE <= TOTAL),
-    SWAP_TOTAL  INT  NOT NULL CHECK(SWAP_TOTAL >= 0),
-    SWAP_FREE   INT  NOT NULL CHECK(SWAP_FREE >= 0 AND SWAP_FREE <= SWAP_TOTAL),
-    PRIMARY KEY (TIMESTAMP)
-);
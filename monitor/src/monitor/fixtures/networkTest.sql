@@ -1,21 +1,2 @@
-CREATE TABLE NETWORKINTERFACE (
-    NAME     TEXT NOT NULL,
-    IP_ADDR  TEXT,
-    PRIMARY KEY (NAME)
-);
-
-CREATE TABLE NETWORKSTAT (
-    NAME          TEXT NOT NULL,
-    TIMESTAMP     INT           CHECK(TIMESTAMP >= 0),
-    KB_RECV       INT  NOT NULL CHECK(KB_RECV >= 0),
-    KB_SENT       INT  NOT NULL CHECK(KB_SENT >= 0),
-    PACKETS_RECV  INT  NOT NULL CHECK(PACKETS_RECV >= 0),
-    PACKETS_SENT  INT  NOT NULL CHECK(PACKETS_SENT >= 0),
-    ERR_RECV      INT  NOT NULL CHECK(ERR_RECV >= 0),
-    ERR_SENT      INT  NOT NULL CHECK(ERR_SENT >= 0),
-    PRIMARY KEY (NAME, TIMESTAMP)
-    FOREIGN KEY (NAME) REFERENCES NETWORKINTERFACE(NAME)
-);
-
-INSERT INTO NETWORKINTERFACE VALUES("old-interface", "1,2,3,4");
-INSERT INTO NETWORKSTAT VALUES("old-interface", 123456789, 42, 42, 42, 42, 42, 42);
+INSERT INTO NETWORKINTERFACE VALUES("old-interface", "1,2,3,4", TRUE, NULL, NULL, NULL, NULL);
+INSERT INTO NETWORKSTAT VALUES("old-interface", 123456789, 42, 42, 42, 42, 42, 42, 0, 0, 0, 0, 0, 0, 0, 0, 0, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL);
@@ -1,97 +1,20 @@
-CREATE TABLE PROCESS (
-    PID            INT    NOT NULL CHECK(PID >= 1),
-    EXEC           TEXT   NOT NULL,
-    START_TIME     INT    NOT NULL CHECK(START_TIME >= 0),
-    IS_ALIVE       INT    NOT NULL CHECK(IS_ALIVE IN (0, 1)),
-    INIT_TOTAL_CPU REAL   NOT NULL CHECK(INIT_TOTAL_CPU >= 0),
-    PRIMARY KEY (PID)
-);
-
-CREATE TABLE CPU (
-    CPU_CORE     INT  NOT NULL CHECK(CPU_CORE >= 0),
-    MHZ          REAL NOT NULL CHECK(MHZ >= 0),
-    TOTAL_CACHE  INT  NOT NULL CHECK(TOTAL_CACHE >= 0),
-    PRIMARY KEY (CPU_CORE)
-);
-
-CREATE TABLE PROCSTAT (
-    PID              INT  NOT NULL,
-    TIMESTAMP        INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    TOTAL_CPU        REAL NOT NULL CHECK(TOTAL_CPU >= 0),
-    PERCENT_CPU      REAL          CHECK(PERCENT_CPU >= 0),
-    CPU_CORE         INT,
-    VIRTUAL_MEMORY   INT  NOT NULL CHECK(VIRTUAL_MEMORY >= 0),
-    RESIDENT_MEMORY  INT  NOT NULL CHECK(RESIDENT_MEMORY >= 0),
-    SHARED_MEMORY    INT  NOT NULL CHECK(SHARED_MEMORY >= 0),
-    PRIMARY KEY (PID, TIMESTAMP)
-    FOREIGN KEY (PID)       REFERENCES PROCESS(PID),
-    FOREIGN KEY (CPU_CORE)  REFERENCES CPU(CPU_CORE)
-);
-
-CREATE TABLE CPUSTAT (
-    CPU_CORE    INT  NOT NULL,
-    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    USAGE       REAL NOT NULL CHECK(USAGE >= 0),
-    PRIMARY KEY (CPU_CORE, TIMESTAMP)
-);
-
-CREATE TABLE MEMORY (
-    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    TOTAL       INT  NOT NULL CHECK(TOTAL >= 0),
-    FREE        INT  NOT NULL CHECK(FREE >= 0 AND FREE <= TOTAL),
-    SWAP_TOTAL  INT  NOT NULL CHECK(SWAP_TOTAL >= 0),
-    SWAP_FREE   INT  NOT NULL CHECK(SWAP_FREE >= 0 AND SWAP_FREE <= SWAP_TOTAL),
-    PRIMARY KEY (TIMESTAMP)
-);
-
-CREATE TABLE DISK (
-    DEVICE_NAME TEXT NOT NULL,
-    MOUNT       TEXT NOT NULL,
-    FS_TYPE     TEXT NOT NULL,
-    PRIMARY KEY (DEVICE_NAME)
-);
-
-CREATE TABLE DISKSTAT (
-    DEVICE_NAME TEXT NOT NULL,
-    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    USED        INT  NOT NULL CHECK(USED >= 0),
-    AVAILABLE   INT  NOT NULL CHECK(AVAILABLE >= 0),
-    PRIMARY KEY (DEVICE_NAME, TIMESTAMP),
-    FOREIGN KEY (DEVICE_NAME) REFERENCES DISK(DEVICE_NAME)
-);
-
-CREATE TABLE NETWORKINTERFACE (
-    NAME     TEXT NOT NULL,
-    IP_ADDR  TEXT,
-    PRIMARY KEY (NAME)
-);
-
-CREATE TABLE NETWORKSTAT (
-    NAME          TEXT NOT NULL,
-    TIMESTAMP     INT           CHECK(TIMESTAMP >= 0),
-    KB_RECV       INT  NOT NULL CHECK(KB_RECV >= 0),
-    KB_SENT       INT  NOT NULL CHECK(KB_SENT >= 0),
-    PACKETS_RECV  INT  NOT NULL CHECK(PACKETS_RECV >= 0),
-    PACKETS_SENT  INT  NOT NULL CHECK(PACKETS_SENT >= 0),
-    ERR_RECV      INT  NOT NULL CHECK(ERR_RECV >= 0),
-    ERR_SENT      INT  NOT NULL CHECK(ERR_SENT >= 0),
-    PRIMARY KEY (NAME, TIMESTAMP)
-    FOREIGN KEY (NAME) REFERENCES NETWORKINTERFACE(NAME)
-);
-
-INSERT INTO CPU VALUES(0, 4200, 2112);
-INSERT INTO PROCESS VALUES(1, "test-exe", 1234567890, FALSE, 500);
-INSERT INTO PROCESS VALUES(2, "old-exe", 1234567890, TRUE, 42);
-INSERT INTO PROCSTAT VALUES(1, 50, 500, NULL, 0, 42, 42, 0);
-INSERT INTO PROCSTAT VALUES(1, 9999999999, 500, NULL, 0, 42, 42, 0);
-INSERT INTO PROCSTAT VALUES(2, 50, 500, NULL, 0, 42, 42, 0);
+INSERT INTO CPU VALUES(0, 4200, 2112, TRUE);
+INSERT INTO PROCESS VALUES(1, "test-exe", 1234567890, FALSE, 500, NULL, NULL, NULL);
+INSERT INTO PROCESS VALUES(2, "old-exe", 1234567890, TRUE, 42, NULL, NULL, NULL);
+INSERT INTO PROCSTAT VALUES(1, 1234567890, 50, 500, NULL, 0, 42, 42, 0, NULL, NULL);
+INSERT INTO PROCSTAT VALUES(1, 1234567890, 9999999999, 500, NULL, 0, 42, 42, 0, NULL, NULL);
+INSERT INTO PROCSTAT VALUES(2, 1234567890, 50, 500, NULL, 0, 42, 42, 0, NULL, NULL);
 INSERT INTO CPUSTAT VALUES(0, 50, 999);
 INSERT INTO CPUSTAT VALUES(0, 9999999999, 400);
 INSERT INTO MEMORY VALUES(50, 50, 50, 50, 50);
 INSERT INTO MEMORY VALUES(9999999999, 50, 50, 50, 50);
-INSERT INTO DISK VALUES("/my/fs", "/", "ext4");
-INSERT INTO DISKSTAT VALUES("/my/fs", 50, 50, 50);
-INSERT INTO DISKSTAT VALUES("/my/fs", 9999999999, 50, 50);
-INSERT INTO NETWORKINTERFACE VALUES("int0", "1.2.3.4");
-INSERT INTO NETWORKSTAT VALUES("int0", 50, 42, 42, 42, 42, 42, 42);
-INSERT INTO NETWORKSTAT VALUES("int0", 9999999999, 42, 42, 42, 42, 42, 42);
+INSERT INTO DISK VALUES("/my/fs", "/", "ext4", TRUE, "rw", FALSE);
+INSERT INTO DISKSTAT VALUES("/my/fs", "/", 50, 50, 50, 10, 5, 5);
+INSERT INTO DISKSTAT VALUES("/my/fs", "/", 9999999999, 50, 50, 10, 5, 5);
+INSERT INTO DISKEVENT VALUES("/my/fs", "/", 50, "remounted_read_only", "ro");
+INSERT INTO DISKEVENT VALUES("/my/fs", "/", 9999999999, "remounted_read_only", "ro");
+INSERT INTO NETWORKINTERFACE VALUES("int0", "1.2.3.4", TRUE, NULL, NULL, NULL, NULL);
+INSERT INTO NETWORKSTAT VALUES("int0", 50, 42, 42, 42, 42, 42, 42, 0, 0, 0, 0, 0, 0, 0, 0, 0, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL);
+INSERT INTO NETWORKSTAT VALUES("int0", 9999999999, 42, 42, 42, 42, 42, 42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 84, 84, 84, 84, 42, 42, 0, 0);
+INSERT INTO PROTOCOLSTAT VALUES(50, 5, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1);
+INSERT INTO PROTOCOLSTAT VALUES(9999999999, 5, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1);
@@ -0,0 +1,432 @@
+use async_trait::async_trait;
+use procfs::{CpuInfo, FromRead, Meminfo};
+use sqlx::SqliteConnection;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{event, instrument, Level};
+
+use models::{error::NebulaError, tables::Host};
+
+use super::collector::Collector;
+use super::system::SystemRoot;
+
+/// Amount of time between checks for changes to the host's details
+const HOST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Hypervisors that can be identified by the DMI vendor or product name,
+/// named the same way as `systemd-detect-virt`
+const DMI_HYPERVISORS: [(&str, &str); 9] = [
+    ("KVM", "kvm"),
+    ("QEMU", "qemu"),
+    ("VMware", "vmware"),
+    ("VirtualBox", "oracle"),
+    ("innotek GmbH", "oracle"),
+    ("Xen", "xen"),
+    ("Bochs", "bochs"),
+    ("Amazon EC2", "amazon"),
+    ("Microsoft Corporation Virtual Machine", "microsoft"),
+];
+
+/// Container runtimes that can be identified from the cgroup of PID 1
+const CGROUP_CONTAINERS: [(&str, &str); 4] = [
+    ("kubepods", "kubernetes"),
+    ("docker", "docker"),
+    ("libpod", "podman"),
+    ("lxc", "lxc"),
+];
+
+/// Number of CPU packages, physical cores, and hardware threads on the host
+#[derive(Debug, PartialEq)]
+struct CpuTopology {
+    /// Number of physical CPU packages
+    sockets: usize,
+    /// Number of physical cores across all packages
+    cores: usize,
+    /// Number of hardware threads (logical CPUs)
+    threads: usize,
+}
+
+/// Collector for the inventory of the host, which only writes to the database
+/// when one of the host's details changes
+#[derive(Debug, Default)]
+pub struct HostCollector {
+    /// Where to read the host information from
+    root: SystemRoot,
+    /// Host information from the last collection
+    host: Option<Host>,
+}
+
+impl HostCollector {
+    /// Creates a new host collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        HostCollector { root, host: None }
+    }
+}
+
+#[async_trait]
+impl Collector for HostCollector {
+    fn name(&self) -> &'static str {
+        "host"
+    }
+
+    /// The host's details rarely change, so they do not need to be checked
+    /// every tick
+    fn interval(&self) -> Duration {
+        HOST_INTERVAL
+    }
+
+    /// The host row is kept up to date by every persist, so there is nothing
+    /// to set up
+    async fn init(&mut self, _conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        Ok(())
+    }
+
+    /// Reads the host's details from procfs, sysfs, and the host file system
+    #[instrument(skip(self))]
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        self.host = Some(get_host_info(&self.root)?);
+        Ok(())
+    }
+
+    /// Records the host's details if they are different from what is stored
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        let host: Host = match self.host.take() {
+            Some(host) => host,
+            None => {
+                event!(Level::WARN, "No host information has been collected");
+                return Ok(());
+            }
+        };
+
+        // The update time is only moved forward when something has changed
+        let changed: u64 = sqlx::query(
+            "INSERT INTO HOST VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (ID) DO UPDATE SET
+                HOSTNAME = excluded.HOSTNAME,
+                KERNEL_RELEASE = excluded.KERNEL_RELEASE,
+                OS_ID = excluded.OS_ID,
+                OS_NAME = excluded.OS_NAME,
+                OS_VERSION = excluded.OS_VERSION,
+                OS_PRETTY_NAME = excluded.OS_PRETTY_NAME,
+                CPU_MODEL = excluded.CPU_MODEL,
+                CPU_FLAGS = excluded.CPU_FLAGS,
+                SOCKETS = excluded.SOCKETS,
+                CORES = excluded.CORES,
+                THREADS = excluded.THREADS,
+                TOTAL_MEMORY = excluded.TOTAL_MEMORY,
+                VIRTUALIZATION = excluded.VIRTUALIZATION,
+                CONTAINER = excluded.CONTAINER,
+                UPDATED_AT = excluded.UPDATED_AT
+            WHERE (HOSTNAME, KERNEL_RELEASE, OS_ID, OS_NAME, OS_VERSION, OS_PRETTY_NAME,
+                    CPU_MODEL, CPU_FLAGS, SOCKETS, CORES, THREADS, TOTAL_MEMORY,
+                    VIRTUALIZATION, CONTAINER)
+                IS NOT (excluded.HOSTNAME, excluded.KERNEL_RELEASE, excluded.OS_ID,
+                    excluded.OS_NAME, excluded.OS_VERSION, excluded.OS_PRETTY_NAME,
+                    excluded.CPU_MODEL, excluded.CPU_FLAGS, excluded.SOCKETS, excluded.CORES,
+                    excluded.THREADS, excluded.TOTAL_MEMORY, excluded.VIRTUALIZATION,
+                    excluded.CONTAINER);",
+        )
+        .bind(&host.hostname)
+        .bind(&host.kernel_release)
+        .bind(&host.os_id)
+        .bind(&host.os_name)
+        .bind(&host.os_version)
+        .bind(&host.os_pretty_name)
+        .bind(&host.cpu_model)
+        .bind(&host.cpu_flags)
+        .bind(host.sockets)
+        .bind(host.cores)
+        .bind(host.threads)
+        .bind(host.total_memory)
+        .bind(&host.virtualization)
+        .bind(&host.container)
+        .bind(cur_time as i64)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        if changed > 0 {
+            event!(Level::INFO, "Recorded updated host information");
+        }
+        Ok(())
+    }
+
+    /// Only the current host is stored, so there is no history to remove
+    async fn prune(
+        &mut self,
+        _cutoff: u64,
+        _conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        Ok(())
+    }
+}
+
+/// Gets the current details of the host, where the update time is left for
+/// the caller to fill in
+#[instrument]
+fn get_host_info(root: &SystemRoot) -> Result<Host, NebulaError> {
+    let cpu_info: CpuInfo = CpuInfo::from_file(root.proc_path("cpuinfo"))?;
+    let cpu_flags: Vec<&str> = cpu_info.flags(0).unwrap_or_default();
+    let topology: CpuTopology = get_cpu_topology(root, &cpu_info);
+    let os_release: HashMap<String, String> = get_os_release(root);
+
+    Ok(Host {
+        id: 1,
+        hostname: read_trimmed(root.proc_path("sys/kernel/hostname"))?,
+        kernel_release: read_trimmed(root.proc_path("sys/kernel/osrelease"))?,
+        os_id: os_release.get("ID").cloned(),
+        os_name: os_release.get("NAME").cloned(),
+        os_version: os_release.get("VERSION_ID").cloned(),
+        os_pretty_name: os_release.get("PRETTY_NAME").cloned(),
+        cpu_model: cpu_info.model_name(0).map(str::to_string),
+        cpu_flags: cpu_flags.join(" "),
+        sockets: topology.sockets as i64,
+        cores: topology.cores as i64,
+        threads: topology.threads as i64,
+        // Meminfo already converts everything to bytes
+        total_memory: Meminfo::from_file(root.proc_path("meminfo"))?.mem_total as i64,
+        virtualization: detect_virtualization(root, cpu_flags.contains(&"hypervisor")),
+        container: detect_container(root),
+        updated_at: 0,
+    })
+}
+
+/// Reads a single value file, such as the ones in procfs and sysfs
+fn read_trimmed(path: PathBuf) -> Result<String, NebulaError> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+/// Gets the fields of the host's os-release file, which is empty if the
+/// distribution does not have one
+fn get_os_release(root: &SystemRoot) -> HashMap<String, String> {
+    ["etc/os-release", "usr/lib/os-release"]
+        .iter()
+        .find_map(|path| fs::read_to_string(root.host_path(path)).ok())
+        .map(|contents| parse_os_release(&contents))
+        .unwrap_or_default()
+}
+
+/// Parses the `KEY=value` lines of an os-release file, where values may be
+/// wrapped in quotes
+fn parse_os_release(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value: &str = value.trim();
+            let unquoted: &str = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .or_else(|| {
+                    value
+                        .strip_prefix('\'')
+                        .and_then(|value| value.strip_suffix('\''))
+                })
+                .unwrap_or(value);
+            (key.trim().to_string(), unquoted.to_string())
+        })
+        .collect()
+}
+
+/// Counts the sockets, cores, and threads of the host from sysfs, falling
+/// back to the less reliable cpuinfo fields if sysfs is not available
+fn get_cpu_topology(root: &SystemRoot, cpu_info: &CpuInfo) -> CpuTopology {
+    // Each logical CPU has a directory named cpu followed by its number
+    let cpu_dirs: Vec<PathBuf> = match fs::read_dir(root.sys.join("devices/system/cpu")) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.strip_prefix("cpu"))
+                    .is_some_and(|num| !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()))
+            })
+            .map(|entry| entry.path())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let cores: Vec<(String, String)> = if cpu_dirs.is_empty() {
+        (0..cpu_info.num_cores())
+            .map(|cpu_index| {
+                (
+                    cpu_info
+                        .get_field(cpu_index, "physical id")
+                        .unwrap_or("0")
+                        .to_string(),
+                    cpu_info
+                        .get_field(cpu_index, "core id")
+                        .unwrap_or(&cpu_index.to_string())
+                        .to_string(),
+                )
+            })
+            .collect()
+    } else {
+        cpu_dirs
+            .iter()
+            .map(|cpu_dir| {
+                (
+                    read_trimmed(cpu_dir.join("topology/physical_package_id")).unwrap_or_default(),
+                    read_trimmed(cpu_dir.join("topology/core_id")).unwrap_or_default(),
+                )
+            })
+            .collect()
+    };
+
+    CpuTopology {
+        sockets: cores
+            .iter()
+            .map(|(socket, _)| socket)
+            .collect::<HashSet<&String>>()
+            .len(),
+        cores: cores.iter().collect::<HashSet<&(String, String)>>().len(),
+        threads: cores.len(),
+    }
+}
+
+/// Detects the hypervisor that the host is running on from its DMI
+/// information, or reports an unknown hypervisor if the CPU says there is one
+fn detect_virtualization(root: &SystemRoot, has_hypervisor_flag: bool) -> Option<String> {
+    let dmi: String = ["sys_vendor", "product_name"]
+        .iter()
+        .filter_map(|file| read_trimmed(root.sys.join("class/dmi/id").join(file)).ok())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    DMI_HYPERVISORS
+        .iter()
+        .find(|(pattern, _)| dmi.contains(pattern))
+        .map(|(_, name)| name.to_string())
+        .or_else(|| has_hypervisor_flag.then(|| "unknown".to_string()))
+}
+
+/// Detects the container runtime that the host's init process is running in
+fn detect_container(root: &SystemRoot) -> Option<String> {
+    // Most runtimes tell init that it is in a container through its environment
+    if let Ok(environ) = fs::read(root.host_ns_path("environ")) {
+        let container: Option<String> = parse_container_from_environ(&environ);
+        if container.is_some() {
+            return container;
+        }
+    }
+
+    if root.host_path(".dockerenv").exists() {
+        return Some("docker".to_string());
+    }
+    if root.host_path("run/.containerenv").exists() {
+        return Some("podman".to_string());
+    }
+
+    let cgroup: String = fs::read_to_string(root.host_ns_path("cgroup")).ok()?;
+    CGROUP_CONTAINERS
+        .iter()
+        .find(|(pattern, _)| cgroup.contains(pattern))
+        .map(|(_, name)| name.to_string())
+}
+
+/// Gets the value of the `container` variable from a NUL separated process
+/// environment
+fn parse_container_from_environ(environ: &[u8]) -> Option<String> {
+    environ
+        .split(|byte| *byte == 0)
+        .filter_map(|var| std::str::from_utf8(var).ok())
+        .find_map(|var| var.strip_prefix("container="))
+        .filter(|container| !container.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+    use std::io;
+
+    #[test]
+    fn test_get_host_info() -> Result<(), NebulaError> {
+        let host: Host = get_host_info(&SystemRoot::fixture())?;
+        assert_eq!(host.hostname, "fixture-host");
+        assert_eq!(host.kernel_release, "6.1.0-18-amd64");
+        assert_eq!(host.os_id.as_deref(), Some("debian"));
+        assert_eq!(host.os_version.as_deref(), Some("12"));
+        assert_eq!(
+            host.os_pretty_name.as_deref(),
+            Some("Debian GNU/Linux 12 (bookworm)")
+        );
+        assert_eq!(
+            host.cpu_model.as_deref(),
+            Some("Intel(R) Xeon(R) Processor")
+        );
+        assert!(host.cpu_flags.split(' ').any(|flag| flag == "avx2"));
+        // Both threads are separate cores in the same socket
+        assert_eq!((host.sockets, host.cores, host.threads), (1, 2, 2));
+        assert_eq!(host.total_memory, 8000000 * 1024);
+        assert_eq!(host.virtualization.as_deref(), Some("qemu"));
+        assert_eq!(host.container, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_host_files() {
+        let os_release: HashMap<String, String> = parse_os_release(
+            "# comment\nNAME=\"Fedora Linux\"\nID=fedora\nVERSION_ID='39'\n\nBROKEN\n",
+        );
+        assert_eq!(os_release.len(), 3);
+        assert_eq!(os_release["NAME"], "Fedora Linux");
+        assert_eq!(os_release["ID"], "fedora");
+        assert_eq!(os_release["VERSION_ID"], "39");
+
+        assert_eq!(
+            parse_container_from_environ(b"HOME=/\0container=podman\0TERM=xterm\0"),
+            Some("podman".to_string())
+        );
+        assert_eq!(parse_container_from_environ(b"HOME=/\0container=\0"), None);
+        assert_eq!(parse_container_from_environ(b""), None);
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_update_host_data(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let mut collector: HostCollector = HostCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector.persist(100, &mut *pool.acquire().await?).await?;
+        // Nothing changed, so the update time stays put
+        collector.collect()?;
+        collector.persist(200, &mut *pool.acquire().await?).await?;
+
+        let host: Host = sqlx::query_as::<_, Host>("SELECT * FROM HOST;")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(host.updated_at, 100);
+        assert_eq!(host.hostname, "fixture-host");
+
+        // A changed detail is written along with a new update time
+        collector.host = Some(Host {
+            kernel_release: "6.6.0".to_string(),
+            ..get_host_info(&SystemRoot::fixture())?
+        });
+        collector.persist(300, &mut *pool.acquire().await?).await?;
+
+        let hosts: Vec<Host> = sqlx::query_as::<_, Host>("SELECT * FROM HOST;")
+            .fetch_all(&pool)
+            .await?;
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].kernel_release, "6.6.0");
+        assert_eq!(hosts[0].updated_at, 300);
+
+        Ok(())
+    }
+}
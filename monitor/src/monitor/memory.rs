@@ -1,34 +1,91 @@
+use async_trait::async_trait;
 use models::error::NebulaError;
-use procfs::{Current, Meminfo};
-use sqlx::{Sqlite, SqlitePool, Transaction};
+use procfs::{FromRead, Meminfo};
+use sqlx::SqliteConnection;
 use tracing::{event, instrument, Level};
 
-/// Inserts the current memory usage information into the db
-#[instrument(skip(conn))]
-pub async fn update_memory_data(cur_time: u64, conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to update memory information");
-
-    let mem_info: Meminfo = Meminfo::current()?;
-
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-    sqlx::query("INSERT INTO MEMORY VALUES (?, ?, ?, ?, ?);")
-        .bind(cur_time as i64)
-        // Meminfo stores everything in bytes, so convert to KB
-        .bind((mem_info.mem_total / 1000) as u32)
-        .bind(
-            (mem_info
-                .mem_available
-                .expect("System should be newer than Linux 3.14")
-                / 1000) as u32,
-        )
-        .bind((mem_info.swap_total / 1000) as u32)
-        .bind((mem_info.swap_free / 1000) as u32)
-        .execute(conn)
-        .await?;
-
-    trans.commit().await?;
-    event!(Level::INFO, "Finished updating memory information");
-    Ok(())
+use super::collector::Collector;
+use super::system::SystemRoot;
+
+/// Collector for the overall memory usage of the system
+#[derive(Debug, Default)]
+pub struct MemoryCollector {
+    /// Where to read the memory information from
+    root: SystemRoot,
+    /// Memory information from the last collection
+    mem_info: Option<Meminfo>,
+}
+
+impl MemoryCollector {
+    /// Creates a new memory collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        MemoryCollector {
+            root,
+            mem_info: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for MemoryCollector {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    /// Memory has no static information to set up
+    async fn init(&mut self, _conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        Ok(())
+    }
+
+    /// Reads the current memory usage from procfs
+    #[instrument(skip(self))]
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        self.mem_info = Some(Meminfo::from_file(self.root.proc_path("meminfo"))?);
+        Ok(())
+    }
+
+    /// Inserts the current memory usage information into the db
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to update memory information");
+
+        let mem_info: Meminfo = match self.mem_info.take() {
+            Some(mem_info) => mem_info,
+            None => {
+                event!(Level::WARN, "No memory information has been collected");
+                return Ok(());
+            }
+        };
+
+        let mem_available: u64 = mem_info.mem_available.ok_or_else(|| {
+            NebulaError::Parse("MemAvailable requires Linux 3.14 or newer".to_string())
+        })?;
+        sqlx::query("INSERT INTO MEMORY VALUES (?, ?, ?, ?, ?);")
+            .bind(cur_time as i64)
+            // Meminfo already converts everything to bytes
+            .bind(mem_info.mem_total as i64)
+            .bind(mem_available as i64)
+            .bind(mem_info.swap_total as i64)
+            .bind(mem_info.swap_free as i64)
+            .execute(&mut *conn)
+            .await?;
+        event!(Level::INFO, "Finished updating memory information");
+        Ok(())
+    }
+
+    /// Removes the memory usage from before the cutoff
+    #[instrument(skip(self, conn))]
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        sqlx::query("DELETE FROM MEMORY WHERE TIMESTAMP < ?;")
+            .bind(cutoff as i64)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -36,26 +93,32 @@ mod tests {
     use models::tables::Memory;
 
     use super::*;
+    use sqlx::SqlitePool;
     use std::io;
-    use std::time::{SystemTime, UNIX_EPOCH};
 
-    #[sqlx::test(fixtures("memoryTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR")]
     async fn test_update_memory_data(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let cur_time: u64 = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        update_memory_data(cur_time, &pool).await?;
+        let mut collector: MemoryCollector = MemoryCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector
+            .persist(123456789, &mut *pool.acquire().await?)
+            .await?;
 
         let mem_data: Vec<Memory> = sqlx::query_as::<_, Memory>("SELECT * FROM MEMORY;")
             .fetch_all(&pool)
             .await?;
         assert_eq!(mem_data.len(), 1);
+        // meminfo reports KiB, which are stored as bytes
+        assert_eq!(mem_data[0].timestamp, 123456789);
+        assert_eq!(mem_data[0].total, 8000000 * 1024);
+        assert_eq!(mem_data[0].free, 5000000 * 1024);
+        assert_eq!(mem_data[0].swap_total, 1000000 * 1024);
+        assert_eq!(mem_data[0].swap_free, 750000 * 1024);
 
         Ok(())
     }
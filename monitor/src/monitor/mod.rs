@@ -1,96 +1,214 @@
+mod boot;
+mod collector;
 mod cpu;
 mod disk;
+mod host;
 mod memory;
 mod network;
 mod process;
+mod protocol;
+mod system;
+
+use boot::BootCollector;
+pub use collector::TICK_INTERVAL;
+use collector::{Collector, ScheduledCollector};
+use cpu::CpuCollector;
+use disk::DiskCollector;
+use host::HostCollector;
+use memory::MemoryCollector;
+use network::NetworkCollector;
+use process::ProcessCollector;
+use protocol::ProtocolCollector;
+pub use system::SystemRoot;
 
 use models::error::NebulaError;
 
-use sqlx::{Sqlite, SqlitePool, Transaction};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+use sqlx::{ConnectOptions, Sqlite, SqlitePool, Transaction};
 use tracing::{event, instrument, span::Id, Level};
 
+use std::fs;
+use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Absolute path to the database file
-const DB_FILE: &str = "sqlite:///var/nebula/db/nebulaMetrics.db";
+const DB_PATH: &str = "/var/nebula/db/nebulaMetrics.db";
 
 /// Struct to encapsulate the core functionality of the monitor
 #[derive(Debug)]
 pub struct Monitor {
     /// Pool of SQLite connections to the database
     conn: SqlitePool,
+    /// Collectors to run, in the order that they are run in
+    collectors: Vec<ScheduledCollector>,
 }
 
 impl Monitor {
     /// Constructor for the monitor and establishes a connection to the db
     #[instrument]
-    pub async fn new() -> Result<Self, NebulaError> {
+    pub async fn new(root: SystemRoot) -> Result<Self, NebulaError> {
+        Monitor::open(Path::new(DB_PATH), root).await
+    }
+
+    /// Constructor for the monitor that writes to the given database file,
+    /// which is created if it does not exist yet
+    #[instrument]
+    pub async fn open(db_file: &Path, root: SystemRoot) -> Result<Self, NebulaError> {
         event!(Level::INFO, "Creating monitor");
         let new_monitor: Monitor = Monitor {
-            conn: SqlitePool::connect(DB_FILE).await?,
-        };
+            conn: open_db(db_file).await?,
+            collectors: Vec::new(),
+        }
+        .with_default_collectors(&root);
         Ok(new_monitor)
     }
 
     /// Constructor for the monitor specifically for testing
     #[instrument(skip(pool))]
-    fn new_with_db_pool(pool: SqlitePool) -> Self {
-        Monitor { conn: pool }
+    fn new_with_db_pool(pool: SqlitePool, root: SystemRoot) -> Self {
+        Monitor {
+            conn: pool,
+            collectors: Vec::new(),
+        }
+        .with_default_collectors(&root)
+    }
+
+    /// Registers all of the built-in collectors, which read the system data
+    /// from the given root
+    fn with_default_collectors(self, root: &SystemRoot) -> Self {
+        // Processes belong to the current boot, so the boot has to be recorded
+        // first, and CPU usage is aggregated from the process data, so
+        // processes have to be collected before the CPUs
+        self.with_collector(Box::new(BootCollector::new(root.clone())))
+            .with_collector(Box::new(ProcessCollector::new(root.clone())))
+            .with_collector(Box::new(CpuCollector::new(root.clone())))
+            .with_collector(Box::new(MemoryCollector::new(root.clone())))
+            .with_collector(Box::new(DiskCollector::new(root.clone())))
+            .with_collector(Box::new(NetworkCollector::new(root.clone())))
+            .with_collector(Box::new(ProtocolCollector::new(root.clone())))
+            .with_collector(Box::new(HostCollector::new(root.clone())))
+    }
+
+    /// Registers a collector to be run after all previously registered ones
+    pub fn with_collector(mut self, collector: Box<dyn Collector>) -> Self {
+        event!(
+            Level::DEBUG,
+            "Registering the {:?} collector",
+            collector.name()
+        );
+        self.collectors.push(ScheduledCollector::new(collector));
+        self
     }
 
     /// Initializes the database and verifies/cleans the pre-existing data
+    ///
+    /// Collectors that fail to initialize are recorded as unhealthy and are
+    /// initialized again before their next run.
     #[instrument(skip(self))]
-    pub async fn setup_init_data(&self) -> Result<(), NebulaError> {
+    pub async fn setup_init_data(&mut self) {
         event!(Level::INFO, "Setting up initial data");
+        let cur_time: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
 
-        cpu::init_cpu_data(&self.conn).await?;
-        disk::init_disk_data(&self.conn).await?;
-        process::init_process_data(&self.conn).await?;
-        network::init_network_data(&self.conn).await?;
-
-        event!(Level::INFO, "Successfully set up initial data");
-        Ok(())
+        let mut trans: Transaction<Sqlite> = match self.conn.begin().await {
+            Ok(trans) => trans,
+            Err(err) => {
+                event!(
+                    Level::ERROR,
+                    "Unable to start the initial data transaction: {}",
+                    err
+                );
+                return;
+            }
+        };
+        for scheduled in self.collectors.iter_mut() {
+            if let Err(err) = scheduled.init(&mut trans).await {
+                scheduled
+                    .record_result(cur_time, &Err(err), &mut trans)
+                    .await;
+            }
+        }
+        if let Err(err) = trans.commit().await {
+            event!(Level::ERROR, "Unable to commit the initial data: {}", err);
+        }
+
+        event!(Level::INFO, "Finished setting up initial data");
     }
 
     /// Inserts new and updated informaiton in the database
+    ///
+    /// All of the due collectors gather their metrics first, and then write
+    /// them in a single transaction, so readers only ever see complete ticks.
+    /// Each collector is isolated from the others, so a failing collector is
+    /// logged, recorded in the collector health table, and retried later with
+    /// a backoff without affecting the rest of the update.
     #[instrument(skip(self))]
-    pub async fn update(&self, id: Id) {
+    pub async fn update(&mut self, id: Id) {
         event!(Level::INFO, "Entering monitor update function");
         let cur_time: u64 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
 
-        process::update_process_data(cur_time, &self.conn)
-            .await
-            .expect("Should update process data without error");
-        cpu::update_cpu_data(cur_time, &self.conn)
-            .await
-            .expect("Should update cpu usage data without error");
-        memory::update_memory_data(cur_time, &self.conn)
-            .await
-            .expect("Should update memory data without error");
-        disk::update_disk_data(cur_time, &self.conn)
-            .await
-            .expect("Should update disk data without error");
-        network::update_network_interface_data(cur_time, &self.conn)
-            .await
-            .expect("Should update network data without error");
+        // Gather everything before touching the database to keep the
+        // transaction short
+        let collect_results: Vec<Option<Result<(), NebulaError>>> = self
+            .collectors
+            .iter_mut()
+            .map(|scheduled| {
+                if !scheduled.tick() {
+                    return None;
+                }
+                event!(
+                    Level::DEBUG,
+                    "Collecting from the {:?} collector",
+                    scheduled.collector.name()
+                );
+                Some(scheduled.collect())
+            })
+            .collect();
+
+        let mut trans: Transaction<Sqlite> = match self.conn.begin().await {
+            Ok(trans) => trans,
+            Err(err) => {
+                event!(
+                    Level::ERROR,
+                    "Unable to start the update transaction: {}",
+                    err
+                );
+                return;
+            }
+        };
+        // Collectors whose writes are part of the transaction
+        let mut persisted: Vec<&mut ScheduledCollector> = Vec::new();
+        for (scheduled, collect_result) in self.collectors.iter_mut().zip(collect_results) {
+            let result: Result<(), NebulaError> = match collect_result {
+                None => continue,
+                Some(Ok(())) => scheduled.persist(cur_time, &mut trans).await,
+                Some(Err(err)) => Err(err),
+            };
+            scheduled.record_result(cur_time, &result, &mut trans).await;
+            if result.is_ok() {
+                persisted.push(scheduled);
+            }
+        }
+        match trans.commit().await {
+            Ok(()) => persisted
+                .into_iter()
+                .for_each(|scheduled| scheduled.committed()),
+            Err(err) => event!(Level::ERROR, "Unable to commit the update: {}", err),
+        }
 
         event!(Level::INFO, "Exiting monitor update function");
     }
 
     /// Prunes the database by removing all outdated information
     #[instrument(skip(self))]
-    pub async fn prune_db(&self, id: Id) {
+    pub async fn prune_db(&mut self, id: Id) {
         event!(Level::INFO, "Entering database pruning");
-        let prune_trans: Transaction<Sqlite> = self
-            .conn
-            .begin()
-            .await
-            .expect("Should be able to start a new transaction");
 
-        // Tables with timestamp data: PROCSTAT, CPUSTAT, MEMORY, DISKSTAT
         let cur_time: u64 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -98,59 +216,430 @@ impl Monitor {
         // Prune any data that is from 3 hours ago or earlier
         let three_hours_ago: u64 = cur_time - 60 * 60 * 3;
 
-        sqlx::query("DELETE FROM PROCSTAT WHERE TIMESTAMP < ?;")
-            .bind(three_hours_ago as i64)
-            .execute(&self.conn)
-            .await
-            .expect("Should be able to prune from PROCSTAT");
-
-        // Processes are just marked as dead, but can remove if dead for over
-        // 3 hours
-        sqlx::query("DELETE FROM PROCESS WHERE PID NOT IN (SELECT DISTINCT PID FROM PROCSTAT);")
-            .execute(&self.conn)
-            .await
-            .expect("Should be able to clear old processes");
-
-        sqlx::query("DELETE FROM CPUSTAT WHERE TIMESTAMP < ?;")
-            .bind(three_hours_ago as i64)
-            .execute(&self.conn)
-            .await
-            .expect("Should be able to prune from CPUSTAT");
-
-        sqlx::query("DELETE FROM MEMORY WHERE TIMESTAMP < ?;")
-            .bind(three_hours_ago as i64)
-            .execute(&self.conn)
-            .await
-            .expect("Should be able to prune from MEMORY");
-
-        sqlx::query("DELETE FROM DISKSTAT WHERE TIMESTAMP < ?;")
-            .bind(three_hours_ago as i64)
-            .execute(&self.conn)
-            .await
-            .expect("Should be able to prune from DISKSTAT");
-
-        sqlx::query("DELETE FROM NETWORKSTAT WHERE TIMESTAMP < ?;")
-            .bind(three_hours_ago as i64)
-            .execute(&self.conn)
-            .await
-            .expect("Should be able to prune from NETWORKSTAT");
-
-        prune_trans
-            .commit()
-            .await
-            .expect("Should be able to commit the transaction");
+        let mut trans: Transaction<Sqlite> = match self.conn.begin().await {
+            Ok(trans) => trans,
+            Err(err) => {
+                event!(
+                    Level::ERROR,
+                    "Unable to start the pruning transaction: {}",
+                    err
+                );
+                return;
+            }
+        };
+        for scheduled in self.collectors.iter_mut() {
+            if let Err(err) = scheduled.prune(three_hours_ago, &mut trans).await {
+                event!(
+                    Level::ERROR,
+                    "Unable to prune the {:?} collector's data: {}",
+                    scheduled.collector.name(),
+                    err
+                );
+            }
+        }
+        if let Err(err) = trans.commit().await {
+            event!(Level::ERROR, "Unable to commit the pruning: {}", err);
+        }
 
         event!(Level::INFO, "Exiting database pruning");
     }
 }
 
+/// Opens the database, creating it if it does not exist yet, and migrates it
+/// to the latest schema
+#[instrument]
+async fn open_db(path: &Path) -> Result<SqlitePool, NebulaError> {
+    if let Some(db_dir) = path.parent() {
+        fs::create_dir_all(db_dir)?;
+    }
+
+    let options: SqliteConnectOptions = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        // Lets the server read while the monitor is writing
+        .journal_mode(SqliteJournalMode::Wal)
+        // Batched inserts have thousands of parameters, which are far too slow
+        // to format and log on every tick
+        .disable_statement_logging();
+    let pool: SqlitePool = SqlitePool::connect_with(options).await?;
+
+    event!(Level::INFO, "Running database migrations");
+    models::MIGRATOR
+        .run(&pool)
+        .await
+        .map_err(sqlx::Error::from)?;
+    event!(Level::INFO, "Database is up to date");
+    Ok(pool)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_trait::async_trait;
+    use models::tables::{CollectorHealth, DiskStat, Memory, NetworkStat, ProcStat};
+    use sqlx::SqliteConnection;
+    use std::env;
     use std::io;
+    use std::path::PathBuf;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
     use tracing::{span, Span};
 
-    #[sqlx::test(fixtures("pruneTest"))]
+    /// Collector that always fails to collect its data
+    #[derive(Debug)]
+    struct FailingCollector;
+
+    #[async_trait]
+    impl Collector for FailingCollector {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn init(&mut self, _conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        fn collect(&mut self) -> Result<(), NebulaError> {
+            Err(NebulaError::Parse("bad data".to_string()))
+        }
+
+        async fn persist(
+            &mut self,
+            _cur_time: u64,
+            _conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        async fn prune(
+            &mut self,
+            _cutoff: u64,
+            _conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            Ok(())
+        }
+    }
+
+    /// Collector that fails after it has already written some of its data
+    #[derive(Debug)]
+    struct PartialWriteCollector;
+
+    #[async_trait]
+    impl Collector for PartialWriteCollector {
+        fn name(&self) -> &'static str {
+            "partial"
+        }
+
+        async fn init(&mut self, _conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        fn collect(&mut self) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        async fn persist(
+            &mut self,
+            _cur_time: u64,
+            conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            sqlx::query("INSERT INTO MEMORY VALUES(1, 1, 1, 1, 1);")
+                .execute(&mut *conn)
+                .await?;
+            Err(NebulaError::Parse("bad data".to_string()))
+        }
+
+        async fn prune(
+            &mut self,
+            _cutoff: u64,
+            _conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            Ok(())
+        }
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_update_isolates_failures(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let update_span: Span = span!(Level::TRACE, "update-test");
+        let _guard = update_span.enter();
+        let mut monitor: Monitor = Monitor {
+            conn: pool.clone(),
+            collectors: Vec::new(),
+        }
+        .with_collector(Box::new(FailingCollector))
+        .with_collector(Box::new(MemoryCollector::new(SystemRoot::fixture())));
+        monitor.setup_init_data().await;
+
+        monitor.update(update_span.id().unwrap()).await;
+
+        // The memory collector should still run after the failing collector
+        assert_eq!(
+            sqlx::query("SELECT * FROM MEMORY;")
+                .fetch_all(&pool)
+                .await?
+                .len(),
+            1
+        );
+
+        let failing_health: CollectorHealth = sqlx::query_as::<_, CollectorHealth>(
+            "SELECT * FROM COLLECTOR_HEALTH WHERE NAME = 'failing';",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(failing_health.consecutive_failures, 1);
+        assert!(failing_health.last_success.is_none());
+        assert!(failing_health.last_failure.is_some());
+        assert_eq!(
+            failing_health.last_error,
+            Some("parse error: bad data".to_string())
+        );
+
+        let memory_health: CollectorHealth = sqlx::query_as::<_, CollectorHealth>(
+            "SELECT * FROM COLLECTOR_HEALTH WHERE NAME = 'memory';",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(memory_health.consecutive_failures, 0);
+        assert!(memory_health.last_success.is_some());
+        assert!(memory_health.last_error.is_none());
+
+        // The failing collector should be backed off for the next tick and
+        // retried on the one after
+        monitor.update(update_span.id().unwrap()).await;
+        let failures: u32 = sqlx::query_as::<_, CollectorHealth>(
+            "SELECT * FROM COLLECTOR_HEALTH WHERE NAME = 'failing';",
+        )
+        .fetch_one(&pool)
+        .await?
+        .consecutive_failures;
+        assert_eq!(failures, 1);
+
+        monitor.update(update_span.id().unwrap()).await;
+        let failures: u32 = sqlx::query_as::<_, CollectorHealth>(
+            "SELECT * FROM COLLECTOR_HEALTH WHERE NAME = 'failing';",
+        )
+        .fetch_one(&pool)
+        .await?
+        .consecutive_failures;
+        assert_eq!(failures, 2);
+
+        Ok(())
+    }
+
+    /// Collector that counts how many of its writes were committed
+    #[derive(Debug)]
+    struct CommitCountingCollector {
+        /// Whether persisting fails
+        fails: bool,
+        /// Number of times the collector was told its writes were committed
+        commits: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Collector for CommitCountingCollector {
+        fn name(&self) -> &'static str {
+            if self.fails {
+                "counting-failing"
+            } else {
+                "counting"
+            }
+        }
+
+        async fn init(&mut self, _conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        fn collect(&mut self) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        async fn persist(
+            &mut self,
+            _cur_time: u64,
+            _conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            if self.fails {
+                return Err(NebulaError::Parse("bad data".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn prune(
+            &mut self,
+            _cutoff: u64,
+            _conn: &mut SqliteConnection,
+        ) -> Result<(), NebulaError> {
+            Ok(())
+        }
+
+        fn committed(&mut self) {
+            self.commits.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_update_commits_persisted_collectors(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let update_span: Span = span!(Level::TRACE, "update-test");
+        let _guard = update_span.enter();
+        let commits: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let failed_commits: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let mut monitor: Monitor = Monitor {
+            conn: pool.clone(),
+            collectors: Vec::new(),
+        }
+        .with_collector(Box::new(CommitCountingCollector {
+            fails: true,
+            commits: failed_commits.clone(),
+        }))
+        .with_collector(Box::new(CommitCountingCollector {
+            fails: false,
+            commits: commits.clone(),
+        }));
+        monitor.setup_init_data().await;
+
+        // Only the collector whose writes made it into the tick is told
+        monitor.update(update_span.id().unwrap()).await;
+        assert_eq!(commits.load(Ordering::SeqCst), 1);
+        assert_eq!(failed_commits.load(Ordering::SeqCst), 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_update_rolls_back_failed_writes(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let update_span: Span = span!(Level::TRACE, "update-test");
+        let _guard = update_span.enter();
+        let mut monitor: Monitor = Monitor {
+            conn: pool.clone(),
+            collectors: Vec::new(),
+        }
+        .with_collector(Box::new(PartialWriteCollector))
+        .with_collector(Box::new(MemoryCollector::new(SystemRoot::fixture())));
+        monitor.setup_init_data().await;
+
+        monitor.update(update_span.id().unwrap()).await;
+
+        // Only the memory collector's row should be committed
+        let memory: Vec<Memory> = sqlx::query_as::<_, Memory>("SELECT * FROM MEMORY;")
+            .fetch_all(&pool)
+            .await?;
+        assert_eq!(memory.len(), 1);
+        assert_ne!(memory[0].timestamp, 1);
+
+        let partial_health: CollectorHealth = sqlx::query_as::<_, CollectorHealth>(
+            "SELECT * FROM COLLECTOR_HEALTH WHERE NAME = 'partial';",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(partial_health.consecutive_failures, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_open_db() -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let test_dir: PathBuf = env::temp_dir().join(format!("nebula-open-db-{}", process::id()));
+        let _ = fs::remove_dir_all(&test_dir);
+
+        // A missing database is created with the latest schema
+        let new_db: SqlitePool = open_db(&test_dir.join("new/nebulaMetrics.db")).await?;
+        assert!(sqlx::query("SELECT * FROM COLLECTOR_HEALTH;")
+            .fetch_all(&new_db)
+            .await?
+            .is_empty());
+        new_db.close().await;
+
+        // A database from before migrations existed keeps its data
+        let legacy_path: PathBuf = test_dir.join("legacy.db");
+        let legacy_db: SqlitePool = SqlitePool::connect_with(
+            SqliteConnectOptions::new()
+                .filename(&legacy_path)
+                .create_if_missing(true),
+        )
+        .await?;
+        sqlx::query(include_str!("fixtures/legacySchema.sql"))
+            .execute(&legacy_db)
+            .await?;
+        sqlx::query(
+            "INSERT INTO MEMORY VALUES(50, 50, 50, 50, 50);
+            INSERT INTO DISK VALUES(\"/my/fs\", \"/\", \"ext4\");
+            INSERT INTO DISKSTAT VALUES(\"/my/fs\", 50, 42, 21);
+            INSERT INTO NETWORKINTERFACE VALUES(\"int0\", \"1.2.3.4\");
+            INSERT INTO NETWORKSTAT VALUES(\"int0\", 50, 42, 21, 1, 1, 0, 0);
+            INSERT INTO NETWORKSTAT VALUES(\"int0\", 60, 50, 30, 2, 2, 0, 0);
+            INSERT INTO CPU VALUES(0, 5, 10);
+            INSERT INTO PROCESS VALUES(7, \"test-exe\", 40, 1, 2);
+            INSERT INTO PROCSTAT VALUES(7, 50, 3, NULL, 0, 42, 21, 0);",
+        )
+        .execute(&legacy_db)
+        .await?;
+        legacy_db.close().await;
+
+        let migrated_db: SqlitePool = open_db(&legacy_path).await?;
+        // Sizes were converted from KB and MB to bytes
+        let memory: Vec<Memory> = sqlx::query_as::<_, Memory>("SELECT * FROM MEMORY;")
+            .fetch_all(&migrated_db)
+            .await?;
+        assert_eq!(memory.len(), 1);
+        assert_eq!(memory[0].total, 50000);
+        let disk_stat: DiskStat = sqlx::query_as::<_, DiskStat>("SELECT * FROM DISKSTAT;")
+            .fetch_one(&migrated_db)
+            .await?;
+        assert_eq!(disk_stat.used, 42 * 1024 * 1024);
+        // Inodes were never recorded before
+        assert!(disk_stat.inodes_total.is_none());
+        let network_stat: NetworkStat =
+            sqlx::query_as::<_, NetworkStat>("SELECT * FROM NETWORKSTAT;")
+                .fetch_one(&migrated_db)
+                .await?;
+        // Network totals were turned into the change since the previous sample
+        assert_eq!(network_stat.timestamp, 60);
+        assert_eq!(network_stat.bytes_recv, 8000);
+        assert_eq!(network_stat.bytes_sent, 9000);
+        assert_eq!(network_stat.packets_recv, 1);
+        assert!(network_stat.bytes_recv_total.is_none());
+        // Process stats are tied to the start time of their process
+        let proc_stat: ProcStat = sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT;")
+            .fetch_one(&migrated_db)
+            .await?;
+        assert_eq!(proc_stat.start_time, 40);
+        assert_eq!(proc_stat.resident_memory, 21000);
+        assert!(sqlx::query("SELECT * FROM COLLECTOR_HEALTH;")
+            .fetch_all(&migrated_db)
+            .await?
+            .is_empty());
+        migrated_db.close().await;
+
+        // Opening an up to date database again is a no-op
+        let reopened_db: SqlitePool = open_db(&legacy_path).await?;
+        reopened_db.close().await;
+
+        let _ = fs::remove_dir_all(&test_dir);
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("pruneTest"))]
     async fn test_prune_db(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
@@ -159,7 +648,7 @@ mod tests {
 
         let prune_span: Span = span!(Level::TRACE, "prune-test");
         let _guard = prune_span.enter();
-        let monitor: Monitor = Monitor::new_with_db_pool(pool.clone());
+        let mut monitor: Monitor = Monitor::new_with_db_pool(pool.clone(), SystemRoot::fixture());
 
         let _ = monitor.prune_db(prune_span.id().unwrap()).await;
 
@@ -203,6 +692,14 @@ mod tests {
             1
         );
 
+        assert_eq!(
+            sqlx::query("SELECT * FROM DISKEVENT;")
+                .fetch_all(&pool)
+                .await?
+                .len(),
+            1
+        );
+
         assert_eq!(
             sqlx::query("SELECT * FROM NETWORKSTAT;")
                 .fetch_all(&pool)
@@ -211,6 +708,14 @@ mod tests {
             1
         );
 
+        assert_eq!(
+            sqlx::query("SELECT * FROM PROTOCOLSTAT;")
+                .fetch_all(&pool)
+                .await?
+                .len(),
+            1
+        );
+
         Ok(())
     }
 }
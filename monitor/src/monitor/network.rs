@@ -1,288 +1,930 @@
+use async_trait::async_trait;
 use models::error::NebulaError;
-use models::tables::NetworkInterface;
-use procfs::net::{self, ARPEntry, DeviceStatus, InterfaceDeviceStatus};
-use procfs::Current;
-use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
+use models::tables::{InterfaceAddress, NetworkStat};
+use nix::ifaddrs::{self, InterfaceAddressIterator};
+use nix::sched::{self, CloneFlags};
+use procfs::net::{DeviceStatus, InterfaceDeviceStatus};
+use procfs::FromRead;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::thread;
 use tracing::{event, instrument, Level};
 
-/// Initializes the database with up-to-date disk info at monitor start up
-#[instrument(skip(conn))]
-pub async fn init_network_data(conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to initialize network data");
-    let interfaces: Vec<DeviceStatus> = InterfaceDeviceStatus::current()?
-        .0
-        .values()
-        .cloned()
-        .collect();
-    let arp_table: Vec<ARPEntry> = net::arp()?;
-
-    event!(
-        Level::DEBUG,
-        "Starting to insert current network interfaces"
-    );
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-    let mut insert_interface_query: QueryBuilder<Sqlite> =
-        QueryBuilder::new("INSERT OR REPLACE INTO NETWORKINTERFACE ");
-    insert_interface_query.push_values(interfaces.iter(), |mut builder, interface| {
-        let arp_entry_vec: Vec<&ARPEntry> = arp_table
+use super::collector::Collector;
+use super::system::SystemRoot;
+
+/// Link details of a network interface from sysfs
+#[derive(Debug, Clone, Default, PartialEq)]
+struct LinkInfo {
+    /// Hardware address of the interface
+    mac_address: Option<String>,
+    /// Maximum transmission unit in bytes
+    mtu: Option<i64>,
+    /// Operational state of the interface
+    operstate: Option<String>,
+    /// Link speed in Mbps
+    speed: Option<i64>,
+}
+
+/// Everything read about the network interfaces during a single collection
+#[derive(Debug)]
+struct NetworkSnapshot {
+    /// Traffic counters of each interface
+    devices: Vec<DeviceStatus>,
+    /// Link details of each interface by name
+    links: HashMap<String, LinkInfo>,
+    /// Addresses assigned to the interfaces
+    addresses: Vec<InterfaceAddress>,
+}
+
+/// Collector for the traffic going through each network interface
+#[derive(Debug, Default)]
+pub struct NetworkCollector {
+    /// Where to read the network information from
+    root: SystemRoot,
+    /// Interfaces found during the last collection
+    interfaces: Option<NetworkSnapshot>,
+    /// Counters of each interface as of the last recorded sample, which the
+    /// next sample is compared against
+    last_counters: HashMap<String, DeviceStatus>,
+    /// Counters of the sample being persisted, which become the last counters
+    /// once the tick is committed
+    pending_counters: Option<HashMap<String, DeviceStatus>>,
+    /// Whether it has been logged that the host's IPv4 addresses cannot be
+    /// read, so that it is not logged on every collection
+    ipv4_warned: bool,
+}
+
+impl NetworkCollector {
+    /// Creates a new network collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        NetworkCollector {
+            root,
+            interfaces: None,
+            last_counters: HashMap::new(),
+            pending_counters: None,
+            ipv4_warned: false,
+        }
+    }
+
+    /// Reads the interface statuses, link details, and addresses of the
+    /// host's network namespace
+    ///
+    /// IPv4 addresses are left out when the host's network namespace cannot
+    /// be joined, rather than reading the monitor's own interfaces in their
+    /// place.
+    fn read_interfaces(&mut self) -> Result<NetworkSnapshot, NebulaError> {
+        let devices: Vec<DeviceStatus> =
+            InterfaceDeviceStatus::from_file(self.root.host_ns_path("net/dev"))?
+                .0
+                .into_values()
+                .collect();
+        let links: HashMap<String, LinkInfo> = devices
             .iter()
-            .filter(|entry| entry.device == interface.name)
+            .map(|device| {
+                (
+                    device.name.clone(),
+                    read_link_info(&self.root.sys.join("class/net").join(&device.name)),
+                )
+            })
             .collect();
-        let interface_ip: Option<String> = if arp_entry_vec.is_empty() {
-            None
-        } else {
-            Some(arp_entry_vec[0].ip_address.to_string())
+        let mut addresses: Vec<InterfaceAddress> = match read_ipv4_addresses(&self.root) {
+            Ok(addresses) => addresses,
+            Err(err) => {
+                if !self.ipv4_warned {
+                    event!(
+                        Level::WARN,
+                        "Leaving out IPv4 addresses, since the host's network namespace cannot be joined: {}",
+                        err
+                    );
+                    self.ipv4_warned = true;
+                }
+                Vec::new()
+            }
         };
+        addresses.extend(read_ipv6_addresses(&self.root)?);
+        Ok(NetworkSnapshot {
+            devices,
+            links,
+            addresses,
+        })
+    }
+}
 
-        builder.push_bind(&interface.name).push_bind(interface_ip);
-    });
-    insert_interface_query.push(";");
-    insert_interface_query.build().execute(conn).await?;
-    event!(
-        Level::DEBUG,
-        "Finished inserting current network interfaces"
-    );
+#[async_trait]
+impl Collector for NetworkCollector {
+    fn name(&self) -> &'static str {
+        "network"
+    }
+
+    /// Initializes the database with up-to-date network info at monitor start up
+    #[instrument(skip(self, conn))]
+    async fn init(&mut self, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to initialize network data");
+        let snapshot: NetworkSnapshot = self.read_interfaces()?;
+        sync_interfaces(conn, &snapshot).await?;
+        // The first sample is compared against the counters at start up
+        self.last_counters = index_counters(snapshot.devices);
+        event!(Level::INFO, "Successfully initialized network info");
+        Ok(())
+    }
 
-    clean_up_old_interfaces(conn, &interfaces).await?;
+    /// Reads the interface statuses, link details, and addresses
+    #[instrument(skip(self))]
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        self.interfaces = Some(self.read_interfaces()?);
+        Ok(())
+    }
 
-    trans.commit().await?;
-    event!(Level::INFO, "Successfully initialized network info");
-    Ok(())
+    /// Records how much the counters of each interface went up since the last
+    /// sample and keeps the interface info up to date
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to update network data");
+        self.pending_counters = None;
+
+        let snapshot: NetworkSnapshot = match self.interfaces.take() {
+            Some(interfaces) => interfaces,
+            None => {
+                event!(Level::WARN, "No network information has been collected");
+                return Ok(());
+            }
+        };
+        // Interfaces can be added and removed at any time
+        sync_interfaces(conn, &snapshot).await?;
+
+        // Interfaces without earlier counters have nothing to compare against,
+        // so they are first recorded during the next collection
+        let network_stats: Vec<NetworkStat> = snapshot
+            .devices
+            .iter()
+            .filter_map(|device| {
+                self.last_counters
+                    .get(&device.name)
+                    .map(|last| get_network_stat(cur_time, device, last))
+            })
+            .collect();
+        if !network_stats.is_empty() {
+            event!(Level::DEBUG, "Starting to insert network stat info");
+            let mut network_stat_query: QueryBuilder<Sqlite> =
+                QueryBuilder::new("INSERT INTO NETWORKSTAT ");
+            network_stat_query.push_values(network_stats.iter(), |mut builder, stat| {
+                builder
+                    .push_bind(&stat.name)
+                    .push_bind(stat.timestamp)
+                    .push_bind(stat.bytes_recv)
+                    .push_bind(stat.bytes_sent)
+                    .push_bind(stat.packets_recv)
+                    .push_bind(stat.packets_sent)
+                    .push_bind(stat.err_recv)
+                    .push_bind(stat.err_sent)
+                    .push_bind(stat.drop_recv)
+                    .push_bind(stat.drop_sent)
+                    .push_bind(stat.fifo_recv)
+                    .push_bind(stat.fifo_sent)
+                    .push_bind(stat.frame_recv)
+                    .push_bind(stat.compressed_recv)
+                    .push_bind(stat.compressed_sent)
+                    .push_bind(stat.multicast_recv)
+                    .push_bind(stat.collisions)
+                    .push_bind(stat.bytes_recv_total)
+                    .push_bind(stat.bytes_sent_total)
+                    .push_bind(stat.packets_recv_total)
+                    .push_bind(stat.packets_sent_total)
+                    .push_bind(stat.err_recv_total)
+                    .push_bind(stat.err_sent_total)
+                    .push_bind(stat.drop_recv_total)
+                    .push_bind(stat.drop_sent_total);
+            });
+            network_stat_query
+                .push(";")
+                .build()
+                .execute(&mut *conn)
+                .await?;
+            event!(Level::DEBUG, "Finished inserting network stat info");
+        }
+        self.pending_counters = Some(index_counters(snapshot.devices));
+
+        event!(Level::DEBUG, "Finished updating network data");
+        Ok(())
+    }
+
+    /// Removes the interface metrics from before the cutoff along with any
+    /// removed interfaces that no longer have any metrics
+    #[instrument(skip(self, conn))]
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        sqlx::query("DELETE FROM NETWORKSTAT WHERE TIMESTAMP < ?;")
+            .bind(cutoff as i64)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            "DELETE FROM NETWORKINTERFACE WHERE NOT IS_PRESENT AND NOT EXISTS
+                (SELECT 1 FROM NETWORKSTAT ns WHERE ns.NAME = NETWORKINTERFACE.NAME);",
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Compares the next sample against the one that was just committed
+    fn committed(&mut self) {
+        if let Some(counters) = self.pending_counters.take() {
+            self.last_counters = counters;
+        }
+    }
 }
 
-/// Removes stas from interfaces that do not exist anymore
-#[instrument(skip(conn))]
-async fn clean_up_old_interfaces(
-    conn: &SqlitePool,
-    cur_interfaces: &Vec<DeviceStatus>,
+/// Brings the interface and address tables up to date with the current
+/// interfaces, where interfaces that were removed are kept along with their
+/// history
+#[instrument(skip(conn, snapshot))]
+async fn sync_interfaces(
+    conn: &mut SqliteConnection,
+    snapshot: &NetworkSnapshot,
 ) -> Result<(), NebulaError> {
-    event!(
-        Level::DEBUG,
-        "Starting to clean up old network interface data"
-    );
-
-    // Start by clearing the NETWORKSTAT table
-    let mut network_stat_delete: QueryBuilder<Sqlite> =
-        QueryBuilder::new("DELETE FROM NETWORKSTAT WHERE NAME NOT IN (");
-    let mut network_stat_separated = network_stat_delete.separated(", ");
-    for interface in cur_interfaces.iter() {
-        network_stat_separated.push_bind(&interface.name);
+    event!(Level::DEBUG, "Starting to update the network interfaces");
+    let cur_interfaces: &Vec<DeviceStatus> = &snapshot.devices;
+    if !cur_interfaces.is_empty() {
+        let mut interface_upsert: QueryBuilder<Sqlite> =
+            QueryBuilder::new("INSERT INTO NETWORKINTERFACE ");
+        interface_upsert.push_values(cur_interfaces.iter(), |mut builder, interface| {
+            let link: LinkInfo = snapshot
+                .links
+                .get(&interface.name)
+                .cloned()
+                .unwrap_or_default();
+            builder
+                .push_bind(&interface.name)
+                .push_bind(primary_address(&interface.name, &snapshot.addresses))
+                .push_bind(true)
+                .push_bind(link.mac_address)
+                .push_bind(link.mtu)
+                .push_bind(link.operstate)
+                .push_bind(link.speed);
+        });
+        interface_upsert.push(
+            " ON CONFLICT (NAME) DO UPDATE SET
+                IP_ADDR = excluded.IP_ADDR,
+                IS_PRESENT = excluded.IS_PRESENT,
+                MAC_ADDRESS = excluded.MAC_ADDRESS,
+                MTU = excluded.MTU,
+                OPERSTATE = excluded.OPERSTATE,
+                SPEED = excluded.SPEED;",
+        );
+        interface_upsert.build().execute(&mut *conn).await?;
+    }
+
+    let mut removed_update: QueryBuilder<Sqlite> =
+        QueryBuilder::new("UPDATE NETWORKINTERFACE SET IS_PRESENT = FALSE WHERE IS_PRESENT");
+    if !cur_interfaces.is_empty() {
+        removed_update.push(" AND NAME NOT IN (");
+        let mut removed_separated = removed_update.separated(", ");
+        for interface in cur_interfaces.iter() {
+            removed_separated.push_bind(&interface.name);
+        }
+        removed_separated.push_unseparated(")");
+    }
+    removed_update.push(";");
+    let removed: u64 = removed_update
+        .build()
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    if removed > 0 {
+        event!(Level::INFO, "{:?} network interfaces were removed", removed);
     }
-    network_stat_separated.push_unseparated(");");
-    network_stat_delete.build().execute(conn).await?;
-
-    // Next clear out the NETWORKINTERFACE table now that the
-    // foreign keys are taken care of
-    let mut network_delete: QueryBuilder<Sqlite> =
-        QueryBuilder::new("DELETE FROM NETWORKINTERFACE WHERE NAME NOT IN (");
-    let mut network_separated = network_delete.separated(", ");
-    for interface in cur_interfaces.iter() {
-        network_separated.push_bind(&interface.name);
+
+    // Addresses are only kept for the present, so they are replaced entirely.
+    // Addresses of interfaces that are not in the host's procfs are skipped.
+    sqlx::query("DELETE FROM INTERFACEADDRESS;")
+        .execute(&mut *conn)
+        .await?;
+    let names: HashSet<&String> = cur_interfaces.iter().map(|device| &device.name).collect();
+    let addresses: Vec<&InterfaceAddress> = snapshot
+        .addresses
+        .iter()
+        .filter(|address| names.contains(&address.name))
+        .collect();
+    if !addresses.is_empty() {
+        let mut address_insert: QueryBuilder<Sqlite> =
+            QueryBuilder::new("INSERT OR IGNORE INTO INTERFACEADDRESS ");
+        address_insert.push_values(addresses.iter(), |mut builder, address| {
+            builder
+                .push_bind(&address.name)
+                .push_bind(&address.address)
+                .push_bind(address.family)
+                .push_bind(address.prefix_length)
+                .push_bind(&address.scope);
+        });
+        address_insert.push(";");
+        address_insert.build().execute(&mut *conn).await?;
     }
-    network_separated.push_unseparated(");");
-    network_delete.build().execute(conn).await?;
 
-    event!(
-        Level::DEBUG,
-        "Finished cleaning up old network interface data"
-    );
+    event!(Level::DEBUG, "Finished updating the network interfaces");
     Ok(())
 }
 
-#[instrument(skip(conn))]
-pub async fn update_network_interface_data(
-    cur_time: u64,
-    conn: &SqlitePool,
-) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to update network data");
+/// Indexes the counters of each interface by the interface's name
+fn index_counters(devices: Vec<DeviceStatus>) -> HashMap<String, DeviceStatus> {
+    devices
+        .into_iter()
+        .map(|device| (device.name.clone(), device))
+        .collect()
+}
 
-    let db_interfaces: Vec<NetworkInterface> =
-        sqlx::query_as::<_, NetworkInterface>("SELECT * FROM NETWORKINTERFACE;")
-            .fetch_all(conn)
-            .await?;
+/// Gets how much each counter of an interface went up since the last sample,
+/// along with the current totals of the exported counters
+fn get_network_stat(cur_time: u64, cur: &DeviceStatus, last: &DeviceStatus) -> NetworkStat {
+    NetworkStat {
+        name: cur.name.clone(),
+        timestamp: cur_time as i64,
+        bytes_recv: counter_delta(cur.recv_bytes, last.recv_bytes),
+        bytes_sent: counter_delta(cur.sent_bytes, last.sent_bytes),
+        packets_recv: counter_delta(cur.recv_packets, last.recv_packets),
+        packets_sent: counter_delta(cur.sent_packets, last.sent_packets),
+        err_recv: counter_delta(cur.recv_errs, last.recv_errs),
+        err_sent: counter_delta(cur.sent_errs, last.sent_errs),
+        drop_recv: counter_delta(cur.recv_drop, last.recv_drop),
+        drop_sent: counter_delta(cur.sent_drop, last.sent_drop),
+        fifo_recv: counter_delta(cur.recv_fifo, last.recv_fifo),
+        fifo_sent: counter_delta(cur.sent_fifo, last.sent_fifo),
+        frame_recv: counter_delta(cur.recv_frame, last.recv_frame),
+        compressed_recv: counter_delta(cur.recv_compressed, last.recv_compressed),
+        compressed_sent: counter_delta(cur.sent_compressed, last.sent_compressed),
+        multicast_recv: counter_delta(cur.recv_multicast, last.recv_multicast),
+        collisions: counter_delta(cur.sent_colls, last.sent_colls),
+        bytes_recv_total: Some(cur.recv_bytes as i64),
+        bytes_sent_total: Some(cur.sent_bytes as i64),
+        packets_recv_total: Some(cur.recv_packets as i64),
+        packets_sent_total: Some(cur.sent_packets as i64),
+        err_recv_total: Some(cur.recv_errs as i64),
+        err_sent_total: Some(cur.sent_errs as i64),
+        drop_recv_total: Some(cur.recv_drop as i64),
+        drop_sent_total: Some(cur.sent_drop as i64),
+    }
+}
 
-    let cur_interfaces: Vec<DeviceStatus> = InterfaceDeviceStatus::current()?
-        .0
-        .values()
-        .cloned()
-        .collect();
-    let cur_arp: Vec<ARPEntry> = net::arp()?;
-
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-    for cur_interface in cur_interfaces.iter() {
-        let matching_db_interface: Vec<NetworkInterface> = db_interfaces
-            .clone()
-            .into_iter()
-            .filter(|i| i.name == cur_interface.name)
-            .collect();
+/// Gets how much a counter went up, where a counter that went down was reset
+/// when the interface was recreated and has counted up from 0 since
+pub(super) fn counter_delta(cur: u64, last: u64) -> i64 {
+    if cur >= last {
+        (cur - last) as i64
+    } else {
+        cur as i64
+    }
+}
 
-        let arp_entry_vec: Vec<ARPEntry> = cur_arp
-            .clone()
-            .into_iter()
-            .filter(|entry| entry.device == cur_interface.name)
-            .collect();
-        let device_ip: Option<String> = if arp_entry_vec.is_empty() {
-            None
-        } else {
-            Some(arp_entry_vec[0].ip_address.to_string())
-        };
+/// Reads the link details of an interface from its sysfs directory, where
+/// anything that is not reported is left unknown
+fn read_link_info(link_dir: &Path) -> LinkInfo {
+    let read_value = |file: &str| -> Option<String> {
+        fs::read_to_string(link_dir.join(file))
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    LinkInfo {
+        mac_address: read_value("address"),
+        mtu: read_value("mtu").and_then(|mtu| mtu.parse::<i64>().ok()),
+        operstate: read_value("operstate"),
+        // Reading the speed fails for links that are down, and virtual
+        // interfaces report -1
+        speed: read_value("speed")
+            .and_then(|speed| speed.parse::<i64>().ok())
+            .filter(|speed| *speed >= 0),
+    }
+}
 
-        if matching_db_interface.is_empty() {
-            event!(
-                Level::DEBUG,
-                "Found new network interface {:?}",
-                cur_interface.name
-            );
-            sqlx::query("INSERT INTO NETWORKINTERFACE VALUES (?, ?);")
-                .bind(&cur_interface.name)
-                .bind(device_ip)
-                .execute(conn)
-                .await?;
-        } else if matching_db_interface[0].ip_addr != device_ip {
-            event!(
-                Level::DEBUG,
-                "New IP found for network interface {:?}",
-                cur_interface.name
-            );
-            sqlx::query("UPDATE NETWORKINTERFACE SET IP_ADDR = ? WHERE NAME = ?;")
-                .bind(device_ip)
-                .bind(&cur_interface.name)
-                .execute(conn)
-                .await?;
-        }
+/// Gets the IPv4 addresses of every interface in the host's network namespace
+///
+/// IPv4 addresses are not listed anywhere in procfs, and the kernel only lists
+/// the ones in the caller's namespace. A monitor in a container of its own has
+/// a helper thread join the host's namespace to list them, which takes the
+/// CAP_SYS_ADMIN capability.
+fn read_ipv4_addresses(root: &SystemRoot) -> io::Result<Vec<InterfaceAddress>> {
+    let host_ns: File = File::open(root.host_ns_path("ns/net"))?;
+    let host_ns_metadata: fs::Metadata = host_ns.metadata()?;
+    let own_ns_metadata: fs::Metadata = fs::metadata("/proc/thread-self/ns/net")?;
+    if (host_ns_metadata.dev(), host_ns_metadata.ino())
+        == (own_ns_metadata.dev(), own_ns_metadata.ino())
+    {
+        return list_ipv4_addresses();
     }
+    // Only the helper thread switches namespaces, and it ends with them
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                sched::setns(&host_ns, CloneFlags::CLONE_NEWNET)?;
+                list_ipv4_addresses()
+            })
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("The helper thread panicked")))
+    })
+}
 
-    event!(Level::DEBUG, "Starting to insert network stat info");
-    let mut network_stat_query: QueryBuilder<Sqlite> =
-        QueryBuilder::new("INSERT INTO NETWORKSTAT ");
-    network_stat_query.push_values(cur_interfaces.iter(), |mut builder, interface| {
-        builder
-            .push_bind(&interface.name)
-            .push_bind(cur_time as i64)
-            .push_bind((interface.recv_bytes / 1000) as i64)
-            .push_bind((interface.sent_bytes / 1000) as i64)
-            .push_bind(interface.recv_packets as i64)
-            .push_bind(interface.sent_packets as i64)
-            .push_bind(interface.recv_errs as i64)
-            .push_bind(interface.sent_errs as i64);
-    });
-    network_stat_query.push(";").build().execute(conn).await?;
-    event!(Level::DEBUG, "Finished inserting network stat info");
-
-    clean_up_old_interfaces(conn, &cur_interfaces).await?;
-
-    trans.commit().await?;
-    event!(Level::DEBUG, "Finished updating network data");
-    Ok(())
+/// Gets the IPv4 addresses of every interface in the calling thread's network
+/// namespace
+fn list_ipv4_addresses() -> io::Result<Vec<InterfaceAddress>> {
+    let if_addrs: InterfaceAddressIterator = ifaddrs::getifaddrs()?;
+    Ok(if_addrs
+        .filter_map(|if_addr| {
+            let address: Ipv4Addr = *SocketAddrV4::from(*if_addr.address?.as_sockaddr_in()?).ip();
+            let netmask: u32 = if_addr
+                .netmask
+                .and_then(|netmask| netmask.as_sockaddr_in().map(|netmask| netmask.ip()))
+                .unwrap_or(u32::MAX);
+            Some(InterfaceAddress {
+                name: if_addr.interface_name,
+                address: address.to_string(),
+                family: 4,
+                prefix_length: netmask.count_ones() as i64,
+                scope: ipv4_scope(&address).to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Gets the scope of an IPv4 address, which the kernel does not report
+/// alongside the address
+fn ipv4_scope(address: &Ipv4Addr) -> &'static str {
+    if address.is_loopback() {
+        "host"
+    } else if address.is_link_local() {
+        "link"
+    } else {
+        "global"
+    }
+}
+
+/// Gets the IPv6 addresses of every interface in the host's network namespace,
+/// which has none if IPv6 is disabled
+fn read_ipv6_addresses(root: &SystemRoot) -> Result<Vec<InterfaceAddress>, NebulaError> {
+    let if_inet6: String = match fs::read_to_string(root.host_ns_path("net/if_inet6")) {
+        Ok(if_inet6) => if_inet6,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(if_inet6.lines().filter_map(parse_if_inet6_line).collect())
+}
+
+/// Parses a single line of /proc/net/if_inet6, returning None for lines that
+/// are not in the expected format
+fn parse_if_inet6_line(line: &str) -> Option<InterfaceAddress> {
+    // Order: address, interface index, prefix length, scope, flags, name,
+    // where everything but the name is in hex
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 6 || fields[0].len() != 32 {
+        return None;
+    }
+    let address: Ipv6Addr = Ipv6Addr::from(u128::from_str_radix(fields[0], 16).ok()?);
+    let prefix_length: i64 = i64::from_str_radix(fields[2], 16).ok()?;
+    let scope: &str = match u8::from_str_radix(fields[3], 16).ok()? & 0xf0 {
+        0x00 => "global",
+        0x10 => "host",
+        0x20 => "link",
+        0x40 => "site",
+        _ => "other",
+    };
+    Some(InterfaceAddress {
+        name: fields[5].to_string(),
+        address: address.to_string(),
+        family: 6,
+        prefix_length,
+        scope: scope.to_string(),
+    })
+}
+
+/// Picks the address to show for an interface, preferring global addresses
+/// and IPv4 over IPv6
+fn primary_address(name: &str, addresses: &[InterfaceAddress]) -> Option<String> {
+    addresses
+        .iter()
+        .filter(|address| address.name == name)
+        .min_by_key(|address| (address.scope != "global", address.family != 4))
+        .map(|address| address.address.clone())
 }
 
 #[cfg(test)]
 mod tests {
-    use models::tables::NetworkStat;
+    use models::tables::{NetworkInterface, NetworkStat};
 
     use super::*;
+    use sqlx::{SqlitePool, Transaction};
+    use std::env;
     use std::io;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+    use std::process;
+
+    /// Reads the fixture interfaces with a fixed IPv4 address, since the
+    /// fixture has no network namespace to read them from
+    fn fixture_snapshot() -> Result<NetworkSnapshot, NebulaError> {
+        let mut snapshot: NetworkSnapshot =
+            NetworkCollector::new(SystemRoot::fixture()).read_interfaces()?;
+        snapshot.addresses.push(InterfaceAddress {
+            name: "eth0".to_string(),
+            address: "192.0.2.1".to_string(),
+            family: 4,
+            prefix_length: 24,
+            scope: "global".to_string(),
+        });
+        Ok(snapshot)
+    }
 
-    #[sqlx::test(fixtures("networkTest"))]
-    async fn test_clean_up_old_interface_data(pool: SqlitePool) -> Result<(), NebulaError> {
+    /// Gets the IPv6 addresses in the database, which only come from the
+    /// fixture
+    async fn get_ipv6_addresses(pool: &SqlitePool) -> Result<Vec<InterfaceAddress>, NebulaError> {
+        Ok(sqlx::query_as::<_, InterfaceAddress>(
+            "SELECT * FROM INTERFACEADDRESS WHERE FAMILY = 6 ORDER BY NAME, ADDRESS;",
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    #[test]
+    fn test_parse_if_inet6_line() {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let cur_interfaces: Vec<DeviceStatus> = InterfaceDeviceStatus::current()?
-            .0
-            .values()
-            .cloned()
-            .collect();
+        assert_eq!(
+            parse_if_inet6_line("fe80000000000000004200fffeac1102 02 40 20 80     eth0"),
+            Some(InterfaceAddress {
+                name: "eth0".to_string(),
+                address: "fe80::42:ff:feac:1102".to_string(),
+                family: 6,
+                prefix_length: 64,
+                scope: "link".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_if_inet6_line("00000000000000000000000000000001 01 80 10 80       lo")
+                .map(|address| (address.address, address.prefix_length, address.scope)),
+            Some(("::1".to_string(), 128, "host".to_string()))
+        );
+        assert_eq!(parse_if_inet6_line("not an address"), None);
 
-        clean_up_old_interfaces(&pool, &cur_interfaces).await?;
+        assert_eq!(ipv4_scope(&Ipv4Addr::new(127, 0, 0, 1)), "host");
+        assert_eq!(ipv4_scope(&Ipv4Addr::new(169, 254, 1, 2)), "link");
+        assert_eq!(ipv4_scope(&Ipv4Addr::new(192, 0, 2, 1)), "global");
+    }
+
+    #[test]
+    fn test_read_ipv4_addresses() -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
 
-        assert!(sqlx::query("SELECT * FROM NETWORKINTERFACE;")
-            .fetch_all(&pool)
-            .await?
-            .is_empty());
-        assert!(sqlx::query("SELECT * FROM NETWORKSTAT;")
-            .fetch_all(&pool)
-            .await?
-            .is_empty());
+        // The addresses of the monitor's own namespace are not mixed in when
+        // the host's namespace cannot be read
+        let mut collector: NetworkCollector = NetworkCollector::new(SystemRoot::fixture());
+        let snapshot: NetworkSnapshot = collector.read_interfaces()?;
+        assert!(snapshot.addresses.iter().all(|address| address.family == 6));
+        assert!(!snapshot.addresses.is_empty());
+        assert!(collector.ipv4_warned);
+
+        // A host root whose PID 1 shares the monitor's namespace is read
+        // directly
+        let test_dir: PathBuf =
+            env::temp_dir().join(format!("nebula-ipv4-addresses-{}", process::id()));
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(test_dir.join("proc/1/ns"))?;
+        symlink("/proc/self/ns/net", test_dir.join("proc/1/ns/net"))?;
+        let addresses: Vec<InterfaceAddress> =
+            read_ipv4_addresses(&SystemRoot::with_host_root(&test_dir))?;
+        fs::remove_dir_all(&test_dir)?;
+        assert!(addresses
+            .iter()
+            .any(|address| address.address == "127.0.0.1" && address.scope == "host"));
+        assert!(addresses.iter().all(|address| address.family == 4));
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("networkTest"))]
-    async fn test_init_interface_data(pool: SqlitePool) -> Result<(), NebulaError> {
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("networkTest"))]
+    async fn test_sync_interfaces(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let cur_interfaces: Vec<DeviceStatus> = InterfaceDeviceStatus::current()?
-            .0
-            .values()
-            .cloned()
-            .collect();
+        sync_interfaces(&mut *pool.acquire().await?, &fixture_snapshot()?).await?;
+
+        // The old interface is kept as removed along with its history
+        let old_interface: NetworkInterface = sqlx::query_as::<_, NetworkInterface>(
+            "SELECT * FROM NETWORKINTERFACE WHERE NAME = 'old-interface';",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(!old_interface.is_present);
+        assert_eq!(
+            sqlx::query("SELECT * FROM NETWORKSTAT;")
+                .fetch_all(&pool)
+                .await?
+                .len(),
+            1
+        );
+
+        // The global IPv4 address is preferred over the IPv6 ones
+        let db_interfaces: Vec<NetworkInterface> = sqlx::query_as::<_, NetworkInterface>(
+            "SELECT * FROM NETWORKINTERFACE WHERE IS_PRESENT ORDER BY NAME;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(db_interfaces.len(), 2);
+        assert_eq!(db_interfaces[0].ip_addr, Some("192.0.2.1".to_string()));
+        assert_eq!(db_interfaces[1].ip_addr, Some("::1".to_string()));
+
+        let addresses: Vec<InterfaceAddress> = sqlx::query_as::<_, InterfaceAddress>(
+            "SELECT * FROM INTERFACEADDRESS ORDER BY NAME, FAMILY, ADDRESS;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(addresses.len(), 4);
+        assert_eq!(addresses[0].address, "192.0.2.1");
+        assert_eq!(addresses[0].prefix_length, 24);
+
+        // Everything is removed when there are no interfaces
+        let empty: NetworkSnapshot = NetworkSnapshot {
+            devices: Vec::new(),
+            links: HashMap::new(),
+            addresses: Vec::new(),
+        };
+        sync_interfaces(&mut *pool.acquire().await?, &empty).await?;
+        let present: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM NETWORKINTERFACE WHERE IS_PRESENT;")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(present, 0);
+        let address_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM INTERFACEADDRESS;")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(address_count, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("networkTest"))]
+    async fn test_init_interface_data(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
 
-        init_network_data(&pool).await?;
+        NetworkCollector::new(SystemRoot::fixture())
+            .init(&mut *pool.acquire().await?)
+            .await?;
 
+        let db_interfaces: Vec<NetworkInterface> = sqlx::query_as::<_, NetworkInterface>(
+            "SELECT * FROM NETWORKINTERFACE WHERE IS_PRESENT ORDER BY NAME;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(db_interfaces.len(), 2);
+        assert_eq!(db_interfaces[0].name, "eth0");
+        assert!(db_interfaces[0].ip_addr.is_some());
+        assert_eq!(
+            db_interfaces[0].mac_address,
+            Some("02:42:ac:11:00:02".to_string())
+        );
+        assert_eq!(db_interfaces[0].mtu, Some(1500));
+        assert_eq!(db_interfaces[0].operstate, Some("up".to_string()));
+        assert_eq!(db_interfaces[0].speed, Some(1000));
+        assert_eq!(db_interfaces[1].name, "lo");
+        assert_eq!(db_interfaces[1].mtu, Some(65536));
+        assert_eq!(db_interfaces[1].operstate, Some("unknown".to_string()));
+        assert_eq!(db_interfaces[1].speed, None);
+
+        let addresses: Vec<InterfaceAddress> = get_ipv6_addresses(&pool).await?;
+        assert_eq!(addresses.len(), 3);
+        assert_eq!(addresses[0].name, "eth0");
+        assert_eq!(addresses[0].address, "2001:db8::5");
+        assert_eq!(addresses[0].prefix_length, 64);
+        assert_eq!(addresses[0].scope, "global");
+        assert_eq!(addresses[1].address, "fe80::42:ff:feac:1102");
+        assert_eq!(addresses[1].scope, "link");
+        assert_eq!(addresses[2].name, "lo");
+        assert_eq!(addresses[2].address, "::1");
+        assert_eq!(addresses[2].scope, "host");
+
+        // The old interface keeps its history until it is pruned
         assert_eq!(
-            sqlx::query("SELECT * FROM NETWORKINTERFACE;")
+            sqlx::query("SELECT * FROM NETWORKSTAT;")
                 .fetch_all(&pool)
                 .await?
                 .len(),
-            cur_interfaces.len()
+            1
         );
-        assert!(sqlx::query("SELECT * FROM NETWORKSTAT;")
-            .fetch_all(&pool)
-            .await?
-            .is_empty());
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("networkTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("networkTest"))]
+    async fn test_first_network_sample(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Nothing is recorded without earlier counters to compare against
+        let mut collector: NetworkCollector = NetworkCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector
+            .persist(123456790, &mut *pool.acquire().await?)
+            .await?;
+        let stat_count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM NETWORKSTAT WHERE TIMESTAMP = 123456790;")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(stat_count, 0);
+        collector.committed();
+
+        // The counters from the previous sample are used from then on
+        collector.collect()?;
+        collector
+            .persist(123456791, &mut *pool.acquire().await?)
+            .await?;
+        let db_stats: Vec<NetworkStat> = sqlx::query_as::<_, NetworkStat>(
+            "SELECT * FROM NETWORKSTAT WHERE TIMESTAMP = 123456791 ORDER BY NAME;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(db_stats.len(), 2);
+        assert_eq!(db_stats[0].bytes_recv, 0);
+        assert_eq!(db_stats[1].packets_sent, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("networkTest"))]
+    async fn test_network_sample_rolled_back(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let mut collector: NetworkCollector = NetworkCollector::new(SystemRoot::fixture());
+        let mut last_counters: HashMap<String, DeviceStatus> =
+            index_counters(collector.read_interfaces()?.devices);
+        if let Some(eth0) = last_counters.get_mut("eth0") {
+            eth0.recv_bytes = 134000;
+        }
+        collector.last_counters = last_counters;
+
+        // A tick that is rolled back is not compared against
+        collector.collect()?;
+        let mut trans: Transaction<Sqlite> = pool.begin().await?;
+        collector.persist(123456790, &mut trans).await?;
+        trans.rollback().await?;
+
+        collector.collect()?;
+        collector
+            .persist(123456791, &mut *pool.acquire().await?)
+            .await?;
+        collector.committed();
+        let db_stats: Vec<NetworkStat> =
+            sqlx::query_as::<_, NetworkStat>("SELECT * FROM NETWORKSTAT ORDER BY TIMESTAMP;")
+                .fetch_all(&pool)
+                .await?
+                .into_iter()
+                .filter(|stat| stat.name == "eth0")
+                .collect();
+        assert_eq!(db_stats.len(), 1);
+        assert_eq!(db_stats[0].timestamp, 123456791);
+        assert_eq!(db_stats[0].bytes_recv, 741);
+
+        // The committed sample is compared against from then on
+        collector.collect()?;
+        collector
+            .persist(123456792, &mut *pool.acquire().await?)
+            .await?;
+        let bytes_recv: i64 = sqlx::query_scalar(
+            "SELECT BYTES_RECV FROM NETWORKSTAT WHERE NAME = 'eth0' AND TIMESTAMP = 123456792;",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(bytes_recv, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("networkTest"))]
     async fn test_update_network_data(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let cur_interfaces: Vec<DeviceStatus> = InterfaceDeviceStatus::current()?
-            .0
-            .values()
-            .cloned()
-            .collect();
+        let mut collector: NetworkCollector = NetworkCollector::new(SystemRoot::fixture());
+        let snapshot: NetworkSnapshot = collector.read_interfaces()?;
 
         let mut pre_insert_query: QueryBuilder<Sqlite> =
             QueryBuilder::new("INSERT INTO NETWORKINTERFACE ");
-        pre_insert_query.push_values(cur_interfaces.iter(), |mut builder, interface| {
+        pre_insert_query.push_values(snapshot.devices.iter(), |mut builder, interface| {
             builder
                 .push_bind(&interface.name)
-                .push_bind(Some("1.2.3.4"));
+                .push_bind(Some("1.2.3.4"))
+                // Interfaces that come back are present again
+                .push_bind(false)
+                .push_bind(None::<String>)
+                .push_bind(Some(1280))
+                .push_bind(Some("down"))
+                .push_bind(None::<i64>);
         });
         pre_insert_query.push(";").build().execute(&pool).await?;
+        sqlx::query("INSERT INTO INTERFACEADDRESS VALUES ('eth0', '1.2.3.4', 4, 8, 'global');")
+            .execute(&pool)
+            .await?;
 
-        let cur_time: u64 = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        update_network_interface_data(cur_time, &pool).await?;
-
-        let db_interfaces: Vec<NetworkInterface> =
-            sqlx::query_as::<_, NetworkInterface>("SELECT * FROM NETWORKINTERFACE;")
-                .fetch_all(&pool)
-                .await?;
-        assert_eq!(db_interfaces.len(), cur_interfaces.len());
-        for interface in db_interfaces.iter() {
-            assert_ne!(interface.ip_addr, Some("1.2.3.4".to_string()));
+        // Earlier counters, where the loopback's counters were reset since
+        let mut last_counters: HashMap<String, DeviceStatus> = index_counters(snapshot.devices);
+        if let Some(eth0) = last_counters.get_mut("eth0") {
+            eth0.recv_bytes = 134000;
+            eth0.sent_bytes = 14000;
+            eth0.recv_packets = 150;
+            eth0.sent_packets = 160;
+            eth0.recv_errs = 0;
+            eth0.recv_drop = 0;
+            eth0.recv_multicast = 0;
         }
+        if let Some(lo) = last_counters.get_mut("lo") {
+            lo.recv_bytes = u64::MAX;
+        }
+        collector.last_counters = last_counters;
 
-        let db_stats: Vec<NetworkStat> =
-            sqlx::query_as::<_, NetworkStat>("SELECT * FROM NETWORKSTAT;")
+        collector.collect()?;
+        collector
+            .persist(123456790, &mut *pool.acquire().await?)
+            .await?;
+
+        let db_interfaces: Vec<NetworkInterface> = sqlx::query_as::<_, NetworkInterface>(
+            "SELECT * FROM NETWORKINTERFACE WHERE IS_PRESENT ORDER BY NAME;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(db_interfaces.len(), 2);
+        assert_ne!(db_interfaces[0].ip_addr, Some("1.2.3.4".to_string()));
+        assert_eq!(db_interfaces[0].mtu, Some(1500));
+        assert_eq!(db_interfaces[0].operstate, Some("up".to_string()));
+        assert_eq!(db_interfaces[0].speed, Some(1000));
+
+        // Addresses that are gone are not kept around
+        let old_address: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM INTERFACEADDRESS WHERE ADDRESS = '1.2.3.4';")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(old_address, 0);
+        assert_eq!(get_ipv6_addresses(&pool).await?.len(), 3);
+
+        let db_stats: Vec<NetworkStat> = sqlx::query_as::<_, NetworkStat>(
+            "SELECT * FROM NETWORKSTAT WHERE TIMESTAMP = 123456790 ORDER BY NAME;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(db_stats.len(), 2);
+        assert_eq!(db_stats[0].name, "eth0");
+        assert_eq!(db_stats[0].timestamp, 123456790);
+        assert_eq!(db_stats[0].bytes_recv, 741);
+        assert_eq!(db_stats[0].bytes_sent, 942);
+        assert_eq!(db_stats[0].packets_recv, 8);
+        assert_eq!(db_stats[0].packets_sent, 1);
+        assert_eq!(db_stats[0].err_recv, 1);
+        assert_eq!(db_stats[0].drop_recv, 2);
+        assert_eq!(db_stats[0].multicast_recv, 4);
+        assert_eq!(db_stats[0].collisions, 0);
+        // The kernel's totals are kept as they are
+        assert_eq!(db_stats[0].bytes_recv_total, Some(134741));
+        assert_eq!(db_stats[0].bytes_sent_total, Some(14942));
+        assert_eq!(db_stats[0].packets_recv_total, Some(158));
+        assert_eq!(db_stats[0].packets_sent_total, Some(161));
+        assert_eq!(db_stats[0].err_recv_total, Some(1));
+        assert_eq!(db_stats[0].drop_recv_total, Some(2));
+        assert_eq!(db_stats[1].name, "lo");
+        assert_eq!(db_stats[1].bytes_recv, 16277602);
+        assert_eq!(db_stats[1].bytes_sent, 0);
+        assert_eq!(db_stats[1].bytes_recv_total, Some(16277602));
+
+        // Removed interfaces are deleted once all of their metrics are pruned
+        collector
+            .prune(123456790, &mut *pool.acquire().await?)
+            .await?;
+        let names: Vec<String> =
+            sqlx::query_scalar("SELECT NAME FROM NETWORKINTERFACE ORDER BY NAME;")
                 .fetch_all(&pool)
                 .await?;
-        assert_eq!(db_stats.len(), cur_interfaces.len());
+        assert_eq!(names, vec!["eth0", "lo"]);
 
         Ok(())
     }
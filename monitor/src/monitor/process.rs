@@ -1,299 +1,291 @@
-use procfs::process::{self, Stat, StatM};
-use procfs::WithCurrentSystemInfo;
-use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
-use std::{cmp::Ordering, path::PathBuf};
+use async_trait::async_trait;
+use procfs::process::{self, Io, Stat, StatM};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
 use tracing::{event, instrument, Level};
 
 use models::{error::NebulaError, tables::Process};
 
+use super::boot::{get_boot_id, get_boot_time};
+use super::collector::{Collector, MAX_ROWS_PER_STATEMENT};
+use super::system::SystemRoot;
+
 #[derive(Debug)]
 struct ProcfsProcess {
     process: process::Process,
     stat: Stat,
     statm: StatM,
+    /// Storage I/O of the process, which is only readable for processes that
+    /// the monitor is allowed to trace
+    io: Option<Io>,
     exe: PathBuf,
+    /// Process start time as a Unix epoch timestamp
+    start_time: i64,
+    /// UID of the user that owns the process
+    uid: u32,
 }
 
-impl TryFrom<process::Process> for ProcfsProcess {
-    type Error = NebulaError;
-
-    fn try_from(value: process::Process) -> Result<Self, Self::Error> {
+impl ProcfsProcess {
+    /// Reads the data of a process, where the boot time of the system it is
+    /// running on is used to compute its start time
+    fn new(process: process::Process, boot_time: u64) -> Result<Self, NebulaError> {
+        let stat: Stat = process.stat()?;
+        // The start time is stored as the number of clock ticks after boot
+        let start_time: i64 = (boot_time + stat.starttime / procfs::ticks_per_second()) as i64;
         Ok(Self {
-            stat: value.stat()?,
-            statm: value.statm()?,
-            exe: value.exe()?,
-            process: value,
+            stat,
+            statm: process.statm()?,
+            io: process.io().ok(),
+            exe: process.exe()?,
+            start_time,
+            uid: process.uid()?,
+            process,
         })
     }
 }
 
-impl From<ProcfsProcess> for Process {
-    fn from(value: ProcfsProcess) -> Self {
-        Process {
-            pid: value.process.pid() as u32,
-            exec: value.exe.to_str().unwrap().to_string(),
-            start_time: value.stat.starttime().get().unwrap().timestamp(),
-            is_alive: value.process.is_alive(),
-            // User time + system time are in Jiffies, so have to convert to seconds
-            init_total_cpu: (value.stat.utime + value.stat.stime) as f32
-                / procfs::ticks_per_second() as f32,
-        }
-    }
-}
-
 impl From<&ProcfsProcess> for Process {
     fn from(value: &ProcfsProcess) -> Self {
         Process {
             pid: value.process.pid() as u32,
-            exec: value.exe.to_str().unwrap().to_string(),
-            start_time: value.stat.starttime().get().unwrap().timestamp(),
+            exec: value.exe.to_string_lossy().to_string(),
+            start_time: value.start_time,
             is_alive: value.process.is_alive(),
             // User time + system time are in Jiffies, so have to convert to seconds
             init_total_cpu: (value.stat.utime + value.stat.stime) as f32
                 / procfs::ticks_per_second() as f32,
+            boot_id: None,
+            uid: Some(value.uid),
+            user_name: None,
         }
     }
 }
 
-/// Sets up the database with the updated process data
-#[instrument(skip(conn))]
-pub async fn init_process_data(conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to initialize process data");
+/// Snapshot of a process and its metrics, which does not hold on to the
+/// process' procfs handle
+#[derive(Debug, Clone)]
+struct ProcessSample {
+    /// Process metadata, where the initial CPU time is the current CPU time
+    process: Process,
+    /// CPU core the process last ran on
+    cpu_core: Option<i32>,
+    /// Amount of virtual memory in bytes
+    virtual_memory: i64,
+    /// Amount of resident memory in bytes
+    resident_memory: i64,
+    /// Amount of shared memory in bytes
+    shared_memory: i64,
+    /// Total bytes read from storage
+    io_read_bytes: Option<i64>,
+    /// Total bytes written to storage
+    io_write_bytes: Option<i64>,
+}
 
-    let cur_processes: Vec<Process> = get_all_processes()?
-        .into_iter()
-        .map(Process::try_from)
-        .filter_map(|p| p.ok())
-        .collect();
-    let db_processes: Vec<Process> = get_processes_in_db(conn).await?;
-
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-
-    let mut cur_index: usize = 0;
-    let mut db_index: usize = 0;
-    while cur_index < cur_processes.len() && db_index < db_processes.len() {
-        let cur_proc: &Process = &cur_processes[cur_index];
-        let db_proc: &Process = &db_processes[db_index];
-
-        match cur_proc.pid.cmp(&db_proc.pid) {
-            Ordering::Equal => {
-                // There was likely a system restart, so have to clean out
-                // the old process' data before inserting the new process
-                if cur_proc.start_time != db_proc.start_time {
-                    event!(
-                        Level::DEBUG,
-                        "Found old process in database to replace with PID {:?}",
-                        db_proc.pid
-                    );
-                    sqlx::query("DELETE FROM PROCSTAT WHERE PID = ?;")
-                        .bind(db_proc.pid)
-                        .execute(conn)
-                        .await?;
-
-                    // This will delete the old process and write the new one
-                    // with only 1 query
-                    sqlx::query("INSERT OR REPLACE INTO PROCESS VALUES (?, ?, ?, ?, ?)")
-                        .bind(cur_proc.pid)
-                        .bind(&cur_proc.exec)
-                        .bind(cur_proc.start_time)
-                        .bind(cur_proc.is_alive)
-                        .bind(cur_proc.init_total_cpu)
-                        .execute(conn)
-                        .await?;
-                } else {
-                    event!(
-                        Level::DEBUG,
-                        "Found existing process that is still running with PID {:?}",
-                        cur_proc.pid
-                    );
-                }
-
-                cur_index += 1;
-                db_index += 1;
-            }
-            Ordering::Less => {
-                event!(
-                    Level::DEBUG,
-                    "Found process to insert with PID {:?}",
-                    cur_proc.pid
-                );
-                // The new process has not been recorded yet, so insert its init data
-                sqlx::query("INSERT INTO PROCESS VALUES (?, ?, ?, ?, ?)")
-                    .bind(cur_proc.pid)
-                    .bind(&cur_proc.exec)
-                    .bind(cur_proc.start_time)
-                    .bind(cur_proc.is_alive)
-                    .bind(cur_proc.init_total_cpu)
-                    .execute(conn)
-                    .await?;
-
-                cur_index += 1;
-            }
-            Ordering::Greater => {
-                event!(
-                    Level::DEBUG,
-                    "Found dead process in database with PID {:?}",
-                    db_proc.pid
-                );
-                // The old process has died since startup, so just mark it as not alive
-                sqlx::query("UPDATE PROCESS SET IS_ALIVE = FALSE WHERE PID = ?")
-                    .bind(db_proc.pid)
-                    .execute(conn)
-                    .await?;
-                db_index += 1;
-            }
+impl From<ProcfsProcess> for ProcessSample {
+    fn from(value: ProcfsProcess) -> Self {
+        ProcessSample {
+            process: Process::from(&value),
+            cpu_core: value.stat.processor,
+            // Statm stores data in pages, and page_size returns bytes
+            virtual_memory: (value.statm.size * procfs::page_size()) as i64,
+            resident_memory: (value.statm.resident * procfs::page_size()) as i64,
+            shared_memory: (value.statm.shared * procfs::page_size()) as i64,
+            io_read_bytes: value.io.as_ref().map(|io| io.read_bytes as i64),
+            io_write_bytes: value.io.as_ref().map(|io| io.write_bytes as i64),
         }
     }
+}
 
-    if cur_index < cur_processes.len() {
-        let mut remaning_proc_inserts: QueryBuilder<Sqlite> =
-            QueryBuilder::new("INSERT INTO PROCESS ");
-
-        remaning_proc_inserts.push_values(
-            cur_processes[cur_index..].iter(),
-            |mut builder, new_proc| {
-                event!(
-                    Level::DEBUG,
-                    "Found process to insert with PID {:?}",
-                    new_proc.pid
-                );
-                // The new process has not been recorded yet, so insert its init data
-                builder
-                    .push_bind(new_proc.pid)
-                    .push_bind(&new_proc.exec)
-                    .push_bind(new_proc.start_time)
-                    .push_bind(new_proc.is_alive)
-                    .push_bind(new_proc.init_total_cpu);
-            },
-        );
-        remaning_proc_inserts.push(";");
-        remaning_proc_inserts.build().execute(conn).await?;
-    }
+/// Collector for the metrics of every process running on the system
+#[derive(Debug, Default)]
+pub struct ProcessCollector {
+    /// Where to read the processes from
+    root: SystemRoot,
+    /// Processes found during the last collection
+    processes: Option<Vec<ProcessSample>>,
+}
 
-    // We have more processes to update for being dead
-    if db_index < db_processes.len() {
-        event!(
-            Level::DEBUG,
-            "Setting all remaining unknown processes to be dead"
-        );
-        let mut update_dead_processes: QueryBuilder<Sqlite> =
-            QueryBuilder::new("UPDATE PROCESS SET IS_ALIVE = FALSE WHERE PID IN (");
-        let mut update_dead_separated = update_dead_processes.separated(", ");
-        for db_proc in db_processes[db_index..].iter() {
-            update_dead_separated.push_bind(db_proc.pid);
+impl ProcessCollector {
+    /// Creates a new process collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        ProcessCollector {
+            root,
+            processes: None,
         }
-        update_dead_separated.push_unseparated(");");
-        update_dead_processes.build().execute(conn).await?;
     }
-
-    trans.commit().await?;
-    event!(Level::INFO, "Finished initializing process data");
-    Ok(())
 }
 
-/// Adds updated process information to the database, while cleaning up any old
-/// data it finds along the way
-#[instrument(skip(conn))]
-pub async fn update_process_data(cur_time: u64, conn: &SqlitePool) -> Result<(), NebulaError> {
-    event!(Level::INFO, "Starting to update process data");
-    let cur_processes: Vec<ProcfsProcess> = get_all_processes()?;
-    let db_processes: Vec<Process> = get_processes_in_db(conn).await?;
+#[async_trait]
+impl Collector for ProcessCollector {
+    fn name(&self) -> &'static str {
+        "process"
+    }
 
-    let trans: Transaction<Sqlite> = conn.begin().await?;
-    for proc in cur_processes.iter() {
-        let proc_metadata: Process = proc.into();
+    /// Sets up the database with the updated process data
+    #[instrument(skip(self, conn))]
+    async fn init(&mut self, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to initialize process data");
 
-        let old_process_vec: Vec<Process> = db_processes
-            .clone()
+        let cur_processes: Vec<Process> = get_all_processes(&self.root)?
             .into_iter()
-            .filter(|old_proc| {
-                old_proc.pid == proc_metadata.pid && old_proc.start_time != proc_metadata.start_time
-            })
+            .map(|sample| sample.process)
             .collect();
+        sync_processes(conn, &cur_processes).await?;
 
-        let db_process_pids: Vec<u32> = db_processes.iter().map(|db_proc| db_proc.pid).collect();
+        event!(Level::INFO, "Finished initializing process data");
+        Ok(())
+    }
 
-        // We have some really old data that has to be cleaned up first
-        // This case is very rare and a batch process should not be needed
-        if !old_process_vec.is_empty() {
-            event!(
-                Level::DEBUG,
-                "Replacing old process in database with PID {:?}",
-                old_process_vec[0].pid
-            );
-            sqlx::query("DELETE FROM PROCSTAT WHERE PID = ?;")
-                .bind(old_process_vec[0].pid)
-                .execute(conn)
-                .await?;
+    /// Reads all of the current processes from procfs
+    #[instrument(skip(self))]
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        self.processes = Some(get_all_processes(&self.root)?);
+        Ok(())
+    }
 
-            // This will delete the old process and write the new one
-            // with only 1 query
-            sqlx::query("INSERT OR REPLACE INTO PROCESS VALUES (?, ?, ?, ?, ?);")
-                .bind(proc_metadata.pid)
-                .bind(&proc_metadata.exec)
-                .bind(proc_metadata.start_time)
-                .bind(proc_metadata.is_alive)
-                .bind(proc_metadata.init_total_cpu)
-                .execute(conn)
-                .await?;
-        } else if !db_process_pids.contains(&proc_metadata.pid) {
-            event!(
-                Level::DEBUG,
-                "Found new process to insert with PID {:?}",
-                proc_metadata.pid
+    /// Adds updated process information to the database, while cleaning up any old
+    /// data it finds along the way
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to update process data");
+        let cur_samples: Vec<ProcessSample> = match self.processes.take() {
+            Some(processes) => processes,
+            None => {
+                event!(Level::WARN, "No process information has been collected");
+                return Ok(());
+            }
+        };
+        let cur_processes: Vec<Process> = cur_samples
+            .iter()
+            .map(|sample| sample.process.clone())
+            .collect();
+        sync_processes(conn, &cur_processes).await?;
+
+        // Insert the current process metrics
+        event!(Level::DEBUG, "Starting to insert process metrics data");
+        for chunk in cur_samples.chunks(MAX_ROWS_PER_STATEMENT) {
+            // Percent CPU time is initially NULL and will be updated when CPU
+            // aggregations are computed. Cores that came online since the CPUs
+            // were last updated are not known yet, so those processes are
+            // left without a core instead of failing the whole insert.
+            let mut proc_stat_insert: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "INSERT INTO PROCSTAT
+                    SELECT v.column1, v.column2, v.column3, v.column4, NULL, CPU.CPU_CORE,
+                        v.column6, v.column7, v.column8, v.column9, v.column10
+                    FROM (",
             );
-            // Our process does not exist in the db yet, so have to insert it
-            sqlx::query("INSERT OR REPLACE INTO PROCESS VALUES (?, ?, ?, ?, ?);")
-                .bind(proc_metadata.pid)
-                .bind(&proc_metadata.exec)
-                .bind(proc_metadata.start_time)
-                .bind(proc_metadata.is_alive)
-                .bind(proc_metadata.init_total_cpu)
-                .execute(conn)
-                .await?;
+            proc_stat_insert.push_values(chunk.iter(), |mut builder, sample| {
+                builder
+                    .push_bind(sample.process.pid)
+                    .push_bind(sample.process.start_time)
+                    .push_bind(cur_time as i64)
+                    // This is just the current cpu time
+                    .push_bind(sample.process.init_total_cpu)
+                    .push_bind(sample.cpu_core)
+                    .push_bind(sample.virtual_memory)
+                    .push_bind(sample.resident_memory)
+                    .push_bind(sample.shared_memory)
+                    .push_bind(sample.io_read_bytes)
+                    .push_bind(sample.io_write_bytes);
+            });
+            proc_stat_insert.push(") AS v LEFT JOIN CPU ON CPU.CPU_CORE = v.column5;");
+            proc_stat_insert.build().execute(&mut *conn).await?;
         }
+        event!(Level::DEBUG, "Finished inserting process metrics data");
+
+        Ok(())
+    }
+
+    /// Removes the process metrics from before the cutoff along with any
+    /// processes that no longer have any metrics
+    #[instrument(skip(self, conn))]
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        sqlx::query("DELETE FROM PROCSTAT WHERE TIMESTAMP < ?;")
+            .bind(cutoff as i64)
+            .execute(&mut *conn)
+            .await?;
+
+        // Processes are just marked as dead, but can remove if dead for over
+        // the pruning period
+        sqlx::query(
+            "DELETE FROM PROCESS WHERE NOT EXISTS (SELECT 1 FROM PROCSTAT ps
+                WHERE ps.PID = PROCESS.PID AND ps.START_TIME = PROCESS.START_TIME);",
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
     }
+}
 
-    // Insert the current process metrics
-    event!(Level::DEBUG, "Starting to insert process metrics data");
-    let mut proc_stat_insert: QueryBuilder<Sqlite> = QueryBuilder::new("INSERT INTO PROCSTAT ");
-    proc_stat_insert.push_values(cur_processes.iter(), |mut builder, proc| {
-        let proc_metadata: Process = proc.into();
-        builder
-            .push_bind(proc_metadata.pid)
-            .push_bind(cur_time as i64)
-            // This is just the current cpu time
-            .push_bind(proc_metadata.init_total_cpu)
-            // Percent CPU time is initially NULL and will be updated when
-            // CPU aggregations are computed
-            .push_bind(None as Option<f32>)
-            .push_bind(proc.stat.processor)
-            // Store all memory data in KB
-            // Statm stores data in pages, and page_size returns bytes,
-            // so have to divide by 1000 to get KB
-            .push_bind((proc.statm.size * procfs::page_size() / 1000) as u32)
-            .push_bind((proc.statm.resident * procfs::page_size() / 1000) as u32)
-            .push_bind((proc.statm.shared * procfs::page_size() / 1000) as u32);
-    });
-    proc_stat_insert.push(";");
-    proc_stat_insert.build().execute(conn).await?;
-    event!(Level::DEBUG, "Finished inserting process metrics data");
+/// Brings the process table up to date with the current processes by
+/// inserting new processes and marking processes that are no longer running
+/// as dead
+///
+/// Processes are identified by their PID and start time, so a process whose
+/// PID has been reused keeps its history as a dead process.
+#[instrument(skip(conn, cur_processes))]
+async fn sync_processes(
+    conn: &mut SqliteConnection,
+    cur_processes: &[Process],
+) -> Result<(), NebulaError> {
+    let db_processes: HashSet<(u32, i64)> = get_alive_processes_in_db(conn)
+        .await?
+        .iter()
+        .map(|db_proc| (db_proc.pid, db_proc.start_time))
+        .collect();
+
+    let new_processes: Vec<&Process> = cur_processes
+        .iter()
+        .filter(|cur_proc| !db_processes.contains(&(cur_proc.pid, cur_proc.start_time)))
+        .collect();
+    // Logging is done in bulk since there can be thousands of processes
+    event!(Level::DEBUG, "Found {} new processes", new_processes.len());
+
+    // Processes that were previously marked as dead are brought back to life
+    for chunk in new_processes.chunks(MAX_ROWS_PER_STATEMENT) {
+        let mut proc_insert: QueryBuilder<Sqlite> = QueryBuilder::new("INSERT INTO PROCESS ");
+        proc_insert.push_values(chunk.iter(), |mut builder, new_proc| {
+            builder
+                .push_bind(new_proc.pid)
+                .push_bind(&new_proc.exec)
+                .push_bind(new_proc.start_time)
+                .push_bind(new_proc.is_alive)
+                .push_bind(new_proc.init_total_cpu)
+                .push_bind(&new_proc.boot_id)
+                .push_bind(new_proc.uid)
+                .push_bind(&new_proc.user_name);
+        });
+        proc_insert
+            .push(" ON CONFLICT (PID, START_TIME) DO UPDATE SET IS_ALIVE = excluded.IS_ALIVE;");
+        proc_insert.build().execute(&mut *conn).await?;
+    }
 
     // Update the process table in case any processes died since the last update
     event!(
         Level::DEBUG,
         "Starting to update the status of dead processes"
     );
-    let mut update_dead_processes: QueryBuilder<Sqlite> =
-        QueryBuilder::new("UPDATE PROCESS SET IS_ALIVE = FALSE WHERE PID NOT IN (");
-    let mut update_dead_separated = update_dead_processes.separated(", ");
-    for proc in cur_processes.iter() {
-        update_dead_separated.push_bind(proc.process.pid);
+    let cur_keys: HashSet<(u32, i64)> = cur_processes
+        .iter()
+        .map(|proc| (proc.pid, proc.start_time))
+        .collect();
+    let dead_keys: Vec<&(u32, i64)> = db_processes.difference(&cur_keys).collect();
+    event!(Level::DEBUG, "Found {} dead processes", dead_keys.len());
+    for chunk in dead_keys.chunks(MAX_ROWS_PER_STATEMENT) {
+        let mut update_dead_processes: QueryBuilder<Sqlite> =
+            QueryBuilder::new("UPDATE PROCESS SET IS_ALIVE = FALSE WHERE (PID, START_TIME) IN (");
+        update_dead_processes.push_values(chunk.iter(), |mut builder, (pid, start_time)| {
+            builder.push_bind(pid).push_bind(start_time);
+        });
+        update_dead_processes.push(");");
+        update_dead_processes.build().execute(&mut *conn).await?;
     }
-    update_dead_separated.push_unseparated(");");
-    update_dead_processes.build().execute(conn).await?;
-
-    trans.commit().await?;
     event!(
         Level::DEBUG,
         "Finished updating the status of dead processes"
@@ -302,40 +294,117 @@ pub async fn update_process_data(cur_time: u64, conn: &SqlitePool) -> Result<(),
     Ok(())
 }
 
-/// Gets all of the current processes from procfs
+/// Gets all of the current processes from procfs, which are tied to the
+/// current boot
 #[instrument]
-fn get_all_processes() -> Result<Vec<ProcfsProcess>, NebulaError> {
+fn get_all_processes(root: &SystemRoot) -> Result<Vec<ProcessSample>, NebulaError> {
     event!(Level::DEBUG, "Getting all processes from procfs");
-    let proc_vec: Vec<ProcfsProcess> = process::all_processes()?
+    let boot_id: String = get_boot_id(root)?;
+    let boot_time: u64 = get_boot_time(root)?;
+    let user_names: HashMap<u32, String> = read_user_names(root);
+    let mut proc_vec: Vec<ProcessSample> = process::all_processes_with_root(&root.proc)?
         // Only keep processes that we can fully access
         .filter_map(|proc_res| proc_res.ok())
-        .map(ProcfsProcess::try_from)
+        .map(|proc| ProcfsProcess::new(proc, boot_time))
         .filter_map(|p| p.ok())
+        // Convert right away so that the procfs handles are closed
+        .map(ProcessSample::from)
         .collect();
+    for sample in proc_vec.iter_mut() {
+        sample.process.boot_id = Some(boot_id.clone());
+        sample.process.user_name = sample
+            .process
+            .uid
+            .and_then(|uid| user_names.get(&uid).cloned());
+    }
+    // Only the real procfs lists processes in order, so sort them to write
+    // the database in a consistent order
+    proc_vec.sort_by_key(|sample| sample.process.pid);
     event!(Level::DEBUG, "Done getting all processes from procfs");
     Ok(proc_vec)
 }
 
-/// Gets all of the process info from the database
-#[instrument(skip(conn))]
-async fn get_processes_in_db(conn: &SqlitePool) -> Result<Vec<Process>, NebulaError> {
-    event!(Level::DEBUG, "Getting all processes from the db");
-    let proc_vec: Vec<Process> =
-        sqlx::query_as::<_, Process>("SELECT * FROM PROCESS ORDER BY PID ASC;")
-            .fetch_all(conn)
-            .await?;
+/// Reads the names of the users in the host's passwd file by their UID, which
+/// is empty if the file cannot be read
+///
+/// Users from other sources like LDAP are not looked up, as those would be the
+/// users of the monitor's system rather than the host's when it is running in
+/// a container.
+fn read_user_names(root: &SystemRoot) -> HashMap<u32, String> {
+    match fs::read_to_string(root.host_path("etc/passwd")) {
+        Ok(contents) => parse_passwd(&contents),
+        Err(err) => {
+            event!(Level::WARN, "Unable to read the passwd file: {}", err);
+            HashMap::new()
+        }
+    }
+}
+
+/// Parses the `name:password:uid:...` lines of a passwd file, where the first
+/// entry wins if a UID is listed more than once
+fn parse_passwd(contents: &str) -> HashMap<u32, String> {
+    let mut user_names: HashMap<u32, String> = HashMap::new();
+    for line in contents.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() < 3 || fields[0].is_empty() || fields[0].starts_with('#') {
+            continue;
+        }
+        if let Ok(uid) = fields[2].parse::<u32>() {
+            user_names
+                .entry(uid)
+                .or_insert_with(|| fields[0].to_string());
+        }
+    }
+    user_names
+}
 
-    event!(Level::DEBUG, "Done getting all processes from the db");
+/// Gets the info of all processes that are alive from the database
+#[instrument(skip(conn))]
+async fn get_alive_processes_in_db(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<Process>, NebulaError> {
+    event!(Level::DEBUG, "Getting all alive processes from the db");
+    let proc_vec: Vec<Process> = sqlx::query_as::<_, Process>(
+        "SELECT * FROM PROCESS WHERE IS_ALIVE = TRUE ORDER BY PID ASC;",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    event!(Level::DEBUG, "Done getting all alive processes from the db");
     Ok(proc_vec)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::monitor::cpu;
-    use models::tables::ProcStat;
+    use crate::monitor::cpu::CpuCollector;
+    use models::tables::{CpuStat, ProcStat};
+    use sqlx::{ConnectOptions, Connection, SqlitePool, Transaction};
     use std::io;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::os::unix::fs::MetadataExt;
+
+    /// Creates the sample of a process running on CPU 0 that has used the
+    /// given amount of CPU time
+    fn synthetic_sample(pid: u32, start_time: i64, total_cpu: f32) -> ProcessSample {
+        ProcessSample {
+            process: Process {
+                pid,
+                exec: format!("/usr/bin/worker-{}", pid),
+                start_time,
+                is_alive: true,
+                init_total_cpu: total_cpu,
+                boot_id: None,
+                uid: Some(1000),
+                user_name: None,
+            },
+            cpu_core: Some(0),
+            virtual_memory: 4096,
+            resident_memory: 2048,
+            shared_memory: 1024,
+            io_read_bytes: Some(512),
+            io_write_bytes: None,
+        }
+    }
 
     #[test]
     fn test_get_all_processes() -> Result<(), NebulaError> {
@@ -344,59 +413,129 @@ mod tests {
             .with_max_level(Level::TRACE)
             .try_init();
 
-        assert!(get_all_processes().is_ok());
+        let processes: Vec<Process> = get_all_processes(&SystemRoot::fixture())?
+            .into_iter()
+            .map(|sample| sample.process)
+            .collect();
+        assert_eq!(processes.len(), 2);
+
+        assert_eq!(processes[0].pid, 1);
+        assert_eq!(processes[0].exec, "/usr/lib/systemd/systemd");
+        // Started 100 ticks after boot
+        assert_eq!(processes[0].start_time, 1700000001);
+        assert_eq!(processes[0].init_total_cpu, 2.5);
+        assert!(processes[0].is_alive);
+
+        assert_eq!(processes[1].pid, 42);
+        assert_eq!(processes[1].exec, "/usr/bin/test-exe");
+        assert_eq!(processes[1].start_time, 1700000500);
+        assert_eq!(processes[1].init_total_cpu, 5.0);
+        assert!(processes[1].is_alive);
+        assert_eq!(
+            processes[1].boot_id.as_deref(),
+            Some("3f2a6c1e-8d4b-4c6e-9a1f-5b7d2e0c4a91")
+        );
+
+        // Processes are owned by whoever owns their procfs directory
+        let owner: u32 = fs::metadata(SystemRoot::fixture().proc_path("42"))?.uid();
+        assert_eq!(processes[1].uid, Some(owner));
+        assert_eq!(
+            processes[1].user_name,
+            read_user_names(&SystemRoot::fixture()).get(&owner).cloned()
+        );
+
         Ok(())
     }
 
-    #[sqlx::test(fixtures("processTest"))]
+    #[test]
+    fn test_parse_passwd() {
+        let user_names: HashMap<u32, String> = parse_passwd(
+            "root:x:0:0:root:/root:/bin/bash\n\
+            # comment\n\
+            \n\
+            broken\n\
+            nobody:x:not-a-uid:65534::/:/bin/false\n\
+            alice:x:1000:1000:Alice:/home/alice:/bin/bash\n\
+            alias:x:1000:1000::/home/alice:/bin/bash\n",
+        );
+        assert_eq!(user_names.len(), 2);
+        assert_eq!(user_names[&0], "root");
+        // The first user with a UID is the one that is shown
+        assert_eq!(user_names[&1000], "alice");
+
+        let fixture_names: HashMap<u32, String> = read_user_names(&SystemRoot::fixture());
+        assert_eq!(fixture_names.len(), 3);
+        assert!(read_user_names(&SystemRoot::with_host_root("/does/not/exist")).is_empty());
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("processTest"))]
     async fn test_get_db_processes(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        // We should get 1 result back
-        let proc_vec: Vec<Process> = get_processes_in_db(&pool).await?;
+        sqlx::query(
+            "INSERT INTO PROCESS VALUES (1, \"dead-exe\", 123456789, 0, 10, NULL, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+
+        // We should get 1 result back since dead processes are left out
+        let proc_vec: Vec<Process> = get_alive_processes_in_db(&mut *pool.acquire().await?).await?;
         assert_eq!(proc_vec.len(), 1);
+        assert_eq!(proc_vec[0].pid, 9999999);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("processTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("processTest"))]
     async fn test_init_process_data_simple(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let cur_process_intermediate: ProcfsProcess =
-            procfs::process::Process::myself()?.try_into()?;
-        let cur_process: Process = cur_process_intermediate.into();
-        sqlx::query("INSERT INTO PROCESS VALUES (?, ?, ?, ?, ?);")
-            .bind(cur_process.pid)
-            .bind(cur_process.exec)
-            .bind(123456789)
-            .bind(true)
-            .bind(10)
+        // PID 1 was recorded before a reboot, so it is a different process
+        sqlx::query(
+            "INSERT INTO PROCESS VALUES (1, \"old-init\", 123456789, 1, 10, NULL, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("INSERT INTO PROCSTAT VALUES (1, 123456789, 987654321, 10, NULL, 0, 1, 1, 0, NULL, NULL);")
             .execute(&pool)
             .await?;
 
-        init_process_data(&pool).await?;
+        ProcessCollector::new(SystemRoot::fixture())
+            .init(&mut *pool.acquire().await?)
+            .await?;
 
-        let my_proc_row: Process =
-            sqlx::query_as::<_, Process>("SELECT * FROM PROCESS WHERE PID = ?")
-                .bind(cur_process.pid)
-                .fetch_one(&pool)
+        let init_proc_rows: Vec<Process> = sqlx::query_as::<_, Process>(
+            "SELECT * FROM PROCESS WHERE PID = 1 ORDER BY START_TIME;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(init_proc_rows.len(), 2);
+        // Make sure the old process is kept along with its stats, but is dead
+        assert_eq!(init_proc_rows[0].exec, "old-init");
+        assert!(!init_proc_rows[0].is_alive);
+        assert_eq!(init_proc_rows[1].start_time, 1700000001);
+        assert_eq!(init_proc_rows[1].exec, "/usr/lib/systemd/systemd");
+        assert!(init_proc_rows[1].is_alive);
+
+        let init_proc_stats: Vec<ProcStat> =
+            sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT WHERE PID = 1;")
+                .fetch_all(&pool)
                 .await?;
-        // Make sure the old process is overwritten and its old stats are gone
-        assert_ne!(my_proc_row.start_time, 123456789);
+        assert_eq!(init_proc_stats.len(), 1);
+        assert_eq!(init_proc_stats[0].start_time, 123456789);
 
-        let my_proc_stats: Vec<ProcStat> =
-            sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT WHERE PID = ?;")
-                .bind(cur_process.pid)
-                .fetch_all(&pool)
+        // The other recorded process is new
+        let new_proc_row: Process =
+            sqlx::query_as::<_, Process>("SELECT * FROM PROCESS WHERE PID = 42;")
+                .fetch_one(&pool)
                 .await?;
-        assert_eq!(my_proc_stats.len(), 0);
+        assert_eq!(new_proc_row.start_time, 1700000500);
 
         // Make sure the not found process is marked as not being alive anymore
         let old_process: Process =
@@ -408,7 +547,7 @@ mod tests {
         Ok(())
     }
 
-    #[sqlx::test(fixtures("processTestEmpty"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("processTestEmpty"))]
     async fn test_init_process_data_additional_checks(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
@@ -416,58 +555,49 @@ mod tests {
             .try_init();
 
         // This will be an existing process that is already running
-        let cur_process_intermediate: ProcfsProcess =
-            procfs::process::Process::myself()?.try_into()?;
-        let cur_process: Process = cur_process_intermediate.into();
-        sqlx::query("INSERT INTO PROCESS VALUES (?, ?, ?, ?, ?);")
-            .bind(cur_process.pid)
-            .bind(cur_process.exec)
-            .bind(cur_process.start_time)
-            .bind(cur_process.is_alive)
-            .bind(cur_process.init_total_cpu)
-            .execute(&pool)
-            .await?;
-
-        sqlx::query("INSERT INTO PROCSTAT VALUES(?, ?, ?, ?, ?, ?, ?, ?);")
-            .bind(cur_process.pid)
-            .bind(123456789)
-            .bind(999)
-            .bind(None as Option<f32>)
-            .bind(0)
-            .bind(42)
-            .bind(42)
-            .bind(0)
-            .execute(&pool)
-            .await?;
+        sqlx::query(
+            "INSERT INTO PROCESS VALUES (1, \"/usr/lib/systemd/systemd\", 1700000001, 1, 2.5, NULL, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "INSERT INTO PROCSTAT VALUES(1, 1700000001, 123456789, 999, NULL, 0, 42, 42, 0, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
 
         // This is an old process that should be marked as dead
-        sqlx::query("INSERT INTO PROCESS VALUES(42, \"test-exe\", 123456790, 1, 2048);")
-            .execute(&pool)
-            .await?;
-        sqlx::query("INSERT INTO PROCSTAT VALUES(42, 987654321, 5000, NULL, 0, 42, 42, 0);")
-            .execute(&pool)
-            .await?;
+        sqlx::query(
+            "INSERT INTO PROCESS VALUES(99, \"test-exe\", 123456790, 1, 2048, NULL, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "INSERT INTO PROCSTAT VALUES(99, 123456790, 987654321, 5000, NULL, 0, 42, 42, 0, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
 
-        init_process_data(&pool).await?;
+        ProcessCollector::new(SystemRoot::fixture())
+            .init(&mut *pool.acquire().await?)
+            .await?;
 
-        let my_proc_row: Process =
-            sqlx::query_as::<_, Process>("SELECT * FROM PROCESS WHERE PID = ?")
-                .bind(cur_process.pid)
+        let init_proc_row: Process =
+            sqlx::query_as::<_, Process>("SELECT * FROM PROCESS WHERE PID = 1;")
                 .fetch_one(&pool)
                 .await?;
         // This process still exists, so make sure that nothing has changed
-        assert_eq!(my_proc_row.start_time, cur_process.start_time);
-        let my_proc_stats: Vec<ProcStat> =
-            sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT WHERE PID = ?;")
-                .bind(cur_process.pid)
+        assert_eq!(init_proc_row.start_time, 1700000001);
+        let init_proc_stats: Vec<ProcStat> =
+            sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT WHERE PID = 1;")
                 .fetch_all(&pool)
                 .await?;
-        assert_eq!(my_proc_stats.len(), 1);
+        assert_eq!(init_proc_stats.len(), 1);
 
         // Make sure the not found process is marked as not being alive anymore
         let old_process: Process =
             sqlx::query_as::<_, Process>("SELECT * FROM PROCESS WHERE PID = ?;")
-                .bind(42)
+                .bind(99)
                 .fetch_one(&pool)
                 .await?;
         assert!(!old_process.is_alive);
@@ -475,27 +605,27 @@ mod tests {
         Ok(())
     }
 
-    #[sqlx::test(fixtures("processTestEmpty"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("processTestEmpty"))]
     async fn test_init_process_data_empty_db(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let processes: Vec<ProcfsProcess> = get_all_processes()?;
-
-        init_process_data(&pool).await?;
+        ProcessCollector::new(SystemRoot::fixture())
+            .init(&mut *pool.acquire().await?)
+            .await?;
 
         let rows: Vec<Process> = sqlx::query_as::<_, Process>("SELECT * FROM PROCESS;")
             .fetch_all(&pool)
             .await?;
         // All processes should have just been inserted without question
-        assert_eq!(rows.len(), processes.len());
+        assert_eq!(rows.len(), 2);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("processTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("processTest"))]
     async fn test_update_process_data(pool: SqlitePool) -> Result<(), NebulaError> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
@@ -503,53 +633,48 @@ mod tests {
             .try_init();
 
         // Init the cpu data to make sure there are no foreign key issues
-        cpu::init_cpu_data(&pool).await?;
-
-        // Insert some junk data
-        let my_pid: i32 = process::Process::myself()?.pid;
-        sqlx::query("INSERT INTO PROCESS VALUES (?, ?, ?, ?, ?);")
-            .bind(my_pid)
-            .bind("the-exe")
-            .bind(123456789)
-            .bind(0)
-            .bind(4242)
-            .execute(&pool)
-            .await?;
-        sqlx::query("INSERT INTO PROCSTAT VALUES (?, ?, ?, ?, ?, ?, ?, ?);")
-            .bind(my_pid)
-            .bind(987654321)
-            .bind(424242)
-            .bind(None as Option<f32>)
-            .bind(0)
-            .bind(99)
-            .bind(89)
-            .bind(20)
-            .execute(&pool)
+        CpuCollector::new(SystemRoot::fixture())
+            .init(&mut *pool.acquire().await?)
             .await?;
 
-        let processes: Vec<ProcfsProcess> = get_all_processes()?;
+        // Insert an old process that had the same PID as a current one
+        sqlx::query(
+            "INSERT INTO PROCESS VALUES (1, \"the-exe\", 123456789, 1, 4242, NULL, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "INSERT INTO PROCSTAT VALUES (1, 123456789, 987654321, 424242, NULL, 0, 99, 89, 20, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
 
-        let cur_time: u64 = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        update_process_data(cur_time, &pool).await?;
+        let mut collector: ProcessCollector = ProcessCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector
+            .persist(123456790, &mut *pool.acquire().await?)
+            .await?;
 
-        // Make sure the garbage process is overwritten
-        let my_pid_res: Process =
-            sqlx::query_as::<_, Process>("SELECT * FROM PROCESS WHERE PID = ?;")
-                .bind(my_pid)
-                .fetch_one(&pool)
-                .await?;
-        assert_ne!(my_pid_res.start_time, 123456789);
-        let my_pid_stats: Vec<ProcStat> = sqlx::query_as::<_, ProcStat>(
-            "SELECT * FROM PROCSTAT WHERE PID = ? AND TIMESTAMP = ?;",
+        // Make sure the old process is dead but keeps its history
+        let old_proc_row: Process = sqlx::query_as::<_, Process>(
+            "SELECT * FROM PROCESS WHERE PID = 1 AND START_TIME = 123456789;",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(!old_proc_row.is_alive);
+        let old_proc_stats: Vec<ProcStat> = sqlx::query_as::<_, ProcStat>(
+            "SELECT * FROM PROCSTAT WHERE PID = 1 AND START_TIME = 123456789;",
         )
-        .bind(my_pid)
-        .bind(987654321)
         .fetch_all(&pool)
         .await?;
-        assert_eq!(my_pid_stats.len(), 0);
+        assert_eq!(old_proc_stats.len(), 1);
+
+        let new_proc_row: Process = sqlx::query_as::<_, Process>(
+            "SELECT * FROM PROCESS WHERE PID = 1 AND START_TIME = 1700000001;",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(new_proc_row.is_alive);
 
         // Check for the dead process
         let dead_proc: Process =
@@ -569,14 +694,135 @@ mod tests {
         let all_processes: Vec<Process> = sqlx::query_as::<_, Process>("SELECT * FROM PROCESS;")
             .fetch_all(&pool)
             .await?;
-        // + 1 because of the dead process
-        assert_eq!(all_processes.len(), processes.len() + 1);
-        let all_process_stats: Vec<ProcStat> =
-            sqlx::query_as::<_, ProcStat>("SELECT * FROM PROCSTAT;")
-                .fetch_all(&pool)
+        // + 2 because of the dead processes
+        assert_eq!(all_processes.len(), 4);
+
+        let new_stats: Vec<ProcStat> = sqlx::query_as::<_, ProcStat>(
+            "SELECT * FROM PROCSTAT WHERE TIMESTAMP = 123456790 ORDER BY PID;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(new_stats.len(), 2);
+        assert_eq!(new_stats[0].start_time, 1700000001);
+        assert_eq!(new_stats[1].pid, 42);
+        assert_eq!(new_stats[1].total_cpu, 5.0);
+        assert_eq!(new_stats[1].cpu_core, Some(1));
+        // Only PID 42 has its storage I/O recorded
+        assert_eq!(new_stats[0].io_read_bytes, None);
+        assert_eq!(new_stats[1].io_read_bytes, Some(8192));
+        assert_eq!(new_stats[1].io_write_bytes, Some(4096));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("processTest"))]
+    async fn test_process_on_unknown_core(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Only core 0 is known, but PID 42 last ran on core 1, which came
+        // online before the CPUs were updated
+        let mut collector: ProcessCollector = ProcessCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector
+            .persist(123456790, &mut *pool.acquire().await?)
+            .await?;
+
+        let new_stats: Vec<ProcStat> = sqlx::query_as::<_, ProcStat>(
+            "SELECT * FROM PROCSTAT WHERE TIMESTAMP = 123456790 ORDER BY PID;",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(new_stats.len(), 2);
+        assert_eq!(new_stats[0].cpu_core, Some(0));
+        assert_eq!(new_stats[1].pid, 42);
+        assert_eq!(new_stats[1].cpu_core, None);
+        assert_eq!(new_stats[1].total_cpu, 5.0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("processTestEmpty"))]
+    async fn test_persist_many_processes(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let num_processes: u32 = 10_000;
+        let mut process_collector: ProcessCollector = ProcessCollector::new(SystemRoot::fixture());
+        let mut cpu_collector: CpuCollector = CpuCollector::new(SystemRoot::fixture());
+
+        // Every process has used 1 second of CPU time during the first tick
+        process_collector.processes = Some(
+            (1..=num_processes)
+                .map(|pid| synthetic_sample(pid, 100, 1.0))
+                .collect(),
+        );
+        // The monitor does not log its statements, which are slow to format
+        let mut conn: SqliteConnection = pool
+            .connect_options()
+            .as_ref()
+            .clone()
+            .disable_statement_logging()
+            .connect()
+            .await?;
+        process_collector.persist(1000, &mut conn).await?;
+        cpu_collector.persist(1000, &mut conn).await?;
+
+        // For the second tick, the first 100 processes die, the next 100 have
+        // had their PIDs reused, and there are 100 brand new processes
+        let second_tick: Vec<ProcessSample> = (101..=num_processes + 100)
+            .map(|pid| match pid {
+                101..=200 => synthetic_sample(pid, 200, 0.4),
+                pid if pid > num_processes => synthetic_sample(pid, 200, 0.4),
+                _ => synthetic_sample(pid, 100, 2.0),
+            })
+            .collect();
+        process_collector.processes = Some(second_tick);
+
+        // Write the tick like the monitor would, where its speed is measured
+        // by the tick benchmark instead
+        let mut trans: Transaction<Sqlite> = conn.begin().await?;
+        process_collector.persist(1004, &mut trans).await?;
+        cpu_collector.persist(1004, &mut trans).await?;
+        trans.commit().await?;
+
+        let alive: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM PROCESS WHERE IS_ALIVE = TRUE;")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(alive, num_processes as i64);
+        let dead: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM PROCESS WHERE IS_ALIVE = FALSE;")
+            .fetch_one(&pool)
+            .await?;
+        // The old processes with reused PIDs are dead too
+        assert_eq!(dead, 200);
+
+        // The stats from before the PIDs were reused are kept
+        let old_stats: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM PROCSTAT WHERE TIMESTAMP = 1000;")
+                .fetch_one(&pool)
                 .await?;
-        // + 1 because of the dead process
-        assert_eq!(all_process_stats.len(), processes.len() + 1);
+        assert_eq!(old_stats, num_processes as i64);
+
+        let reused_stat: ProcStat =
+            sqlx::query_as("SELECT * FROM PROCSTAT WHERE PID = 150 AND TIMESTAMP = 1004;")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(reused_stat.percent_cpu, Some(0.1));
+        let running_stat: ProcStat =
+            sqlx::query_as("SELECT * FROM PROCSTAT WHERE PID = 500 AND TIMESTAMP = 1004;")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(running_stat.percent_cpu, Some(0.25));
+
+        // 9800 processes at 0.25 and 200 processes at 0.1
+        let cpu_stat: CpuStat = sqlx::query_as("SELECT * FROM CPUSTAT WHERE TIMESTAMP = 1004;")
+            .fetch_one(&pool)
+            .await?;
+        assert!((cpu_stat.usage - 2470.0).abs() < 0.01);
 
         Ok(())
     }
@@ -0,0 +1,373 @@
+use async_trait::async_trait;
+use models::error::NebulaError;
+use models::tables::ProtocolStat;
+use sqlx::SqliteConnection;
+use std::collections::HashMap;
+use std::fs;
+use tracing::{event, instrument, Level};
+
+use super::collector::Collector;
+use super::network::counter_delta;
+use super::system::SystemRoot;
+
+/// Counters from /proc/net/snmp and /proc/net/netstat by section and name
+type CounterTable = HashMap<(String, String), u64>;
+
+/// Kernel network protocol counters as of a single collection
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ProtocolCounters {
+    /// TCP segments that were retransmitted
+    tcp_retrans_segs: u64,
+    /// TCP segments sent with the RST flag
+    tcp_out_rsts: u64,
+    /// Established TCP connections that were reset
+    tcp_estab_resets: u64,
+    /// TCP connections opened by the host
+    tcp_active_opens: u64,
+    /// TCP connections opened by a peer
+    tcp_passive_opens: u64,
+    /// Times a TCP listen queue was full
+    tcp_listen_overflows: u64,
+    /// TCP connection requests that were dropped while listening
+    tcp_listen_drops: u64,
+    /// UDP datagrams dropped because the receive buffer was full
+    udp_rcvbuf_errors: u64,
+    /// UDP datagrams dropped because the send buffer was full
+    udp_sndbuf_errors: u64,
+    /// IP packets that could not be reassembled from their fragments
+    ip_reasm_fails: u64,
+}
+
+/// Collector for the kernel's network protocol statistics
+#[derive(Debug, Default)]
+pub struct ProtocolCollector {
+    /// Where to read the protocol statistics from
+    root: SystemRoot,
+    /// Counters from the last collection
+    counters: Option<ProtocolCounters>,
+    /// Time and counters of the last recorded sample, which the next sample is
+    /// compared against
+    last_sample: Option<(u64, ProtocolCounters)>,
+    /// Time and counters of the sample being persisted, which becomes the last
+    /// sample once the tick is committed
+    pending_sample: Option<(u64, ProtocolCounters)>,
+}
+
+impl ProtocolCollector {
+    /// Creates a new protocol collector that reads from the given root
+    pub fn new(root: SystemRoot) -> Self {
+        ProtocolCollector {
+            root,
+            counters: None,
+            last_sample: None,
+            pending_sample: None,
+        }
+    }
+
+    /// Reads the protocol counters of the host's network namespace
+    fn read_counters(&self) -> Result<ProtocolCounters, NebulaError> {
+        let mut table: CounterTable =
+            parse_counter_table(&fs::read_to_string(self.root.host_ns_path("net/snmp"))?)?;
+        table.extend(parse_counter_table(&fs::read_to_string(
+            self.root.host_ns_path("net/netstat"),
+        )?)?);
+
+        // Counters that the running kernel does not report are left at 0
+        let get = |section: &str, name: &str| -> u64 {
+            table
+                .get(&(section.to_string(), name.to_string()))
+                .copied()
+                .unwrap_or(0)
+        };
+        Ok(ProtocolCounters {
+            tcp_retrans_segs: get("Tcp", "RetransSegs"),
+            tcp_out_rsts: get("Tcp", "OutRsts"),
+            tcp_estab_resets: get("Tcp", "EstabResets"),
+            tcp_active_opens: get("Tcp", "ActiveOpens"),
+            tcp_passive_opens: get("Tcp", "PassiveOpens"),
+            tcp_listen_overflows: get("TcpExt", "ListenOverflows"),
+            tcp_listen_drops: get("TcpExt", "ListenDrops"),
+            udp_rcvbuf_errors: get("Udp", "RcvbufErrors"),
+            udp_sndbuf_errors: get("Udp", "SndbufErrors"),
+            ip_reasm_fails: get("Ip", "ReasmFails"),
+        })
+    }
+}
+
+#[async_trait]
+impl Collector for ProtocolCollector {
+    fn name(&self) -> &'static str {
+        "protocol"
+    }
+
+    /// Protocol statistics have no static information to set up
+    async fn init(&mut self, _conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        Ok(())
+    }
+
+    /// Reads the current protocol counters from procfs
+    #[instrument(skip(self))]
+    fn collect(&mut self) -> Result<(), NebulaError> {
+        self.counters = Some(self.read_counters()?);
+        Ok(())
+    }
+
+    /// Records how much the protocol counters went up since the last sample
+    #[instrument(skip(self, conn))]
+    async fn persist(
+        &mut self,
+        cur_time: u64,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), NebulaError> {
+        event!(Level::INFO, "Starting to update protocol statistics");
+        self.pending_sample = None;
+
+        let counters: ProtocolCounters = match self.counters.take() {
+            Some(counters) => counters,
+            None => {
+                event!(Level::WARN, "No protocol statistics have been collected");
+                return Ok(());
+            }
+        };
+
+        match &self.last_sample {
+            Some((last_time, last)) if cur_time > *last_time => {
+                let stat: ProtocolStat = get_protocol_stat(cur_time, *last_time, &counters, last);
+                sqlx::query(
+                    "INSERT INTO PROTOCOLSTAT VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);",
+                )
+                .bind(stat.timestamp)
+                .bind(stat.elapsed)
+                .bind(stat.tcp_retrans_segs)
+                .bind(stat.tcp_out_rsts)
+                .bind(stat.tcp_estab_resets)
+                .bind(stat.tcp_active_opens)
+                .bind(stat.tcp_passive_opens)
+                .bind(stat.tcp_listen_overflows)
+                .bind(stat.tcp_listen_drops)
+                .bind(stat.udp_rcvbuf_errors)
+                .bind(stat.udp_sndbuf_errors)
+                .bind(stat.ip_reasm_fails)
+                .execute(&mut *conn)
+                .await?;
+            }
+            // The counters are only compared once there is an earlier sample
+            // to compare against
+            _ => event!(
+                Level::DEBUG,
+                "No earlier protocol statistics to compare against"
+            ),
+        }
+        self.pending_sample = Some((cur_time, counters));
+
+        event!(Level::INFO, "Finished updating protocol statistics");
+        Ok(())
+    }
+
+    /// Removes the protocol statistics from before the cutoff
+    #[instrument(skip(self, conn))]
+    async fn prune(&mut self, cutoff: u64, conn: &mut SqliteConnection) -> Result<(), NebulaError> {
+        sqlx::query("DELETE FROM PROTOCOLSTAT WHERE TIMESTAMP < ?;")
+            .bind(cutoff as i64)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Compares the next sample against the one that was just committed
+    fn committed(&mut self) {
+        if let Some(sample) = self.pending_sample.take() {
+            self.last_sample = Some(sample);
+        }
+    }
+}
+
+/// Parses the contents of /proc/net/snmp or /proc/net/netstat, where each
+/// section is a line of counter names followed by a line of their values
+///
+/// Counters are looked up by name since the kernel adds new ones over time.
+fn parse_counter_table(contents: &str) -> Result<CounterTable, NebulaError> {
+    let mut table: CounterTable = HashMap::new();
+    let mut lines = contents.lines();
+    while let Some(header) = lines.next() {
+        let values: &str = lines.next().ok_or_else(|| {
+            NebulaError::Parse(format!("Missing the values for the header {:?}", header))
+        })?;
+        let (section, names) = header
+            .split_once(':')
+            .ok_or_else(|| NebulaError::Parse(format!("Invalid header {:?}", header)))?;
+        let (value_section, values) = values
+            .split_once(':')
+            .ok_or_else(|| NebulaError::Parse(format!("Invalid values {:?}", values)))?;
+        if section != value_section {
+            return Err(NebulaError::Parse(format!(
+                "Values for {} are listed under {}",
+                section, value_section
+            )));
+        }
+
+        for (name, value) in names.split_whitespace().zip(values.split_whitespace()) {
+            // Some fields are settings rather than counters, such as the -1
+            // for TCP's maximum number of connections
+            if let Ok(value) = value.parse::<u64>() {
+                table.insert((section.to_string(), name.to_string()), value);
+            }
+        }
+    }
+    Ok(table)
+}
+
+/// Gets how much each protocol counter went up since the last sample
+fn get_protocol_stat(
+    cur_time: u64,
+    last_time: u64,
+    cur: &ProtocolCounters,
+    last: &ProtocolCounters,
+) -> ProtocolStat {
+    ProtocolStat {
+        timestamp: cur_time as i64,
+        elapsed: (cur_time - last_time) as i64,
+        tcp_retrans_segs: counter_delta(cur.tcp_retrans_segs, last.tcp_retrans_segs),
+        tcp_out_rsts: counter_delta(cur.tcp_out_rsts, last.tcp_out_rsts),
+        tcp_estab_resets: counter_delta(cur.tcp_estab_resets, last.tcp_estab_resets),
+        tcp_active_opens: counter_delta(cur.tcp_active_opens, last.tcp_active_opens),
+        tcp_passive_opens: counter_delta(cur.tcp_passive_opens, last.tcp_passive_opens),
+        tcp_listen_overflows: counter_delta(cur.tcp_listen_overflows, last.tcp_listen_overflows),
+        tcp_listen_drops: counter_delta(cur.tcp_listen_drops, last.tcp_listen_drops),
+        udp_rcvbuf_errors: counter_delta(cur.udp_rcvbuf_errors, last.udp_rcvbuf_errors),
+        udp_sndbuf_errors: counter_delta(cur.udp_sndbuf_errors, last.udp_sndbuf_errors),
+        ip_reasm_fails: counter_delta(cur.ip_reasm_fails, last.ip_reasm_fails),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::{Sqlite, SqlitePool, Transaction};
+    use std::io;
+
+    #[test]
+    fn test_parse_counter_table() -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let counters: ProtocolCounters =
+            ProtocolCollector::new(SystemRoot::fixture()).read_counters()?;
+        assert_eq!(
+            counters,
+            ProtocolCounters {
+                tcp_retrans_segs: 1874,
+                tcp_out_rsts: 1203,
+                tcp_estab_resets: 97,
+                tcp_active_opens: 5120,
+                tcp_passive_opens: 842,
+                tcp_listen_overflows: 6,
+                tcp_listen_drops: 9,
+                udp_rcvbuf_errors: 5,
+                udp_sndbuf_errors: 2,
+                ip_reasm_fails: 3,
+            }
+        );
+
+        // Counters are found by name regardless of their position
+        let table: CounterTable = parse_counter_table("Tcp: OutRsts MaxConn\nTcp: 4 -1\n")?;
+        assert_eq!(table.len(), 1);
+        assert_eq!(
+            table.get(&("Tcp".to_string(), "OutRsts".to_string())),
+            Some(&4)
+        );
+        assert!(parse_counter_table("Tcp: OutRsts\n").is_err());
+        assert!(parse_counter_table("Tcp: OutRsts\nUdp: 4\n").is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_update_protocol_data(pool: SqlitePool) -> Result<(), NebulaError> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Nothing is recorded without earlier counters to compare against
+        let mut collector: ProtocolCollector = ProtocolCollector::new(SystemRoot::fixture());
+        collector.collect()?;
+        collector
+            .persist(123456789, &mut *pool.acquire().await?)
+            .await?;
+        collector.committed();
+        assert!(sqlx::query("SELECT * FROM PROTOCOLSTAT;")
+            .fetch_all(&pool)
+            .await?
+            .is_empty());
+
+        // Earlier counters, where the retransmits were reset since
+        collector.last_sample = Some((
+            123456789,
+            ProtocolCounters {
+                tcp_retrans_segs: 5000,
+                tcp_out_rsts: 1200,
+                tcp_listen_overflows: 1,
+                udp_rcvbuf_errors: 5,
+                ..Default::default()
+            },
+        ));
+        collector.collect()?;
+        collector
+            .persist(123456794, &mut *pool.acquire().await?)
+            .await?;
+
+        let stats: Vec<ProtocolStat> =
+            sqlx::query_as::<_, ProtocolStat>("SELECT * FROM PROTOCOLSTAT;")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].timestamp, 123456794);
+        assert_eq!(stats[0].elapsed, 5);
+        assert_eq!(stats[0].tcp_retrans_segs, 1874);
+        assert_eq!(stats[0].tcp_out_rsts, 3);
+        assert_eq!(stats[0].tcp_listen_overflows, 5);
+        assert_eq!(stats[0].udp_rcvbuf_errors, 0);
+        assert_eq!(stats[0].ip_reasm_fails, 3);
+
+        // The collection is compared against once it is committed
+        assert_eq!(
+            collector.last_sample.as_ref().map(|(time, _)| *time),
+            Some(123456789)
+        );
+        collector.committed();
+        assert_eq!(
+            collector.last_sample.as_ref().map(|(time, _)| *time),
+            Some(123456794)
+        );
+
+        // A tick that is rolled back is not compared against, so the next
+        // sample covers the time since the last committed one
+        collector.collect()?;
+        let mut trans: Transaction<Sqlite> = pool.begin().await?;
+        collector.persist(123456798, &mut trans).await?;
+        trans.rollback().await?;
+        collector.collect()?;
+        collector
+            .persist(123456802, &mut *pool.acquire().await?)
+            .await?;
+        collector.committed();
+        let elapsed: Vec<i64> =
+            sqlx::query_scalar("SELECT ELAPSED FROM PROTOCOLSTAT ORDER BY TIMESTAMP;")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(elapsed, vec![5, 8]);
+
+        collector
+            .prune(123456803, &mut *pool.acquire().await?)
+            .await?;
+        assert!(sqlx::query("SELECT * FROM PROTOCOLSTAT;")
+            .fetch_all(&pool)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+}
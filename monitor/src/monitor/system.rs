@@ -0,0 +1,153 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Environment variable for the root of the host's file system
+const HOST_ROOT_VAR: &str = "NEBULA_HOST_ROOT";
+/// Environment variable for the mount point of procfs
+const PROC_ROOT_VAR: &str = "NEBULA_PROC_ROOT";
+/// Environment variable for the mount point of sysfs
+const SYS_ROOT_VAR: &str = "NEBULA_SYS_ROOT";
+
+/// Locations of the file systems that the collectors read system data from
+///
+/// These default to the live system, but can point somewhere else to monitor a
+/// host from inside of a container (ex. `/host/proc` and `/host/sys`) or to
+/// read recorded procfs files in tests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemRoot {
+    /// Mount point of procfs
+    pub proc: PathBuf,
+    /// Mount point of sysfs
+    pub sys: PathBuf,
+    /// Root of the host's file system, which mount points are relative to
+    pub host: PathBuf,
+}
+
+impl Default for SystemRoot {
+    /// Uses the live system's file systems
+    fn default() -> Self {
+        SystemRoot::with_host_root("/")
+    }
+}
+
+impl SystemRoot {
+    /// Creates the roots for a host file system mounted at the given path, with
+    /// procfs and sysfs in their usual places under it
+    pub fn with_host_root(host: impl AsRef<Path>) -> Self {
+        SystemRoot {
+            proc: host.as_ref().join("proc"),
+            sys: host.as_ref().join("sys"),
+            host: host.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Creates the roots from the `NEBULA_HOST_ROOT`, `NEBULA_PROC_ROOT`, and
+    /// `NEBULA_SYS_ROOT` environment variables
+    pub fn from_env() -> Self {
+        SystemRoot::from_vars(
+            env::var(HOST_ROOT_VAR).ok(),
+            env::var(PROC_ROOT_VAR).ok(),
+            env::var(SYS_ROOT_VAR).ok(),
+        )
+    }
+
+    /// Creates the roots from optional overrides, where procfs and sysfs
+    /// default to being under the host root
+    fn from_vars(host: Option<String>, proc: Option<String>, sys: Option<String>) -> Self {
+        let mut root: SystemRoot = match host {
+            Some(host) => SystemRoot::with_host_root(host),
+            None => SystemRoot::default(),
+        };
+        if let Some(proc) = proc {
+            root.proc = PathBuf::from(proc);
+        }
+        if let Some(sys) = sys {
+            root.sys = PathBuf::from(sys);
+        }
+        root
+    }
+
+    /// Path to a file within procfs
+    pub fn proc_path(&self, path: &str) -> PathBuf {
+        self.proc.join(path)
+    }
+
+    /// Path to a per-namespace file within procfs (ex. `mounts` or `net/dev`)
+    ///
+    /// These are read through PID 1 so that they describe the host's
+    /// namespaces even when the monitor is running inside of a container.
+    pub fn host_ns_path(&self, path: &str) -> PathBuf {
+        self.proc.join("1").join(path)
+    }
+
+    /// Path to an absolute path on the host's file system
+    pub fn host_path(&self, path: &str) -> PathBuf {
+        self.host.join(path.trim_start_matches('/'))
+    }
+
+    /// Roots for the recorded system in the test fixtures
+    #[cfg(test)]
+    pub fn fixture() -> Self {
+        let fixture_dir: PathBuf =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("src/monitor/fixtures/system");
+        SystemRoot {
+            proc: fixture_dir.join("proc"),
+            sys: fixture_dir.join("sys"),
+            host: fixture_dir.join("host"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_root_from_vars() {
+        assert_eq!(
+            SystemRoot::from_vars(None, None, None),
+            SystemRoot {
+                proc: PathBuf::from("/proc"),
+                sys: PathBuf::from("/sys"),
+                host: PathBuf::from("/"),
+            }
+        );
+
+        // procfs and sysfs follow the host root unless they are overridden
+        assert_eq!(
+            SystemRoot::from_vars(Some("/host".to_string()), None, None),
+            SystemRoot {
+                proc: PathBuf::from("/host/proc"),
+                sys: PathBuf::from("/host/sys"),
+                host: PathBuf::from("/host"),
+            }
+        );
+        assert_eq!(
+            SystemRoot::from_vars(
+                Some("/host".to_string()),
+                Some("/hostproc".to_string()),
+                Some("/hostsys".to_string())
+            ),
+            SystemRoot {
+                proc: PathBuf::from("/hostproc"),
+                sys: PathBuf::from("/hostsys"),
+                host: PathBuf::from("/host"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_system_root_paths() {
+        let root: SystemRoot = SystemRoot::with_host_root("/host");
+        assert_eq!(
+            root.proc_path("meminfo"),
+            PathBuf::from("/host/proc/meminfo")
+        );
+        assert_eq!(
+            root.host_ns_path("net/dev"),
+            PathBuf::from("/host/proc/1/net/dev")
+        );
+        assert_eq!(root.host_path("/"), PathBuf::from("/host/"));
+        assert_eq!(root.host_path("/data"), PathBuf::from("/host/data"));
+    }
+}
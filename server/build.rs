@@ -4,6 +4,8 @@ use std::process::{Command, ExitStatus};
 
 fn main() {
     let dashboard_dir: PathBuf = fs::canonicalize("../dashboard/").expect("Dashboard should exist");
+    // The assets folder only holds build output, so it may not exist yet
+    fs::create_dir_all("../assets/").expect("Should be able to create the assets folder");
     let assets_dir: PathBuf = fs::canonicalize("../assets/").expect("Assets should exist");
 
     let ci_status: ExitStatus = Command::new("npm")
@@ -0,0 +1,201 @@
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Path, Query};
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{event, Level};
+use utoipa::ToSchema;
+
+/// Machine readable kind of an API error, which clients can match on
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request was not valid, like a query parameter that is out of range
+    BadRequest,
+    /// The request did not come with valid credentials
+    Unauthorized,
+    /// The credentials of the request do not allow it
+    Forbidden,
+    /// The requested data does not exist
+    NotFound,
+    /// The database could not be opened or was too busy to answer
+    DatabaseUnavailable,
+    /// Anything else that went wrong while answering the request
+    Internal,
+}
+
+impl ErrorCode {
+    /// Status code that the error is sent with
+    fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::DatabaseUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Error returned by the API handlers, which is sent as a JSON body
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+pub struct ApiError {
+    /// Kind of error
+    pub code: ErrorCode,
+    /// Human readable description of the error
+    pub message: String,
+    /// Extra information about the error, like the parameter that was not
+    /// valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    /// Creates an error for a request that is not valid
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        ApiError {
+            code: ErrorCode::BadRequest,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Creates an error for a query parameter that is not valid, which is
+    /// named in the details
+    pub fn invalid_parameter(parameter: &str, message: impl Into<String>) -> Self {
+        ApiError::bad_request(message).with_details(serde_json::json!({ "parameter": parameter }))
+    }
+
+    /// Creates an error for a request without valid credentials
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        ApiError {
+            code: ErrorCode::Unauthorized,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Creates an error for a request that its credentials do not allow
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        ApiError {
+            code: ErrorCode::Forbidden,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Creates an error for data that does not exist
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError {
+            code: ErrorCode::NotFound,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Creates an error for a failed database query, where the message says
+    /// what was being done
+    ///
+    /// The database error itself is only logged, since it can describe the
+    /// queries and files of the server.
+    pub fn database(message: impl Into<String>, err: sqlx::Error) -> Self {
+        let message: String = message.into();
+        event!(Level::ERROR, "{}: {}", message, err);
+        let code: ErrorCode = if is_unavailable(&err) {
+            ErrorCode::DatabaseUnavailable
+        } else {
+            ErrorCode::Internal
+        };
+        ApiError {
+            code,
+            message,
+            details: None,
+        }
+    }
+
+    /// Adds details to the error
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+impl IntoResponse for ApiError {
+    /// Sends the error as JSON with the status code of its kind
+    fn into_response(self) -> Response {
+        let mut response: Response = (self.code.status(), Json(self)).into_response();
+        if response.status() == StatusCode::UNAUTHORIZED {
+            // Bearer is used over Basic so that browsers do not prompt for a
+            // password when the dashboard's session runs out
+            response.headers_mut().insert(
+                WWW_AUTHENTICATE,
+                HeaderValue::from_static(r#"Bearer realm="Nebula Metrics""#),
+            );
+        }
+        response
+    }
+}
+
+/// Whether a database error means the database cannot be used right now, as
+/// opposed to something being wrong with the query
+fn is_unavailable(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => true,
+        // SQLITE_BUSY, SQLITE_LOCKED, and SQLITE_CANTOPEN, which happen while
+        // the monitor holds a lock or before it has created the database. The
+        // extended result codes keep the primary code in their lowest byte.
+        sqlx::Error::Database(db_err) => matches!(
+            db_err
+                .code()
+                .and_then(|code| code.parse::<i32>().ok())
+                .map(|code| code & 0xff),
+            Some(5) | Some(6) | Some(14)
+        ),
+        _ => false,
+    }
+}
+
+/// Query string extractor that rejects requests with an API error
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(ApiQuery(value)),
+            Err(rejection) => Err(ApiError::bad_request(rejection.body_text())),
+        }
+    }
+}
+
+/// Path parameter extractor that rejects requests with an API error
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(value)) => Ok(ApiPath(value)),
+            Err(rejection) => Err(ApiError::bad_request(rejection.body_text())),
+        }
+    }
+}
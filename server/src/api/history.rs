@@ -0,0 +1,160 @@
+use serde::Deserialize;
+use sqlx::{QueryBuilder, Sqlite};
+use utoipa::{IntoParams, ToSchema};
+
+use super::error::ApiError;
+
+/// How the entries in a bucket are combined into one
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregate {
+    /// Average of the entries
+    #[default]
+    Avg,
+    /// Smallest of the entries
+    Min,
+    /// Largest of the entries
+    Max,
+}
+
+impl Aggregate {
+    /// Name of the SQL function for the aggregate
+    pub fn sql_function(&self) -> &'static str {
+        match self {
+            Aggregate::Avg => "AVG",
+            Aggregate::Min => "MIN",
+            Aggregate::Max => "MAX",
+        }
+    }
+}
+
+/// Query parameters shared by every endpoint that returns history
+#[derive(Debug, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryParams {
+    /// Only include entries recorded at or after this Unix epoch timestamp
+    pub from: Option<i64>,
+    /// Only include entries recorded at or before this Unix epoch timestamp
+    pub to: Option<i64>,
+    /// Number of seconds in each bucket, where every series gets a single
+    /// entry per bucket that is stamped with the start of the bucket
+    pub step: Option<i64>,
+    /// Most number of entries to return, keeping the most recent ones
+    pub limit: Option<i64>,
+    /// How the entries in each bucket are combined
+    #[serde(default)]
+    pub agg: Aggregate,
+}
+
+impl HistoryParams {
+    /// Makes sure the parameters describe a range that can be queried
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(ApiError::invalid_parameter(
+                    "from",
+                    format!("from ({}) must not be after to ({})", from, to),
+                ));
+            }
+        }
+        if matches!(self.step, Some(step) if step <= 0) {
+            return Err(ApiError::invalid_parameter(
+                "step",
+                "step must be a positive number of seconds",
+            ));
+        }
+        if matches!(self.limit, Some(limit) if limit <= 0) {
+            return Err(ApiError::invalid_parameter(
+                "limit",
+                "limit must be a positive number of entries",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// How the columns of a history endpoint are treated when the entries are put
+/// into buckets, where every column but TIMESTAMP has to be listed
+pub struct HistoryColumns {
+    /// Columns that identify a series, which the entries are grouped by
+    pub keys: &'static [&'static str],
+    /// Columns that describe an entry without measuring anything, where any
+    /// of the values in the bucket is used
+    pub labels: &'static [&'static str],
+    /// Whole number measurements, which are rounded after they are combined
+    pub integers: &'static [&'static str],
+    /// Decimal measurements
+    pub reals: &'static [&'static str],
+}
+
+/// Builds the query for a history endpoint on top of the query for every one
+/// of its entries, which `push_base` adds to the builder
+///
+/// The entries are limited to the requested range, put into buckets if a step
+/// was given, cut down to the most recent ones if there is a limit, and then
+/// sorted by `order_by`. Column names are given in uppercase so that they
+/// match the responses.
+pub fn history_query<'a>(
+    columns: &HistoryColumns,
+    params: &HistoryParams,
+    order_by: &str,
+    push_base: impl FnOnce(&mut QueryBuilder<'a, Sqlite>),
+) -> QueryBuilder<'a, Sqlite> {
+    let mut query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM (SELECT ");
+    let mut selected = query.separated(", ");
+    match params.step {
+        Some(step) => {
+            selected.push(format!("TIMESTAMP - (TIMESTAMP % {}) AS TIMESTAMP", step));
+            for key in columns.keys {
+                selected.push(format!("{} AS {}", key, key));
+            }
+            for label in columns.labels {
+                selected.push(format!("MAX({}) AS {}", label, label));
+            }
+            let function: &str = params.agg.sql_function();
+            for integer in columns.integers {
+                selected.push(format!(
+                    "CAST(ROUND({}({})) AS INTEGER) AS {}",
+                    function, integer, integer
+                ));
+            }
+            for real in columns.reals {
+                selected.push(format!("{}({}) AS {}", function, real, real));
+            }
+        }
+        None => {
+            selected.push("TIMESTAMP AS TIMESTAMP");
+            for column in columns
+                .keys
+                .iter()
+                .chain(columns.labels)
+                .chain(columns.integers)
+                .chain(columns.reals)
+            {
+                selected.push(format!("{} AS {}", column, column));
+            }
+        }
+    }
+
+    query.push(" FROM (");
+    push_base(&mut query);
+    query.push(") AS history WHERE TRUE");
+    if let Some(from) = params.from {
+        query.push(" AND TIMESTAMP >= ").push_bind(from);
+    }
+    if let Some(to) = params.to {
+        query.push(" AND TIMESTAMP <= ").push_bind(to);
+    }
+    if let Some(step) = params.step {
+        query.push(format!(" GROUP BY TIMESTAMP - (TIMESTAMP % {})", step));
+        for key in columns.keys {
+            query.push(format!(", {}", key));
+        }
+    }
+    // SQLite treats a negative limit as no limit
+    query
+        .push(" ORDER BY TIMESTAMP DESC LIMIT ")
+        .push_bind(params.limit.unwrap_or(-1));
+    query.push(format!(") ORDER BY {};", order_by));
+    query
+}
@@ -0,0 +1,572 @@
+use std::fmt::Write;
+
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use sqlx::{FromRow, SqlitePool};
+
+use super::error::ApiError;
+use super::response::{CpuInfo, DiskInfo, HostInfo, MemoryInfo};
+use super::{
+    fetch_host_info, fetch_latest_cpu_info, fetch_latest_disk_info, fetch_latest_memory_data,
+    require_identity, AppState,
+};
+
+/// Content type of the Prometheus text format
+const EXPOSITION_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Number of processes exported for each of CPU and memory, which keeps the
+/// number of series from growing with the number of processes on the host
+const TOP_PROCESS_COUNT: i64 = 10;
+
+/// Kind of a metric, which tells Prometheus how its values can be used
+#[derive(Debug, Clone, Copy)]
+enum MetricType {
+    /// Value that can go up and down
+    Gauge,
+    /// Running total that only goes up, except when it is reset
+    Counter,
+}
+
+impl MetricType {
+    /// Name of the type on the TYPE line
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Gauge => "gauge",
+            MetricType::Counter => "counter",
+        }
+    }
+}
+
+/// Value of a metric with its labels
+type Sample = (Vec<(&'static str, String)>, f64);
+
+/// Text in the Prometheus text format
+#[derive(Debug, Default)]
+struct Exposition {
+    /// Families that have been written so far
+    text: String,
+}
+
+impl Exposition {
+    /// Adds a metric with its HELP and TYPE lines, where a metric without any
+    /// samples is left out
+    fn family(
+        &mut self,
+        name: &str,
+        metric_type: MetricType,
+        help: &str,
+        samples: impl IntoIterator<Item = Sample>,
+    ) {
+        let mut samples = samples.into_iter().peekable();
+        if samples.peek().is_none() {
+            return;
+        }
+        // Writing to a String cannot fail
+        let _ = writeln!(self.text, "# HELP {} {}", name, escape_help(help));
+        let _ = writeln!(self.text, "# TYPE {} {}", name, metric_type.as_str());
+        for (labels, value) in samples {
+            self.text.push_str(name);
+            if !labels.is_empty() {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|(label, value)| format!("{}=\"{}\"", label, escape_label_value(value)))
+                    .collect();
+                let _ = write!(self.text, "{{{}}}", labels.join(","));
+            }
+            let _ = writeln!(self.text, " {}", format_value(value));
+        }
+    }
+}
+
+/// Escapes the text of a HELP line
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Escapes a label value, which is quoted
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Formats a value the way Prometheus parses it
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        String::from("NaN")
+    } else if value.is_infinite() {
+        String::from(if value > 0.0 { "+Inf" } else { "-Inf" })
+    } else {
+        value.to_string()
+    }
+}
+
+/// Interface counters, either as how much they went up since the previous
+/// sample or as the kernel's running totals
+#[derive(Debug, FromRow)]
+#[sqlx(rename_all = "UPPERCASE")]
+struct NetworkCounters {
+    /// Bytes received
+    bytes_recv: i64,
+    /// Bytes sent
+    bytes_sent: i64,
+    /// Packets received
+    packets_recv: i64,
+    /// Packets sent
+    packets_sent: i64,
+    /// Receive errors
+    err_recv: i64,
+    /// Send errors
+    err_sent: i64,
+    /// Received packets dropped
+    drop_recv: i64,
+    /// Sent packets dropped
+    drop_sent: i64,
+}
+
+/// Interface counter with the middle of its metric names, what it counts, and
+/// its value
+type NetworkCounter = (&'static str, &'static str, fn(&NetworkCounters) -> i64);
+
+/// Every interface counter that is exported
+const NETWORK_COUNTERS: [NetworkCounter; 8] = [
+    ("receive_bytes", "Bytes received", |c| c.bytes_recv),
+    ("transmit_bytes", "Bytes sent", |c| c.bytes_sent),
+    ("receive_packets", "Packets received", |c| c.packets_recv),
+    ("transmit_packets", "Packets sent", |c| c.packets_sent),
+    ("receive_errors", "Receive errors", |c| c.err_recv),
+    ("transmit_errors", "Send errors", |c| c.err_sent),
+    ("receive_drops", "Received packets dropped", |c| c.drop_recv),
+    ("transmit_drops", "Sent packets dropped", |c| c.drop_sent),
+];
+
+/// Latest sample of a network interface that is present, with the time it
+/// covers
+#[derive(Debug, FromRow)]
+#[sqlx(rename_all = "UPPERCASE")]
+struct NetworkRateRow {
+    /// Name of the interface
+    name: String,
+    /// Number of seconds since the previous sample of the interface
+    elapsed: i64,
+    /// How much the counters went up since the previous sample
+    #[sqlx(flatten)]
+    counters: NetworkCounters,
+}
+
+/// Kernel's running totals of a network interface that is present, as of its
+/// latest sample
+#[derive(Debug, FromRow)]
+#[sqlx(rename_all = "UPPERCASE")]
+struct NetworkTotalRow {
+    /// Name of the interface
+    name: String,
+    /// Totals of the counters since the interface was created
+    #[sqlx(flatten)]
+    counters: NetworkCounters,
+}
+
+/// Gets the kernel's totals from the latest sample of each present interface,
+/// where samples from before the totals were recorded are left out
+///
+/// The totals are exported as they are, so they only reset when the kernel's
+/// do, instead of whenever the server restarts or old samples are pruned.
+async fn fetch_network_totals(conn: &SqlitePool) -> Result<Vec<NetworkTotalRow>, sqlx::Error> {
+    let query = r#"
+        SELECT
+            ns.NAME,
+            ns.BYTES_RECV_TOTAL AS BYTES_RECV,
+            ns.BYTES_SENT_TOTAL AS BYTES_SENT,
+            ns.PACKETS_RECV_TOTAL AS PACKETS_RECV,
+            ns.PACKETS_SENT_TOTAL AS PACKETS_SENT,
+            ns.ERR_RECV_TOTAL AS ERR_RECV,
+            ns.ERR_SENT_TOTAL AS ERR_SENT,
+            ns.DROP_RECV_TOTAL AS DROP_RECV,
+            ns.DROP_SENT_TOTAL AS DROP_SENT
+        FROM (
+            SELECT
+                *,
+                ROW_NUMBER() OVER (PARTITION BY NAME ORDER BY TIMESTAMP DESC) AS RECENCY
+            FROM
+                NetworkStat
+        ) AS ns
+        INNER JOIN
+            NetworkInterface ni
+        ON
+            ni.name = ns.name
+        WHERE
+            ni.is_present
+            AND ns.recency = 1
+            AND ns.bytes_recv_total IS NOT NULL
+        ORDER BY
+            ns.name
+    "#;
+
+    sqlx::query_as::<_, NetworkTotalRow>(query)
+        .fetch_all(conn)
+        .await
+}
+
+/// Gets the latest sample of each present interface that has an earlier sample
+/// to compare against
+async fn fetch_network_rates(conn: &SqlitePool) -> Result<Vec<NetworkRateRow>, sqlx::Error> {
+    let query = r#"
+        SELECT
+            ns.NAME,
+            ns.TIMESTAMP - ns.PREV_TIMESTAMP AS ELAPSED,
+            ns.BYTES_RECV,
+            ns.BYTES_SENT,
+            ns.PACKETS_RECV,
+            ns.PACKETS_SENT,
+            ns.ERR_RECV,
+            ns.ERR_SENT,
+            ns.DROP_RECV,
+            ns.DROP_SENT
+        FROM (
+            SELECT
+                *,
+                LAG(TIMESTAMP) OVER (PARTITION BY NAME ORDER BY TIMESTAMP) AS PREV_TIMESTAMP,
+                ROW_NUMBER() OVER (PARTITION BY NAME ORDER BY TIMESTAMP DESC) AS RECENCY
+            FROM
+                NetworkStat
+        ) AS ns
+        INNER JOIN
+            NetworkInterface ni
+        ON
+            ni.name = ns.name
+        WHERE
+            ni.is_present
+            AND ns.recency = 1
+            AND ns.timestamp > ns.prev_timestamp
+        ORDER BY
+            ns.name
+    "#;
+
+    sqlx::query_as::<_, NetworkRateRow>(query)
+        .fetch_all(conn)
+        .await
+}
+
+/// Latest stat of a process that is among the top users of CPU or memory
+#[derive(Debug, FromRow)]
+#[sqlx(rename_all = "UPPERCASE")]
+struct TopProcessRow {
+    /// The PID of the process
+    pid: u32,
+    /// The name of the executable
+    exec: String,
+    /// Fraction of a core used since the previous stat
+    percent_cpu: Option<f64>,
+    /// Resident memory in bytes
+    resident_memory: i64,
+    /// Running total of bytes read from storage
+    io_read_bytes: Option<i64>,
+    /// Running total of bytes written to storage
+    io_write_bytes: Option<i64>,
+}
+
+/// Gets the latest stats of the processes that use the most CPU and the most
+/// memory
+async fn fetch_top_processes(conn: &SqlitePool) -> Result<Vec<TopProcessRow>, sqlx::Error> {
+    let query = r#"
+        WITH latest AS (
+            SELECT
+                ps.PID,
+                ps.START_TIME,
+                p.EXEC,
+                ps.PERCENT_CPU,
+                ps.RESIDENT_MEMORY,
+                ps.IO_READ_BYTES,
+                ps.IO_WRITE_BYTES
+            FROM
+                PROCSTAT ps
+            INNER JOIN
+                PROCESS p
+            ON
+                p.pid = ps.pid
+                AND p.start_time = ps.start_time
+            WHERE
+                ps.timestamp = (SELECT MAX(TIMESTAMP) FROM PROCSTAT)
+        )
+        SELECT
+            PID,
+            EXEC,
+            PERCENT_CPU,
+            RESIDENT_MEMORY,
+            IO_READ_BYTES,
+            IO_WRITE_BYTES
+        FROM
+            latest
+        WHERE
+            (pid, start_time) IN (
+                SELECT pid, start_time FROM latest
+                WHERE percent_cpu IS NOT NULL
+                ORDER BY percent_cpu DESC, pid LIMIT ?
+            )
+            OR (pid, start_time) IN (
+                SELECT pid, start_time FROM latest
+                ORDER BY resident_memory DESC, pid LIMIT ?
+            )
+        ORDER BY
+            pid
+    "#;
+
+    sqlx::query_as::<_, TopProcessRow>(query)
+        .bind(TOP_PROCESS_COUNT)
+        .bind(TOP_PROCESS_COUNT)
+        .fetch_all(conn)
+        .await
+}
+
+/// Creates the router for the Prometheus metrics, which takes the same
+/// credentials as the API
+pub fn create_metrics_router(state: AppState) -> Router {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .route_layer(middleware::from_fn_with_state(
+            state.auth.clone(),
+            require_identity,
+        ))
+        .with_state(state)
+}
+
+/// Renders the host's inventory and the latest CPU, memory, disk, network, and
+/// top process values in the Prometheus text format
+///
+/// Network interfaces are reported as the kernel's running totals for `rate()`
+/// and as rates over the latest tick. Only the processes with the most CPU and memory usage
+/// in the latest tick are included.
+async fn get_metrics(State(state): State<AppState>) -> Result<Response, ApiError> {
+    let host: Option<HostInfo> = fetch_host_info(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error fetching host info", e))?;
+    let cpus: Vec<CpuInfo> = fetch_latest_cpu_info(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error fetching latest CPU information", e))?;
+    let memory: Vec<MemoryInfo> = fetch_latest_memory_data(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error fetching latest memory data", e))?;
+    let disks: Vec<DiskInfo> = fetch_latest_disk_info(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error fetching latest disk information", e))?;
+    let interfaces: Vec<NetworkRateRow> = fetch_network_rates(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error fetching latest network rates", e))?;
+    let interface_totals: Vec<NetworkTotalRow> = fetch_network_totals(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error fetching network totals", e))?;
+    let processes: Vec<TopProcessRow> = fetch_top_processes(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error fetching top processes", e))?;
+
+    let mut exposition: Exposition = Exposition::default();
+    let latest_sample: Option<i64> = memory
+        .iter()
+        .map(|memory| memory.timestamp)
+        .chain(cpus.iter().map(|cpu| cpu.timestamp))
+        .max();
+    exposition.family(
+        "nebula_last_sample_timestamp_seconds",
+        MetricType::Gauge,
+        "Unix time of the latest memory or CPU sample recorded by the monitor.",
+        latest_sample.map(|timestamp| (vec![], timestamp as f64)),
+    );
+
+    // Host, whose labels can be joined onto the other metrics in queries.
+    // Details that are not known are left empty, which Prometheus treats the
+    // same as a missing label.
+    exposition.family(
+        "nebula_host_info",
+        MetricType::Gauge,
+        "Inventory of the host, which is always 1.",
+        host.map(|host| {
+            (
+                vec![
+                    ("hostname", host.hostname),
+                    ("kernel_release", host.kernel_release),
+                    ("os_id", host.os_id.unwrap_or_default()),
+                    ("cpu_model", host.cpu_model.unwrap_or_default()),
+                    ("virtualization", host.virtualization.unwrap_or_default()),
+                    ("container", host.container.unwrap_or_default()),
+                ],
+                1.0,
+            )
+        }),
+    );
+
+    // CPU
+    let core = |cpu: &CpuInfo| vec![("core", cpu.cpu_core.to_string())];
+    exposition.family(
+        "nebula_cpu_usage_ratio",
+        MetricType::Gauge,
+        "CPU time per second of the processes that last ran on the core in the latest tick, which is above 1 when their threads also ran on other cores.",
+        cpus.iter().map(|cpu| (core(cpu), cpu.usage as f64)),
+    );
+    exposition.family(
+        "nebula_cpu_frequency_hertz",
+        MetricType::Gauge,
+        "Clock speed of the core.",
+        cpus.iter()
+            .map(|cpu| (core(cpu), cpu.mhz as f64 * 1_000_000.0)),
+    );
+    exposition.family(
+        "nebula_cpu_cache_bytes",
+        MetricType::Gauge,
+        "Size of the core's cache.",
+        cpus.iter()
+            .map(|cpu| (core(cpu), cpu.total_cache as f64 * 1024.0)),
+    );
+
+    // Memory
+    for (name, help, value) in [
+        (
+            "nebula_memory_total_bytes",
+            "Total usable memory.",
+            (|memory: &MemoryInfo| memory.total_bytes) as fn(&MemoryInfo) -> i64,
+        ),
+        (
+            "nebula_memory_free_bytes",
+            "Memory that is not in use.",
+            |memory: &MemoryInfo| memory.free_bytes,
+        ),
+        (
+            "nebula_memory_swap_total_bytes",
+            "Total swap space.",
+            |memory: &MemoryInfo| memory.swap_total_bytes,
+        ),
+        (
+            "nebula_memory_swap_free_bytes",
+            "Swap space that is not in use.",
+            |memory: &MemoryInfo| memory.swap_free_bytes,
+        ),
+    ] {
+        exposition.family(
+            name,
+            MetricType::Gauge,
+            help,
+            memory.iter().map(|memory| (vec![], value(memory) as f64)),
+        );
+    }
+
+    // Disks
+    let disk_labels = |disk: &DiskInfo| {
+        vec![
+            ("device", disk.device_name.clone()),
+            ("mount", disk.mount.clone()),
+        ]
+    };
+    for (name, help, value) in [
+        (
+            "nebula_disk_used_bytes",
+            "Space used on the file system.",
+            (|disk: &DiskInfo| Some(disk.used_bytes)) as fn(&DiskInfo) -> Option<i64>,
+        ),
+        (
+            "nebula_disk_available_bytes",
+            "Space available to unprivileged users on the file system.",
+            |disk: &DiskInfo| Some(disk.available_bytes),
+        ),
+        (
+            "nebula_disk_read_only",
+            "Whether the file system is mounted read-only (1) or not (0).",
+            |disk: &DiskInfo| Some(disk.is_read_only as i64),
+        ),
+        (
+            "nebula_disk_inodes_total",
+            "Number of inodes on the file system.",
+            |disk: &DiskInfo| disk.inodes_total,
+        ),
+        (
+            "nebula_disk_inodes_used",
+            "Number of inodes in use on the file system.",
+            |disk: &DiskInfo| disk.inodes_used,
+        ),
+        (
+            "nebula_disk_inodes_free",
+            "Number of free inodes on the file system.",
+            |disk: &DiskInfo| disk.inodes_free,
+        ),
+    ] {
+        exposition.family(
+            name,
+            MetricType::Gauge,
+            help,
+            disks
+                .iter()
+                .filter_map(|disk| value(disk).map(|value| (disk_labels(disk), value as f64))),
+        );
+    }
+
+    // Network
+    for (name, counts, value) in NETWORK_COUNTERS {
+        exposition.family(
+            &format!("nebula_network_{}_total", name),
+            MetricType::Counter,
+            &format!("{} since the interface was created.", counts),
+            interface_totals.iter().map(|row| {
+                (
+                    vec![("interface", row.name.clone())],
+                    value(&row.counters) as f64,
+                )
+            }),
+        );
+    }
+    for (name, counts, value) in NETWORK_COUNTERS {
+        exposition.family(
+            &format!("nebula_network_{}_per_second", name),
+            MetricType::Gauge,
+            &format!("{} per second in the latest tick.", counts),
+            interfaces.iter().map(|row| {
+                (
+                    vec![("interface", row.name.clone())],
+                    value(&row.counters) as f64 / row.elapsed as f64,
+                )
+            }),
+        );
+    }
+
+    // Processes
+    let process_labels =
+        |row: &TopProcessRow| vec![("pid", row.pid.to_string()), ("exec", row.exec.clone())];
+    exposition.family(
+        "nebula_process_cpu_usage_ratio",
+        MetricType::Gauge,
+        "Fraction of a core used by the process in the latest tick.",
+        processes.iter().filter_map(|row| {
+            row.percent_cpu
+                .map(|percent_cpu| (process_labels(row), percent_cpu))
+        }),
+    );
+    exposition.family(
+        "nebula_process_resident_memory_bytes",
+        MetricType::Gauge,
+        "Memory that the process has resident in RAM.",
+        processes
+            .iter()
+            .map(|row| (process_labels(row), row.resident_memory as f64)),
+    );
+    exposition.family(
+        "nebula_process_io_read_bytes_total",
+        MetricType::Counter,
+        "Bytes that the process has read from storage.",
+        processes.iter().filter_map(|row| {
+            row.io_read_bytes
+                .map(|bytes| (process_labels(row), bytes as f64))
+        }),
+    );
+    exposition.family(
+        "nebula_process_io_write_bytes_total",
+        MetricType::Counter,
+        "Bytes that the process has written to storage.",
+        processes.iter().filter_map(|row| {
+            row.io_write_bytes
+                .map(|bytes| (process_labels(row), bytes as f64))
+        }),
+    );
+
+    Ok(([(CONTENT_TYPE, EXPOSITION_CONTENT_TYPE)], exposition.text).into_response())
+}
@@ -1,213 +1,950 @@
+mod error;
+mod history;
+mod metrics;
+mod openapi;
+mod process_list;
 pub mod response;
-use response::{CpuInfo, DiskInfo, ProcessInfo};
+mod stream;
+mod top;
+use error::{ApiError, ApiPath, ApiQuery};
+use history::{history_query, HistoryColumns, HistoryParams};
+pub use metrics::create_metrics_router;
+#[cfg(test)]
+pub use openapi::ApiDoc;
+use openapi::{get_docs, get_openapi};
+use process_list::ProcessFilter;
+use response::{
+    BootInfo, CollectorHealthInfo, CpuInfo, DiskEventInfo, DiskInfo, HostInfo, MemoryInfo,
+    NetworkInfo, ProcessInfo, ProtocolStatInfo, SessionInfo, TopProcessInfo,
+};
+use stream::{send_ticks, watch_ticks, StreamParams, Subscription, HEARTBEAT_INTERVAL};
+use top::{group_top_rows, top_query, TopParams, TopRow};
 
-use axum::extract::Path;
-use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
-use models::tables::Memory;
-use sqlx::SqlitePool;
-use tracing::{event, Level};
+use crate::auth::{Authenticator, Identity};
+use axum::extract::{OriginalUri, Request};
+use axum::http::header::HeaderName;
+use axum::http::HeaderMap;
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Response;
+use axum::routing::{get, MethodRouter};
+use axum::{extract::State, Extension, Json, Router};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
 
-/// Absolute path to the database file
-const DB_FILE: &str = "sqlite:///var/nebula/db/nebulaMetrics.db?mode=ro";
+/// Header with the number of entries that matched a request before it was
+/// paged
+pub const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Columns of the MEMORY table in the units of the memory info response, as the
+/// sizes were originally reported in KB
+const MEMORY_INFO_COLUMNS: &str = r#"
+    m.timestamp,
+    m.total / 1000 AS TOTAL,
+    m.free / 1000 AS FREE,
+    m.swap_total / 1000 AS SWAP_TOTAL,
+    m.swap_free / 1000 AS SWAP_FREE,
+    m.total AS TOTAL_BYTES,
+    m.free AS FREE_BYTES,
+    m.swap_total AS SWAP_TOTAL_BYTES,
+    m.swap_free AS SWAP_FREE_BYTES
+"#;
+
+/// Columns of the PROCESS and PROCSTAT tables in the units of the process info
+/// response, as the memory sizes were originally reported in KB
+const PROCESS_INFO_COLUMNS: &str = r#"
+    p.pid,
+    p.exec,
+    p.start_time,
+    p.is_alive,
+    p.init_total_cpu,
+    p.boot_id,
+    p.uid,
+    p.user_name,
+    ps.timestamp,
+    ps.total_cpu,
+    ps.percent_cpu,
+    ps.cpu_core,
+    ps.virtual_memory / 1000 AS VIRTUAL_MEMORY,
+    ps.resident_memory / 1000 AS RESIDENT_MEMORY,
+    ps.shared_memory / 1000 AS SHARED_MEMORY,
+    ps.virtual_memory AS VIRTUAL_MEMORY_BYTES,
+    ps.resident_memory AS RESIDENT_MEMORY_BYTES,
+    ps.shared_memory AS SHARED_MEMORY_BYTES
+"#;
+
+/// Every process joined to its latest PROCSTAT entry, as `p` and `ps`, for the
+/// list of processes and its count
+const LATEST_PROCESS_SOURCE: &str = r#"
+    FROM
+        Process p
+    LEFT JOIN (
+        SELECT
+            pid,
+            start_time,
+            MAX(timestamp) AS latest_timestamp
+        FROM
+            ProcStat
+        GROUP BY
+            pid,
+            start_time
+    ) AS latest_ps
+    ON
+        p.pid = latest_ps.pid
+        AND p.start_time = latest_ps.start_time
+    LEFT JOIN
+        ProcStat ps
+    ON
+        latest_ps.pid = ps.pid
+        AND latest_ps.start_time = ps.start_time
+        AND latest_ps.latest_timestamp = ps.timestamp
+"#;
+
+/// Columns of the PROTOCOLSTAT table as rates, since the counters are stored as
+/// the change since the previous sample
+const PROTOCOL_STAT_COLUMNS: &str = r#"
+    ps.timestamp,
+    ps.elapsed,
+    CAST(ps.tcp_retrans_segs AS REAL) / ps.elapsed AS TCP_RETRANS_SEGS_PER_SEC,
+    CAST(ps.tcp_out_rsts AS REAL) / ps.elapsed AS TCP_OUT_RSTS_PER_SEC,
+    CAST(ps.tcp_estab_resets AS REAL) / ps.elapsed AS TCP_ESTAB_RESETS_PER_SEC,
+    CAST(ps.tcp_active_opens AS REAL) / ps.elapsed AS TCP_ACTIVE_OPENS_PER_SEC,
+    CAST(ps.tcp_passive_opens AS REAL) / ps.elapsed AS TCP_PASSIVE_OPENS_PER_SEC,
+    CAST(ps.tcp_listen_overflows AS REAL) / ps.elapsed AS TCP_LISTEN_OVERFLOWS_PER_SEC,
+    CAST(ps.tcp_listen_drops AS REAL) / ps.elapsed AS TCP_LISTEN_DROPS_PER_SEC,
+    CAST(ps.udp_rcvbuf_errors AS REAL) / ps.elapsed AS UDP_RCVBUF_ERRORS_PER_SEC,
+    CAST(ps.udp_sndbuf_errors AS REAL) / ps.elapsed AS UDP_SNDBUF_ERRORS_PER_SEC,
+    CAST(ps.ip_reasm_fails AS REAL) / ps.elapsed AS IP_REASM_FAILS_PER_SEC
+"#;
+
+/// How the memory info response is put into buckets
+const MEMORY_HISTORY: HistoryColumns = HistoryColumns {
+    keys: &[],
+    labels: &[],
+    integers: &[
+        "TOTAL",
+        "FREE",
+        "SWAP_TOTAL",
+        "SWAP_FREE",
+        "TOTAL_BYTES",
+        "FREE_BYTES",
+        "SWAP_TOTAL_BYTES",
+        "SWAP_FREE_BYTES",
+    ],
+    reals: &[],
+};
+
+/// How the process info response is put into buckets
+const PROCESS_HISTORY: HistoryColumns = HistoryColumns {
+    keys: &["PID", "START_TIME"],
+    labels: &[
+        "EXEC",
+        "IS_ALIVE",
+        "BOOT_ID",
+        "UID",
+        "USER_NAME",
+        "CPU_CORE",
+    ],
+    integers: &[
+        "VIRTUAL_MEMORY",
+        "RESIDENT_MEMORY",
+        "SHARED_MEMORY",
+        "VIRTUAL_MEMORY_BYTES",
+        "RESIDENT_MEMORY_BYTES",
+        "SHARED_MEMORY_BYTES",
+    ],
+    reals: &["TOTAL_CPU", "PERCENT_CPU"],
+};
+
+/// How the CPU info response is put into buckets
+const CPU_HISTORY: HistoryColumns = HistoryColumns {
+    keys: &["CPU_CORE"],
+    labels: &["MHZ", "TOTAL_CACHE"],
+    integers: &[],
+    reals: &["USAGE"],
+};
+
+/// How the network info response is put into buckets
+const NETWORK_HISTORY: HistoryColumns = HistoryColumns {
+    keys: &["NAME"],
+    labels: &[
+        "IP_ADDR",
+        "IS_PRESENT",
+        "MAC_ADDRESS",
+        "MTU",
+        "OPERSTATE",
+        "SPEED",
+    ],
+    integers: &[
+        "BYTES_RECV",
+        "BYTES_SENT",
+        "PACKETS_RECV",
+        "PACKETS_SENT",
+        "ERR_RECV",
+        "ERR_SENT",
+        "DROP_RECV",
+        "DROP_SENT",
+        "FIFO_RECV",
+        "FIFO_SENT",
+        "FRAME_RECV",
+        "COMPRESSED_RECV",
+        "COMPRESSED_SENT",
+        "MULTICAST_RECV",
+        "COLLISIONS",
+    ],
+    reals: &[],
+};
+
+/// How the protocol stat response is put into buckets
+const PROTOCOL_STAT_HISTORY: HistoryColumns = HistoryColumns {
+    keys: &[],
+    labels: &[],
+    integers: &["ELAPSED"],
+    reals: &[
+        "TCP_RETRANS_SEGS_PER_SEC",
+        "TCP_OUT_RSTS_PER_SEC",
+        "TCP_ESTAB_RESETS_PER_SEC",
+        "TCP_ACTIVE_OPENS_PER_SEC",
+        "TCP_PASSIVE_OPENS_PER_SEC",
+        "TCP_LISTEN_OVERFLOWS_PER_SEC",
+        "TCP_LISTEN_DROPS_PER_SEC",
+        "UDP_RCVBUF_ERRORS_PER_SEC",
+        "UDP_SNDBUF_ERRORS_PER_SEC",
+        "IP_REASM_FAILS_PER_SEC",
+    ],
+};
 
 /// Struct for storing the data for the api state
 #[derive(Clone)]
-struct AppState {
+pub struct AppState {
     /// Connection to the database
     conn: SqlitePool,
+    /// Timestamp of the latest tick that the monitor has committed
+    latest_tick: watch::Receiver<Option<i64>>,
+    /// Checks who made each request
+    auth: Arc<Authenticator>,
+}
+
+/// Creates the state shared by the api and metrics routes, which reads from the
+/// database file unless a test gives it a connection
+pub async fn create_app_state(
+    db_file: &Path,
+    test_sql_conn: Option<SqlitePool>,
+    auth: Arc<Authenticator>,
+) -> Result<AppState, sqlx::Error> {
+    let conn: SqlitePool = match test_sql_conn {
+        Some(test_pool) => test_pool,
+        None => {
+            // REGEXP is registered for the process list's executable filter
+            SqlitePool::connect_with(
+                SqliteConnectOptions::new()
+                    .filename(db_file)
+                    .read_only(true)
+                    .with_regexp(),
+            )
+            .await?
+        }
+    };
+    // A single task watches for new ticks, rather than every stream polling
+    let (tick_sender, latest_tick) = watch::channel(None);
+    tokio::spawn(watch_ticks(conn.clone(), tick_sender));
+
+    Ok(AppState {
+        conn,
+        latest_tick,
+        auth,
+    })
 }
 
 /// Creates the router for the api routes
-pub async fn create_api_router(test_sql_conn: Option<SqlitePool>) -> Result<Router, sqlx::Error> {
-    let router: Router = Router::new()
-        .route("/memory", get(get_memory_data))
-        .route("/allProcesses", get(get_all_processes))
-        .route("/process/:pid", get(get_combined_process_info))
-        .route("/disks", get(get_disk_info))
-        .route("/cpu-info", get(get_cpu_info))
-        .route("/cpu-info-current", get(get_latest_cpu_info))
-        .route("/memory-current", get(get_latest_memory_data))
-        .with_state(AppState {
-            conn: match test_sql_conn {
-                Some(test_pool) => test_pool,
-                None => SqlitePool::connect(DB_FILE).await?,
-            },
-        });
-
-    Ok(router)
+pub fn create_api_router(state: AppState) -> Router {
+    let mut router: Router<AppState> = Router::new();
+    for (path, method_router) in api_routes() {
+        router = router.route(path, method_router);
+    }
+    router
+        .route("/openapi.json", get(get_openapi))
+        .route("/docs", get(get_docs))
+        .fallback(get_missing_endpoint)
+        // Runs for every request, including the ones without an endpoint
+        .layer(middleware::from_fn_with_state(
+            state.auth.clone(),
+            require_identity,
+        ))
+        .with_state(state)
+}
+
+/// Every data endpoint of the API with its handler, which all have to be
+/// described by the OpenAPI document
+fn api_routes() -> Vec<(&'static str, MethodRouter<AppState>)> {
+    vec![
+        ("/memory", get(get_memory_data)),
+        ("/allProcesses", get(get_all_processes)),
+        ("/process/:pid", get(get_combined_process_info)),
+        ("/process/:pid/:start_time", get(get_process_instance_info)),
+        ("/top", get(get_top_processes)),
+        ("/disks", get(get_disk_info)),
+        ("/disk-events", get(get_disk_events)),
+        ("/network", get(get_network_info)),
+        ("/protocol-stats", get(get_protocol_stats)),
+        ("/protocol-stats-current", get(get_latest_protocol_stats)),
+        ("/cpu-info", get(get_cpu_info)),
+        ("/cpu-info-current", get(get_latest_cpu_info)),
+        ("/memory-current", get(get_latest_memory_data)),
+        ("/collector-health", get(get_collector_health)),
+        ("/boots", get(get_boots)),
+        ("/host", get(get_host_info)),
+        ("/stream", get(get_stream)),
+        ("/session", get(get_session)),
+    ]
+}
+
+/// Paths of the data endpoints in the router's syntax, where path parameters
+/// start with a colon
+#[cfg(test)]
+pub fn route_paths() -> Vec<&'static str> {
+    api_routes().into_iter().map(|(path, _)| path).collect()
+}
+
+/// Rejects requests that are not from anyone or that the sender is not allowed
+/// to make, when authentication is enabled
+async fn require_identity(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !auth.is_enabled() {
+        return Ok(next.run(request).await);
+    }
+    match auth.identify(request.headers()).await {
+        Some(identity) if identity.can_use(request.method()) => {
+            request.extensions_mut().insert(identity);
+            Ok(next.run(request).await)
+        }
+        Some(identity) => Err(ApiError::forbidden(format!(
+            "{} is not allowed to make {} requests",
+            identity.name,
+            request.method()
+        ))),
+        None => Err(ApiError::unauthorized(
+            "A valid bearer token, username and password, or session is required",
+        )),
+    }
+}
+
+/// Returns who made the request
+///
+/// The dashboard uses this to show who is signed in.
+#[utoipa::path(
+    get,
+    path = "/session",
+    tag = "Auth",
+    responses(
+        (status = 200, description = "Returns whether authentication is enabled and who made the request.", body = SessionInfo),
+        (status = 401, description = "The request did not come with valid credentials.", body = ApiError),
+    ),
+)]
+async fn get_session(
+    State(state): State<AppState>,
+    identity: Option<Extension<Identity>>,
+) -> Json<SessionInfo> {
+    let identity: Option<Identity> = identity.map(|Extension(identity)| identity);
+    Json(SessionInfo {
+        auth_enabled: state.auth.is_enabled(),
+        name: identity.as_ref().map(|identity| identity.name.clone()),
+        role: identity.map(|identity| identity.role),
+    })
 }
 
-/// Returns all data in Memory Table
+/// Returns the error for a path that is not an API endpoint
+async fn get_missing_endpoint(OriginalUri(uri): OriginalUri) -> ApiError {
+    ApiError::not_found(format!("There is no API endpoint at {}", uri.path()))
+}
+
+/// Returns the history of the memory usage
+#[utoipa::path(
+    get,
+    path = "/memory",
+    tag = "Memory",
+    params(HistoryParams),
+    responses(
+        (status = 200, description = "Returns an array of memory data at each stored timestamp.", body = [MemoryInfo]),
+        (status = 400, description = "The query parameters do not describe a valid range.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
 async fn get_memory_data(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Memory>>, (StatusCode, String)> {
-    let res: Result<Vec<Memory>, sqlx::Error> =
-        sqlx::query_as::<_, Memory>("SELECT * FROM Memory;")
-            .fetch_all(&state.conn)
-            .await;
+    ApiQuery(params): ApiQuery<HistoryParams>,
+) -> Result<Json<Vec<MemoryInfo>>, ApiError> {
+    params.validate()?;
+    let base_query: String = format!("SELECT {} FROM Memory m", MEMORY_INFO_COLUMNS);
+    let res: Result<Vec<MemoryInfo>, sqlx::Error> =
+        history_query(&MEMORY_HISTORY, &params, "TIMESTAMP", |query| {
+            query.push(base_query);
+        })
+        .build_query_as::<MemoryInfo>()
+        .fetch_all(&state.conn)
+        .await;
 
     match res {
         Ok(memory_vec) => Ok(Json(memory_vec)),
-        Err(_) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Error fetching memory data".to_string(),
-        )),
+        Err(e) => Err(ApiError::database("Error fetching memory data", e)),
     }
 }
 
-/// Returns all data for all processes
+/// Returns the latest data for every process that matches the filter
+///
+/// Processes that reused a PID are returned separately. The number of
+/// matching processes before the offset and limit are applied is returned in
+/// the `X-Total-Count` header.
+#[utoipa::path(
+    get,
+    path = "/allProcesses",
+    tag = "Process",
+    params(ProcessFilter),
+    responses(
+        (
+            status = 200,
+            description = "Returns an array of process records from the latest timestamp or the last record stored for each process.",
+            body = [ProcessInfo],
+            headers(("x-total-count" = usize, description = "Number of processes that matched the filters.")),
+        ),
+        (status = 400, description = "The query parameters are not valid.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
 async fn get_all_processes(
     State(state): State<AppState>,
-) -> Result<Json<Vec<ProcessInfo>>, (StatusCode, String)> {
-    let query = r#"
-        SELECT
-            p.pid,
-            p.exec,
-            p.start_time,
-            p.is_alive,
-            p.init_total_cpu,
-            ps.timestamp,
-            ps.total_cpu,
-            ps.percent_cpu,
-            ps.cpu_core,
-            ps.virtual_memory,
-            ps.resident_memory,
-            ps.shared_memory
-        FROM
-            Process p
-        LEFT JOIN (
-            SELECT
-                pid,
-                MAX(timestamp) AS latest_timestamp
-            FROM
-                ProcStat
-            GROUP BY
-                pid
-        ) AS latest_ps
-        ON
-            p.pid = latest_ps.pid
-        LEFT JOIN
-            ProcStat ps
-        ON
-            latest_ps.pid = ps.pid
-            AND latest_ps.latest_timestamp = ps.timestamp
-    "#;
-
-    let res = sqlx::query_as::<_, ProcessInfo>(query)
+    ApiQuery(filter): ApiQuery<ProcessFilter>,
+) -> Result<([(HeaderName, String); 1], Json<Vec<ProcessInfo>>), ApiError> {
+    filter.validate()?;
+    let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+        "SELECT {} {}",
+        PROCESS_INFO_COLUMNS, LATEST_PROCESS_SOURCE
+    ));
+    filter.push_conditions(&mut query);
+    filter.push_order_by(&mut query);
+    filter.push_limit(&mut query);
+    query.push(";");
+    let page: Vec<ProcessInfo> = query
+        .build_query_as::<ProcessInfo>()
         .fetch_all(&state.conn)
-        .await;
+        .await
+        .map_err(|e| ApiError::database("Error fetching all processes", e))?;
 
-    match res {
-        Ok(process_infos) => Ok(Json(process_infos)),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Error fetching all processes: {}", e),
+    let mut count_query: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("SELECT COUNT(*) {}", LATEST_PROCESS_SOURCE));
+    filter.push_conditions(&mut count_query);
+    count_query.push(";");
+    let total_count: i64 = count_query
+        .build_query_scalar::<i64>()
+        .fetch_one(&state.conn)
+        .await
+        .map_err(|e| ApiError::database("Error counting all processes", e))?;
+
+    Ok(([(TOTAL_COUNT_HEADER, total_count.to_string())], Json(page)))
+}
+
+/// Returns the history of every process that has had the specified PID
+///
+/// Processes that reused the PID can be told apart by their start time.
+#[utoipa::path(
+    get,
+    path = "/process/{pid}",
+    tag = "Process",
+    params(
+        ("pid" = u32, Path, description = "PID of the process to get."),
+        HistoryParams,
+    ),
+    responses(
+        (status = 200, description = "Returns an array of process records for the specified PID, newest first.", body = [ProcessInfo]),
+        (status = 400, description = "The query parameters do not describe a valid range.", body = ApiError),
+        (status = 404, description = "Requested PID does not exist in the database.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_combined_process_info(
+    state: State<AppState>,
+    ApiPath(pid): ApiPath<u32>,
+    ApiQuery(params): ApiQuery<HistoryParams>,
+) -> Result<Json<Vec<ProcessInfo>>, ApiError> {
+    params.validate()?;
+    match get_process_info(&state.conn, pid, None, &params).await {
+        Ok(Some(combined_infos)) => Ok(Json(combined_infos)),
+        Ok(None) => Err(ApiError::not_found(format!("Process {} not found", pid))
+            .with_details(serde_json::json!({ "pid": pid }))),
+        Err(err) => Err(ApiError::database(
+            format!("Error fetching combined process info for PID {}", pid),
+            err,
         )),
     }
 }
 
-/// Returns the information of the specified process
-async fn get_combined_process_info(
+/// Returns the history of the process with the specified PID and start time
+#[utoipa::path(
+    get,
+    path = "/process/{pid}/{start_time}",
+    tag = "Process",
+    params(
+        ("pid" = u32, Path, description = "PID of the process to get."),
+        ("start_time" = i64, Path, description = "Unix epoch timestamp that the process started at."),
+        HistoryParams,
+    ),
+    responses(
+        (status = 200, description = "Returns an array of process records for the specified process, newest first.", body = [ProcessInfo]),
+        (status = 400, description = "The query parameters do not describe a valid range.", body = ApiError),
+        (status = 404, description = "Requested process does not exist in the database.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_process_instance_info(
     state: State<AppState>,
-    Path(pid): Path<u32>,
-) -> Result<Json<Vec<ProcessInfo>>, (StatusCode, String)> {
-    // Execute the SQL query to fetch combined process info
-    let query_result = sqlx::query_as::<_, ProcessInfo>(
+    ApiPath((pid, start_time)): ApiPath<(u32, i64)>,
+    ApiQuery(params): ApiQuery<HistoryParams>,
+) -> Result<Json<Vec<ProcessInfo>>, ApiError> {
+    params.validate()?;
+    match get_process_info(&state.conn, pid, Some(start_time), &params).await {
+        Ok(Some(instance_infos)) => Ok(Json(instance_infos)),
+        Ok(None) => Err(ApiError::not_found(format!(
+            "Process {} started at {} not found",
+            pid, start_time
+        ))
+        .with_details(serde_json::json!({ "pid": pid, "start_time": start_time }))),
+        Err(err) => Err(ApiError::database(
+            format!(
+                "Error fetching process info for PID {} started at {}",
+                pid, start_time
+            ),
+            err,
+        )),
+    }
+}
+
+/// Gets the history of the processes with the given PID, newest first, which
+/// can be limited to the process that started at the given time
+///
+/// Returns None if there is no such process, as opposed to a process without
+/// any history in the requested range.
+async fn get_process_info(
+    conn: &SqlitePool,
+    pid: u32,
+    start_time: Option<i64>,
+    params: &HistoryParams,
+) -> Result<Option<Vec<ProcessInfo>>, sqlx::Error> {
+    let base_query: String = format!(
         r#"
-        SELECT *
+        SELECT
+            {}
         FROM
             PROCESS p
         JOIN
-            PROCSTAT ps ON p.PID = ps.PID
+            PROCSTAT ps ON p.PID = ps.PID AND p.START_TIME = ps.START_TIME
         WHERE
-            p.PID = ?
-        ORDER BY timestamp DESC;
+            p.PID =
         "#,
+        PROCESS_INFO_COLUMNS
+    );
+    let process_infos: Vec<ProcessInfo> =
+        history_query(&PROCESS_HISTORY, params, "TIMESTAMP DESC", |query| {
+            query.push(base_query).push_bind(pid);
+            if let Some(start_time) = start_time {
+                query.push(" AND p.START_TIME = ").push_bind(start_time);
+            }
+        })
+        .build_query_as::<ProcessInfo>()
+        .fetch_all(conn)
+        .await?;
+    if !process_infos.is_empty() {
+        return Ok(Some(process_infos));
+    }
+
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM PROCSTAT
+            WHERE PID = ? AND (? IS NULL OR START_TIME = ?));",
     )
     .bind(pid)
-    .fetch_all(&state.conn)
-    .await;
+    .bind(start_time)
+    .bind(start_time)
+    .fetch_one(conn)
+    .await?;
+    Ok(exists.then_some(process_infos))
+}
 
-    // Match the query result
-    match query_result {
-        Ok(combined_infos) => {
-            if combined_infos.is_empty() {
-                Err((
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                    format!("Process {} not found", pid),
-                ))
-            } else {
-                Ok(Json(combined_infos))
-            }
-        }
-        Err(err) => {
-            event!(
-                Level::ERROR,
-                "Error fetching combined process info for PID {}: {}",
-                pid,
-                err
-            );
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!(
-                    "Error fetching combined process info for PID {}: {}",
-                    pid, err
-                ),
-            ))
-        }
+/// Returns the processes that used the most of a resource
+///
+/// The window ends at the latest process stat, and each process comes with
+/// its history in the window so that it can be charted. The I/O rate of the
+/// first stat of a process in the window is unknown.
+#[utoipa::path(
+    get,
+    path = "/top",
+    tag = "Process",
+    params(TopParams),
+    responses(
+        (status = 200, description = "Returns an array of processes, highest ranked first.", body = [TopProcessInfo]),
+        (status = 400, description = "The query parameters are not valid.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_top_processes(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<TopParams>,
+) -> Result<Json<Vec<TopProcessInfo>>, ApiError> {
+    let window: i64 = params.validate()?;
+    let res: Result<Vec<TopRow>, sqlx::Error> = top_query(&params, window)
+        .build_query_as::<TopRow>()
+        .fetch_all(&state.conn)
+        .await;
+
+    match res {
+        Ok(rows) => Ok(Json(group_top_rows(rows))),
+        Err(e) => Err(ApiError::database("Error fetching top processes", e)),
     }
 }
 
-/// Returns the latest disk information for each device
-async fn get_disk_info(
+/// Streams the samples of every tick the monitor commits as Server-Sent Events
+///
+/// Each event is named after its metric and has the Unix epoch timestamp of
+/// the tick as its ID. The cpu, memory, and process events hold the same
+/// arrays as `/cpu-info-current`, `/memory-current`, and `/process/{pid}`,
+/// limited to the one tick. The stream starts with the latest tick, or with
+/// the ticks after the one in the `Last-Event-ID` header, and a comment is
+/// sent every 15 seconds to keep idle streams open.
+#[utoipa::path(
+    get,
+    path = "/stream",
+    tag = "Stream",
+    params(
+        StreamParams,
+        ("Last-Event-ID" = Option<i64>, Header, description = "ID of the last event received before reconnecting, so that the ticks after it are sent first."),
+    ),
+    responses(
+        (status = 200, description = "Returns a stream of events.", content_type = "text/event-stream", body = String),
+        (status = 400, description = "The query parameters or Last-Event-ID are not valid.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_stream(
     State(state): State<AppState>,
-) -> Result<Json<Vec<DiskInfo>>, (StatusCode, String)> {
+    headers: HeaderMap,
+    ApiQuery(params): ApiQuery<StreamParams>,
+) -> Result<Sse<ReceiverStream<Result<Event, Infallible>>>, ApiError> {
+    let subscription: Subscription = params.subscription()?;
+    let last_event_id: Option<i64> = match headers.get("last-event-id") {
+        Some(value) => Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(|id| id.trim().parse::<i64>().ok())
+                .ok_or_else(|| {
+                    ApiError::bad_request(
+                        "Last-Event-ID must be the ID of an event from the stream",
+                    )
+                })?,
+        ),
+        None => None,
+    };
+
+    let (events, stream) = mpsc::channel(16);
+    tokio::spawn(send_ticks(
+        state.conn.clone(),
+        subscription,
+        last_event_id,
+        state.latest_tick.clone(),
+        events,
+    ));
+    Ok(Sse::new(ReceiverStream::new(stream))
+        .keep_alive(KeepAlive::new().interval(HEARTBEAT_INTERVAL)))
+}
+
+/// Gets the latest stats of each mounted disk
+async fn fetch_latest_disk_info(conn: &SqlitePool) -> Result<Vec<DiskInfo>, sqlx::Error> {
     let query = r#"
         SELECT
             d.device_name,
             d.mount,
             d.fs_type,
             ds.timestamp,
-            ds.used,
-            ds.available
+            -- Sizes were originally reported in MB
+            ds.used / 1048576 AS USED,
+            ds.available / 1048576 AS AVAILABLE,
+            ds.used AS USED_BYTES,
+            ds.available AS AVAILABLE_BYTES,
+            d.mount_options,
+            d.is_read_only,
+            ds.inodes_total,
+            ds.inodes_used,
+            ds.inodes_free
         FROM
             Disk d
         INNER JOIN (
             SELECT
                 device_name,
+                mount,
                 MAX(timestamp) AS latest_timestamp
             FROM
                 DiskStat
             GROUP BY
-                device_name
+                device_name,
+                mount
         ) AS latest_ds
         ON
             d.device_name = latest_ds.device_name
+            AND d.mount = latest_ds.mount
         INNER JOIN
             DiskStat ds
         ON
             latest_ds.device_name = ds.device_name
+            AND latest_ds.mount = ds.mount
             AND latest_ds.latest_timestamp = ds.timestamp
+        WHERE
+            d.is_mounted
+        ORDER BY
+            d.device_name,
+            d.mount
+    "#;
+
+    sqlx::query_as::<_, DiskInfo>(query).fetch_all(conn).await
+}
+
+/// Returns the latest disk information for each mounted disk
+#[utoipa::path(
+    get,
+    path = "/disks",
+    tag = "Disk",
+    responses(
+        (status = 200, description = "Returns an array of disk data from the latest timestamp.", body = [DiskInfo]),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_disk_info(State(state): State<AppState>) -> Result<Json<Vec<DiskInfo>>, ApiError> {
+    let res: Result<Vec<DiskInfo>, sqlx::Error> = fetch_latest_disk_info(&state.conn).await;
+
+    match res {
+        Ok(disk_info) => Ok(Json(disk_info)),
+        Err(e) => Err(ApiError::database(
+            "Error fetching latest disk information",
+            e,
+        )),
+    }
+}
+
+/// Returns every event recorded for the disks, oldest first
+///
+/// Events include a file system being remounted read-only, which usually
+/// means that the disk ran into errors.
+#[utoipa::path(
+    get,
+    path = "/disk-events",
+    tag = "Disk",
+    responses(
+        (status = 200, description = "Returns an array of disk events, oldest first.", body = [DiskEventInfo]),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_disk_events(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DiskEventInfo>>, ApiError> {
+    let query = r#"
+        SELECT
+            de.*
+        FROM
+            DiskEvent de
+        ORDER BY
+            de.timestamp,
+            de.device_name,
+            de.mount
     "#;
 
-    let res = sqlx::query_as::<_, DiskInfo>(query)
+    let res = sqlx::query_as::<_, DiskEventInfo>(query)
         .fetch_all(&state.conn)
         .await;
 
     match res {
-        Ok(disk_info) => Ok(Json(disk_info)),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Error fetching latest disk information: {}", e),
+        Ok(event_vec) => Ok(Json(event_vec)),
+        Err(e) => Err(ApiError::database("Error fetching disk events", e)),
+    }
+}
+
+/// Returns the history of every network interface
+///
+/// Interfaces that have since been removed are included, and each entry holds
+/// how much the counters went up since the previous entry.
+#[utoipa::path(
+    get,
+    path = "/network",
+    tag = "Network",
+    params(HistoryParams),
+    responses(
+        (status = 200, description = "Returns an array of network data ordered by interface and timestamp.", body = [NetworkInfo]),
+        (status = 400, description = "The query parameters do not describe a valid range.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_network_info(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<HistoryParams>,
+) -> Result<Json<Vec<NetworkInfo>>, ApiError> {
+    params.validate()?;
+    let base_query = r#"
+        SELECT
+            ni.name,
+            ni.ip_addr,
+            ni.is_present,
+            ni.mac_address,
+            ni.mtu,
+            ni.operstate,
+            ni.speed,
+            ns.timestamp,
+            ns.bytes_recv,
+            ns.bytes_sent,
+            ns.packets_recv,
+            ns.packets_sent,
+            ns.err_recv,
+            ns.err_sent,
+            ns.drop_recv,
+            ns.drop_sent,
+            ns.fifo_recv,
+            ns.fifo_sent,
+            ns.frame_recv,
+            ns.compressed_recv,
+            ns.compressed_sent,
+            ns.multicast_recv,
+            ns.collisions
+        FROM
+            NetworkInterface ni
+        INNER JOIN
+            NetworkStat ns
+        ON
+            ni.name = ns.name
+    "#;
+
+    let res = history_query(&NETWORK_HISTORY, &params, "NAME, TIMESTAMP", |query| {
+        query.push(base_query);
+    })
+    .build_query_as::<NetworkInfo>()
+    .fetch_all(&state.conn)
+    .await;
+
+    match res {
+        Ok(network_info) => Ok(Json(network_info)),
+        Err(e) => Err(ApiError::database("Error fetching network information", e)),
+    }
+}
+
+/// Returns the history of the rates of the kernel's network protocol statistics
+#[utoipa::path(
+    get,
+    path = "/protocol-stats",
+    tag = "Protocol",
+    params(HistoryParams),
+    responses(
+        (status = 200, description = "Returns an array of protocol statistics ordered by timestamp.", body = [ProtocolStatInfo]),
+        (status = 400, description = "The query parameters do not describe a valid range.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_protocol_stats(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<HistoryParams>,
+) -> Result<Json<Vec<ProtocolStatInfo>>, ApiError> {
+    params.validate()?;
+    let base_query: String = format!("SELECT {} FROM ProtocolStat ps", PROTOCOL_STAT_COLUMNS);
+    let res: Result<Vec<ProtocolStatInfo>, sqlx::Error> =
+        history_query(&PROTOCOL_STAT_HISTORY, &params, "TIMESTAMP", |query| {
+            query.push(base_query);
+        })
+        .build_query_as::<ProtocolStatInfo>()
+        .fetch_all(&state.conn)
+        .await;
+
+    match res {
+        Ok(stat_vec) => Ok(Json(stat_vec)),
+        Err(e) => Err(ApiError::database("Error fetching protocol statistics", e)),
+    }
+}
+
+/// Returns the latest rates of the kernel's network protocol statistics
+#[utoipa::path(
+    get,
+    path = "/protocol-stats-current",
+    tag = "Protocol",
+    responses(
+        (status = 200, description = "Returns an array with the protocol statistics from the latest timestamp.", body = [ProtocolStatInfo]),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_latest_protocol_stats(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ProtocolStatInfo>>, ApiError> {
+    let query: String = format!(
+        r#"
+        SELECT
+            {}
+        FROM
+            ProtocolStat ps
+        INNER JOIN (
+            SELECT
+                MAX(timestamp) AS latest_timestamp
+            FROM
+                ProtocolStat
+        ) AS latest_ps
+        ON
+            ps.timestamp = latest_ps.latest_timestamp
+        "#,
+        PROTOCOL_STAT_COLUMNS
+    );
+
+    let res: Result<Vec<ProtocolStatInfo>, sqlx::Error> =
+        sqlx::query_as::<_, ProtocolStatInfo>(&query)
+            .fetch_all(&state.conn)
+            .await;
+
+    match res {
+        Ok(stat_vec) => Ok(Json(stat_vec)),
+        Err(e) => Err(ApiError::database(
+            "Error fetching latest protocol statistics",
+            e,
         )),
     }
 }
 
-/// Returns all CPU information
+/// Returns the history of every CPU core
+#[utoipa::path(
+    get,
+    path = "/cpu-info",
+    tag = "CPU",
+    params(HistoryParams),
+    responses(
+        (status = 200, description = "Returns an array of CPU data at each stored timestamp.", body = [CpuInfo]),
+        (status = 400, description = "The query parameters do not describe a valid range.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
 async fn get_cpu_info(
     State(state): State<AppState>,
-) -> Result<Json<Vec<CpuInfo>>, (StatusCode, String)> {
-    let query = r#"
+    ApiQuery(params): ApiQuery<HistoryParams>,
+) -> Result<Json<Vec<CpuInfo>>, ApiError> {
+    params.validate()?;
+    let base_query = r#"
         SELECT
             c.cpu_core,
             c.mhz,
@@ -222,23 +959,21 @@ async fn get_cpu_info(
             c.cpu_core = cs.cpu_core
     "#;
 
-    let res = sqlx::query_as::<_, CpuInfo>(query)
-        .fetch_all(&state.conn)
-        .await;
+    let res = history_query(&CPU_HISTORY, &params, "CPU_CORE, TIMESTAMP", |query| {
+        query.push(base_query);
+    })
+    .build_query_as::<CpuInfo>()
+    .fetch_all(&state.conn)
+    .await;
 
     match res {
         Ok(cpu_info) => Ok(Json(cpu_info)),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Error fetching CPU information: {}", e),
-        )),
+        Err(e) => Err(ApiError::database("Error fetching CPU information", e)),
     }
 }
 
-/// Returns the latest CPU information for each core
-async fn get_latest_cpu_info(
-    State(state): State<AppState>,
-) -> Result<Json<Vec<CpuInfo>>, (StatusCode, String)> {
+/// Gets the latest usage of each online CPU core
+async fn fetch_latest_cpu_info(conn: &SqlitePool) -> Result<Vec<CpuInfo>, sqlx::Error> {
     let query = r#"
         SELECT
             c.cpu_core,
@@ -264,28 +999,45 @@ async fn get_latest_cpu_info(
         ON
             latest_cs.cpu_core = cs.cpu_core
             AND latest_cs.latest_timestamp = cs.timestamp
+        WHERE
+            c.is_online
     "#;
 
-    let res = sqlx::query_as::<_, CpuInfo>(query)
-        .fetch_all(&state.conn)
-        .await;
+    sqlx::query_as::<_, CpuInfo>(query).fetch_all(conn).await
+}
+
+/// Returns the latest CPU information for each online core
+#[utoipa::path(
+    get,
+    path = "/cpu-info-current",
+    tag = "CPU",
+    responses(
+        (status = 200, description = "Returns an array of CPU data from the latest timestamp.", body = [CpuInfo]),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_latest_cpu_info(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<CpuInfo>>, ApiError> {
+    let res: Result<Vec<CpuInfo>, sqlx::Error> = fetch_latest_cpu_info(&state.conn).await;
 
     match res {
         Ok(cpu_info) => Ok(Json(cpu_info)),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Error fetching latest CPU information: {}", e),
+        Err(e) => Err(ApiError::database(
+            "Error fetching latest CPU information",
+            e,
         )),
     }
 }
 
-/// Returns the latest data from the Memory table
-async fn get_latest_memory_data(
-    State(state): State<AppState>,
-) -> Result<Json<Vec<Memory>>, (StatusCode, String)> {
-    let query = r#"
+/// Gets the latest memory record, which is empty before the monitor's first
+/// tick
+async fn fetch_latest_memory_data(conn: &SqlitePool) -> Result<Vec<MemoryInfo>, sqlx::Error> {
+    let query: String = format!(
+        r#"
         SELECT
-            m.*
+            {}
         FROM
             Memory m
         INNER JOIN (
@@ -296,17 +1048,144 @@ async fn get_latest_memory_data(
         ) AS latest_mem
         ON
             m.timestamp = latest_mem.latest_timestamp
+        "#,
+        MEMORY_INFO_COLUMNS
+    );
+
+    sqlx::query_as::<_, MemoryInfo>(&query)
+        .fetch_all(conn)
+        .await
+}
+
+/// Returns the latest data from the Memory table
+#[utoipa::path(
+    get,
+    path = "/memory-current",
+    tag = "Memory",
+    responses(
+        (status = 200, description = "Returns an array of a single memory record from the latest timestamp.", body = [MemoryInfo]),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_latest_memory_data(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<MemoryInfo>>, ApiError> {
+    let res: Result<Vec<MemoryInfo>, sqlx::Error> = fetch_latest_memory_data(&state.conn).await;
+
+    match res {
+        Ok(memory_vec) => Ok(Json(memory_vec)),
+        Err(e) => Err(ApiError::database("Error fetching latest memory data", e)),
+    }
+}
+
+/// Returns the health of each of the monitor's collectors
+///
+/// The health tells which data is stale and why.
+#[utoipa::path(
+    get,
+    path = "/collector-health",
+    tag = "Monitor",
+    responses(
+        (status = 200, description = "Returns an array of the health of each collector.", body = [CollectorHealthInfo]),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_collector_health(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<CollectorHealthInfo>>, ApiError> {
+    let query = r#"
+        SELECT
+            ch.*,
+            CAST(strftime('%s', 'now') AS INTEGER) - COALESCE(ch.last_success, 0)
+                > 2 * ch.interval AS IS_STALE
+        FROM
+            Collector_Health ch
+        ORDER BY
+            ch.name
     "#;
 
-    let res = sqlx::query_as::<_, Memory>(query)
+    let res = sqlx::query_as::<_, CollectorHealthInfo>(query)
         .fetch_all(&state.conn)
         .await;
 
     match res {
-        Ok(memory_vec) => Ok(Json(memory_vec)),
-        Err(_) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Error fetching latest memory data".to_string(),
+        Ok(health_vec) => Ok(Json(health_vec)),
+        Err(e) => Err(ApiError::database("Error fetching collector health", e)),
+    }
+}
+
+/// Returns every boot of the system that the monitor has seen, oldest first
+///
+/// The boots can be used to mark reboots on charts.
+#[utoipa::path(
+    get,
+    path = "/boots",
+    tag = "Boot",
+    responses(
+        (status = 200, description = "Returns an array of boots, oldest first.", body = [BootInfo]),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_boots(State(state): State<AppState>) -> Result<Json<Vec<BootInfo>>, ApiError> {
+    let query = r#"
+        SELECT
+            b.*
+        FROM
+            Boot b
+        ORDER BY
+            b.boot_time
+    "#;
+
+    let res = sqlx::query_as::<_, BootInfo>(query)
+        .fetch_all(&state.conn)
+        .await;
+
+    match res {
+        Ok(boot_vec) => Ok(Json(boot_vec)),
+        Err(e) => Err(ApiError::database("Error fetching boots", e)),
+    }
+}
+
+/// Gets the inventory of the host, which is missing before the monitor's first
+/// tick
+async fn fetch_host_info(conn: &SqlitePool) -> Result<Option<HostInfo>, sqlx::Error> {
+    let query = r#"
+        SELECT
+            h.*
+        FROM
+            Host h
+    "#;
+
+    sqlx::query_as::<_, HostInfo>(query)
+        .fetch_optional(conn)
+        .await
+}
+
+/// Returns the inventory of the host that the monitor is running on
+///
+/// The hostname, kernel, distribution, CPU, memory, and virtualization details
+/// are refreshed when they change.
+#[utoipa::path(
+    get,
+    path = "/host",
+    tag = "Host",
+    responses(
+        (status = 200, description = "Returns the host's details.", body = HostInfo),
+        (status = 404, description = "The monitor has not collected the host's details yet.", body = ApiError),
+        (status = 500, description = "Error occurred while fetching the data.", body = ApiError),
+        (status = 503, description = "The database is unavailable or too busy to answer.", body = ApiError),
+    ),
+)]
+async fn get_host_info(State(state): State<AppState>) -> Result<Json<HostInfo>, ApiError> {
+    match fetch_host_info(&state.conn).await {
+        Ok(Some(host)) => Ok(Json(host)),
+        // The monitor has not recorded the host yet
+        Ok(None) => Err(ApiError::not_found(
+            "Host information has not been collected yet",
         )),
+        Err(e) => Err(ApiError::database("Error fetching host info", e)),
     }
 }
@@ -0,0 +1,141 @@
+use axum::response::Html;
+use axum::Json;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::SecurityRequirement;
+use utoipa::{Modify, OpenApi};
+
+use super::error::{ApiError, ErrorCode};
+use super::history::Aggregate;
+use super::process_list::{ProcessSort, SortOrder};
+use super::response::{
+    BootInfo, CollectorHealthInfo, CpuInfo, DiskEventInfo, DiskInfo, HostInfo, MemoryInfo,
+    NetworkInfo, ProcessInfo, ProtocolStatInfo, SessionInfo, TopProcessInfo, TopSample,
+};
+use super::top::TopMetric;
+use crate::auth::Role;
+
+/// Page that renders the OpenAPI document with Swagger UI
+const DOCS_PAGE: &str = include_str!("docs.html");
+
+/// OpenAPI document of the API, which is generated from the handlers and the
+/// response types
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Nebula Metrics API",
+        description = "API for fetching data stored by Nebula Metrics. The API is run through the nebula-server.service Systemd service and has a base URL of 0.0.0.0:4242/api. Errors are returned as a JSON object with a code, a message, and sometimes details, under a status code that matches the code. When the server is configured with tokens or users, every request needs a bearer token, a username and password, or a dashboard session, and is rejected with a 401 otherwise. Tokens and users with the read_only role are rejected with a 403 for anything but GET requests.",
+    ),
+    servers((url = "/api")),
+    modifiers(&RemoveEmptyLicense, &AddSecuritySchemes),
+    paths(
+        super::get_cpu_info,
+        super::get_latest_cpu_info,
+        super::get_disk_info,
+        super::get_disk_events,
+        super::get_network_info,
+        super::get_protocol_stats,
+        super::get_latest_protocol_stats,
+        super::get_memory_data,
+        super::get_latest_memory_data,
+        super::get_all_processes,
+        super::get_combined_process_info,
+        super::get_process_instance_info,
+        super::get_top_processes,
+        super::get_boots,
+        super::get_host_info,
+        super::get_collector_health,
+        super::get_stream,
+        super::get_session,
+    ),
+    components(schemas(
+        Aggregate,
+        ApiError,
+        BootInfo,
+        CollectorHealthInfo,
+        CpuInfo,
+        DiskEventInfo,
+        DiskInfo,
+        ErrorCode,
+        HostInfo,
+        MemoryInfo,
+        NetworkInfo,
+        ProcessInfo,
+        ProcessSort,
+        ProtocolStatInfo,
+        Role,
+        SessionInfo,
+        SortOrder,
+        TopMetric,
+        TopProcessInfo,
+        TopSample,
+    )),
+    tags(
+        (name = "CPU", description = "CPU information for the overall system."),
+        (name = "Disk", description = "Disk information for the overall system."),
+        (name = "Memory", description = "Memory information for the overall system."),
+        (name = "Network", description = "Traffic through each network interface."),
+        (name = "Protocol", description = "The kernel's network protocol statistics for the overall system."),
+        (name = "Process", description = "Information for both active and dead processes."),
+        (name = "Monitor", description = "Status of the monitor that collects the data."),
+        (name = "Boot", description = "History of the system's boots."),
+        (name = "Host", description = "Inventory of the host that the monitor is running on."),
+        (name = "Stream", description = "Live samples pushed as the monitor records them."),
+        (name = "Auth", description = "Who is making requests to the API."),
+    )
+)]
+pub struct ApiDoc;
+
+/// Drops the license that is filled in from Cargo, which is empty since the
+/// crates do not declare one
+struct RemoveEmptyLicense;
+
+impl Modify for RemoveEmptyLicense {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if openapi
+            .info
+            .license
+            .as_ref()
+            .is_some_and(|license| license.name.is_empty())
+        {
+            openapi.info.license = None;
+        }
+    }
+}
+
+/// Adds the ways that requests can be authenticated, which are only checked
+/// when the server has tokens or users configured
+struct AddSecuritySchemes;
+
+impl Modify for AddSecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "basic",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("nebula_session"))),
+        );
+        openapi.security = Some(
+            ["bearer_token", "basic", "session"]
+                .into_iter()
+                .map(|name| SecurityRequirement::new::<&str, [&str; 0], &str>(name, []))
+                .collect(),
+        );
+    }
+}
+
+/// Returns the OpenAPI document of the API
+pub async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Returns the page for browsing the OpenAPI document
+pub async fn get_docs() -> Html<&'static str> {
+    Html(DOCS_PAGE)
+}
@@ -0,0 +1,181 @@
+use regex::Regex;
+use serde::Deserialize;
+use sqlx::{QueryBuilder, Sqlite};
+use utoipa::{IntoParams, ToSchema};
+
+use super::error::ApiError;
+
+/// What the list of processes is sorted by
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessSort {
+    /// PID of the process
+    #[default]
+    Pid,
+    /// Time the process started
+    StartTime,
+    /// Path of the executable
+    Exec,
+    /// Name of the user that owns the process
+    User,
+    /// Fraction of a core used in the latest entry
+    Cpu,
+    /// Resident memory in the latest entry
+    Memory,
+}
+
+impl ProcessSort {
+    /// Column of the process list query that is sorted on
+    fn column(&self) -> &'static str {
+        match self {
+            ProcessSort::Pid => "p.PID",
+            ProcessSort::StartTime => "p.START_TIME",
+            ProcessSort::Exec => "p.EXEC",
+            ProcessSort::User => "p.USER_NAME",
+            ProcessSort::Cpu => "ps.PERCENT_CPU",
+            ProcessSort::Memory => "ps.RESIDENT_MEMORY",
+        }
+    }
+}
+
+/// Direction that the list of processes is sorted in
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Smallest first
+    #[default]
+    Asc,
+    /// Largest first
+    Desc,
+}
+
+impl SortOrder {
+    /// SQL keyword for the direction
+    fn sql_keyword(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Query parameters for filtering, sorting and paging the list of processes
+#[derive(Debug, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProcessFilter {
+    /// Only include the processes that ran during this boot
+    pub boot_id: Option<String>,
+    /// Only include the processes that are alive, or only the dead ones
+    pub alive: Option<bool>,
+    /// Only include the processes whose executable contains this text
+    pub exec: Option<String>,
+    /// Only include the processes whose executable matches this regular
+    /// expression
+    pub exec_regex: Option<String>,
+    /// Only include the processes owned by this user, given by name or UID
+    pub user: Option<String>,
+    /// Only include the processes that used at least this fraction of a core
+    /// in their latest entry, where 0.5 is half of a core and 2 is two cores
+    pub min_cpu: Option<f32>,
+    /// Only include the processes that had at least this many bytes of
+    /// resident memory in their latest entry
+    pub min_memory: Option<i64>,
+    /// What the processes are sorted by
+    #[serde(default)]
+    pub sort: ProcessSort,
+    /// Direction that the processes are sorted in
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Number of matching processes to skip
+    #[serde(default)]
+    pub offset: usize,
+    /// Most number of processes to return
+    pub limit: Option<usize>,
+}
+
+impl ProcessFilter {
+    /// Makes sure the parameters can be used
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if matches!(self.limit, Some(0)) {
+            return Err(ApiError::invalid_parameter(
+                "limit",
+                "limit must be a positive number of processes",
+            ));
+        }
+        // SQLite would only fail the whole query on an invalid expression
+        match &self.exec_regex {
+            Some(pattern) => Regex::new(pattern).map(|_| ()).map_err(|err| {
+                ApiError::invalid_parameter(
+                    "exec_regex",
+                    format!("exec_regex is not a valid regular expression: {}", err),
+                )
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the conditions for every filter to a query over PROCESS `p` joined
+    /// to the latest PROCSTAT `ps`
+    ///
+    /// `exec_regex` needs a connection with the REGEXP function registered.
+    pub fn push_conditions<'a>(&'a self, query: &mut QueryBuilder<'a, Sqlite>) {
+        query.push(" WHERE TRUE");
+        if let Some(boot_id) = &self.boot_id {
+            query.push(" AND p.BOOT_ID = ").push_bind(boot_id);
+        }
+        if let Some(alive) = self.alive {
+            query.push(" AND p.IS_ALIVE = ").push_bind(alive);
+        }
+        if let Some(exec) = &self.exec {
+            // INSTR is used over LIKE so that % and _ are matched literally
+            query
+                .push(" AND INSTR(p.EXEC, ")
+                .push_bind(exec)
+                .push(") > 0");
+        }
+        if let Some(exec_regex) = &self.exec_regex {
+            query.push(" AND p.EXEC REGEXP ").push_bind(exec_regex);
+        }
+        if let Some(user) = &self.user {
+            query
+                .push(" AND (p.USER_NAME = ")
+                .push_bind(user)
+                .push(" OR CAST(p.UID AS TEXT) = ")
+                .push_bind(user)
+                .push(")");
+        }
+        if let Some(min_cpu) = self.min_cpu {
+            query.push(" AND ps.PERCENT_CPU >= ").push_bind(min_cpu);
+        }
+        if let Some(min_memory) = self.min_memory {
+            query
+                .push(" AND ps.RESIDENT_MEMORY >= ")
+                .push_bind(min_memory);
+        }
+    }
+
+    /// Adds the ORDER BY clause, where processes without a value for the sort
+    /// key always come last and ties are broken by the process' identity
+    pub fn push_order_by(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+        let column: &str = self.sort.column();
+        let direction: &str = self.order.sql_keyword();
+        query.push(format!(
+            " ORDER BY {} IS NULL, {} {}, p.PID {}, p.START_TIME {}",
+            column, column, direction, direction, direction
+        ));
+    }
+
+    /// Adds the LIMIT and OFFSET clauses for the page of processes
+    pub fn push_limit(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+        // SQLite only takes an offset after a limit, where -1 means no limit
+        let limit: i64 = self
+            .limit
+            .map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX));
+        let offset: i64 = i64::try_from(self.offset).unwrap_or(i64::MAX);
+        query
+            .push(" LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+    }
+}
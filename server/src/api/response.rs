@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
+
+use crate::auth::Role;
 
 /// Struct For Process Info Response
-#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
 #[sqlx(rename_all = "UPPERCASE")]
 pub struct ProcessInfo {
     /// The PID of the process
@@ -13,24 +16,38 @@ pub struct ProcessInfo {
     pub timestamp: i64,
     /// Total CPU time for the process in seconds
     pub total_cpu: f32,
-    /// Percent of CPU time since the last metric check
+    /// Fraction of a core used since the last metric check, where 1 is a
+    /// whole core
     pub percent_cpu: Option<f32>,
     /// CPU core the process is running on
     pub cpu_core: Option<u32>,
     /// Amount of virtual memory for the process in KB
-    pub virtual_memory: u32,
+    pub virtual_memory: i64,
     /// Amount of space the process actively has in memory in KB
-    pub resident_memory: u32,
+    pub resident_memory: i64,
     /// Amount of memory the process is sharing with other processes in KB
-    pub shared_memory: u32,
-    /// Elapsed time since start in seconds
+    pub shared_memory: i64,
+    /// Amount of virtual memory for the process in bytes
+    pub virtual_memory_bytes: i64,
+    /// Amount of space the process actively has in memory in bytes
+    pub resident_memory_bytes: i64,
+    /// Amount of memory the process is sharing with other processes in bytes
+    pub shared_memory_bytes: i64,
+    /// Process start time as a Unix epoch timestamp, which identifies the
+    /// process along with its PID
     pub start_time: i64,
     /// Whether or not the process is alive
     pub is_alive: bool,
+    /// Boot the process was running in, if it is known
+    pub boot_id: Option<String>,
+    /// UID of the user that owns the process, if it is known
+    pub uid: Option<u32>,
+    /// Name of the user that owns the process, if it is known
+    pub user_name: Option<String>,
 }
 
 /// Struct For disk Info Response
-#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
 #[sqlx(rename_all = "UPPERCASE")]
 pub struct DiskInfo {
     /// Name of the device
@@ -42,13 +59,150 @@ pub struct DiskInfo {
     /// Unix epoch timestamp at which the entry was recorded
     pub timestamp: i64,
     /// Amount of disk space used in MB
-    pub used: u32,
+    pub used: i64,
     /// Amount of disk space available in MB
-    pub available: u32,
+    pub available: i64,
+    /// Amount of disk space used in bytes
+    pub used_bytes: i64,
+    /// Amount of disk space available in bytes
+    pub available_bytes: i64,
+    /// Comma separated options the device is mounted with
+    pub mount_options: String,
+    /// Whether or not the device is mounted read-only
+    pub is_read_only: bool,
+    /// Total number of inodes, if it was recorded
+    pub inodes_total: Option<i64>,
+    /// Number of inodes in use, if it was recorded
+    pub inodes_used: Option<i64>,
+    /// Number of free inodes, if it was recorded
+    pub inodes_free: Option<i64>,
+}
+
+/// Struct For Disk Event Response
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct DiskEventInfo {
+    /// Name of the device
+    pub device_name: String,
+    /// Folder the device is mounted to
+    pub mount: String,
+    /// Unix epoch timestamp at which the event was noticed
+    pub timestamp: i64,
+    /// What happened to the disk (ex. remounted_read_only)
+    pub event: String,
+    /// Comma separated options the device was mounted with afterwards
+    pub mount_options: String,
+}
+
+/// Struct For Network Info Response
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct NetworkInfo {
+    /// Logical name of the interface
+    pub name: String,
+    /// Primary IP address of the interface, if it has one
+    pub ip_addr: Option<String>,
+    /// Whether or not the interface still exists
+    pub is_present: bool,
+    /// Hardware address of the interface, if it has one
+    pub mac_address: Option<String>,
+    /// Maximum transmission unit in bytes
+    pub mtu: Option<i64>,
+    /// Operational state of the interface
+    pub operstate: Option<String>,
+    /// Link speed in Mbps, if the link reports one
+    pub speed: Option<i64>,
+    /// Unix epoch timestamp at which the entry was recorded
+    pub timestamp: i64,
+    /// Bytes received since the previous entry
+    pub bytes_recv: i64,
+    /// Bytes sent since the previous entry
+    pub bytes_sent: i64,
+    /// Packets received since the previous entry
+    pub packets_recv: i64,
+    /// Packets sent since the previous entry
+    pub packets_sent: i64,
+    /// Receive errors since the previous entry
+    pub err_recv: i64,
+    /// Transmit errors since the previous entry
+    pub err_sent: i64,
+    /// Received packets that were dropped since the previous entry
+    pub drop_recv: i64,
+    /// Packets dropped before being sent since the previous entry
+    pub drop_sent: i64,
+    /// Receive FIFO buffer overruns since the previous entry
+    pub fifo_recv: i64,
+    /// Transmit FIFO buffer overruns since the previous entry
+    pub fifo_sent: i64,
+    /// Received packets with framing errors since the previous entry
+    pub frame_recv: i64,
+    /// Compressed packets received since the previous entry
+    pub compressed_recv: i64,
+    /// Compressed packets sent since the previous entry
+    pub compressed_sent: i64,
+    /// Multicast packets received since the previous entry
+    pub multicast_recv: i64,
+    /// Collisions while sending since the previous entry
+    pub collisions: i64,
+}
+
+/// Struct For Protocol Stat Response, where every rate is over the time since
+/// the previous entry
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct ProtocolStatInfo {
+    /// Unix epoch timestamp at which the entry was recorded
+    pub timestamp: i64,
+    /// Number of seconds since the previous entry
+    pub elapsed: i64,
+    /// TCP segments retransmitted per second
+    pub tcp_retrans_segs_per_sec: f64,
+    /// TCP segments sent with the RST flag per second
+    pub tcp_out_rsts_per_sec: f64,
+    /// Established TCP connections reset per second
+    pub tcp_estab_resets_per_sec: f64,
+    /// TCP connections opened by the host per second
+    pub tcp_active_opens_per_sec: f64,
+    /// TCP connections opened by a peer per second
+    pub tcp_passive_opens_per_sec: f64,
+    /// Times a TCP listen queue was full per second
+    pub tcp_listen_overflows_per_sec: f64,
+    /// TCP connection requests dropped while listening per second
+    pub tcp_listen_drops_per_sec: f64,
+    /// UDP datagrams dropped because the receive buffer was full per second
+    pub udp_rcvbuf_errors_per_sec: f64,
+    /// UDP datagrams dropped because the send buffer was full per second
+    pub udp_sndbuf_errors_per_sec: f64,
+    /// IP packets that could not be reassembled per second
+    pub ip_reasm_fails_per_sec: f64,
+}
+
+/// Struct For Memory Info Response
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct MemoryInfo {
+    /// Unix epoch timestamp at which the entry was recorded
+    pub timestamp: i64,
+    /// Total amount of memory in KB
+    pub total: i64,
+    /// Amount of memory that is free in KB
+    pub free: i64,
+    /// Total amount of swap space in KB
+    pub swap_total: i64,
+    /// Amount of swap space that is free in KB
+    pub swap_free: i64,
+    /// Total amount of memory in bytes
+    pub total_bytes: i64,
+    /// Amount of memory that is free in bytes
+    pub free_bytes: i64,
+    /// Total amount of swap space in bytes
+    pub swap_total_bytes: i64,
+    /// Amount of swap space that is free in bytes
+    pub swap_free_bytes: i64,
 }
 
 /// Struct For disk Info Response
-#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
 #[sqlx(rename_all = "UPPERCASE")]
 pub struct CpuInfo {
     /// The core number for the CPU
@@ -62,3 +216,117 @@ pub struct CpuInfo {
     /// Percentage of time the CPU was in-use
     pub usage: f32,
 }
+
+/// Struct For Collector Health Response
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct CollectorHealthInfo {
+    /// Name of the collector
+    pub name: String,
+    /// Number of seconds between runs of the collector when it is healthy
+    pub interval: u32,
+    /// Unix epoch timestamp of the last successful run
+    pub last_success: Option<i64>,
+    /// Unix epoch timestamp of the last failed run
+    pub last_failure: Option<i64>,
+    /// Error message from the last failed run
+    pub last_error: Option<String>,
+    /// Number of failed runs since the last successful run
+    pub consecutive_failures: u32,
+    /// Whether or not the collector has missed at least 2 of its intervals
+    pub is_stale: bool,
+}
+
+/// Struct For Boot Info Response
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct BootInfo {
+    /// Random ID the kernel generated for the boot
+    pub boot_id: String,
+    /// Unix epoch timestamp that the system booted at
+    pub boot_time: i64,
+    /// Unix epoch timestamp of the monitor's first sample during the boot
+    pub first_sample: Option<i64>,
+    /// Unix epoch timestamp of the monitor's last sample during the boot
+    pub last_sample: Option<i64>,
+    /// Number of seconds between the last sample of the previous boot and
+    /// this boot, if there was a previous boot
+    pub downtime: Option<i64>,
+}
+
+/// Struct For Host Response
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, ToSchema)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct HostInfo {
+    /// Name of the host
+    pub hostname: String,
+    /// Release of the running kernel
+    pub kernel_release: String,
+    /// ID of the distribution from os-release
+    pub os_id: Option<String>,
+    /// Name of the distribution from os-release
+    pub os_name: Option<String>,
+    /// Version of the distribution from os-release
+    pub os_version: Option<String>,
+    /// Human readable name and version of the distribution
+    pub os_pretty_name: Option<String>,
+    /// Model name of the CPU
+    pub cpu_model: Option<String>,
+    /// Space separated feature flags of the CPU
+    pub cpu_flags: String,
+    /// Number of physical CPU packages
+    pub sockets: i64,
+    /// Number of physical cores across all sockets
+    pub cores: i64,
+    /// Number of hardware threads (logical CPUs)
+    pub threads: i64,
+    /// Total amount of memory in bytes
+    pub total_memory: i64,
+    /// Hypervisor the host is running on, if it is a virtual machine
+    pub virtualization: Option<String>,
+    /// Container runtime the host is running in, if any
+    pub container: Option<String>,
+    /// Unix epoch timestamp of the last time any of the host details changed
+    pub updated_at: i64,
+}
+
+/// Struct For Top Process Response
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TopProcessInfo {
+    /// The PID of the process
+    pub pid: u32,
+    /// Process start time as a Unix epoch timestamp, which identifies the
+    /// process along with its PID
+    pub start_time: i64,
+    /// The name of the executable
+    pub exec: String,
+    /// Name of the user that owns the process, if it is known
+    pub user_name: Option<String>,
+    /// Average or peak of the metric over the window, which the processes
+    /// are ranked by
+    pub score: f64,
+    /// Value of the metric for each entry of the process in the window, oldest
+    /// first
+    pub series: Vec<TopSample>,
+}
+
+/// Struct For a single entry of a Top Process Response
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TopSample {
+    /// Unix epoch timestamp at which the entry was recorded
+    pub timestamp: i64,
+    /// Value of the metric, if it is known for the entry
+    pub value: Option<f64>,
+}
+
+/// Struct For Session Response
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SessionInfo {
+    /// Whether requests have to be authenticated
+    pub auth_enabled: bool,
+    /// Name of the token or user that made the request, if authentication is
+    /// enabled
+    pub name: Option<String>,
+    /// What the token or user is allowed to do, if authentication is enabled
+    pub role: Option<Role>,
+}
@@ -0,0 +1,351 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::response::sse::Event;
+use serde::Deserialize;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use tokio::sync::{mpsc, watch};
+use tracing::{event, instrument, Level};
+use utoipa::IntoParams;
+
+use super::error::ApiError;
+use super::response::{CpuInfo, MemoryInfo, ProcessInfo};
+use super::{MEMORY_INFO_COLUMNS, PROCESS_INFO_COLUMNS};
+
+/// How often the database is checked for a new tick from the monitor
+pub const TICK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often a comment is sent to keep idle streams open
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// How long clients should wait before reconnecting to a dropped stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+/// Most number of ticks that are replayed to a client that reconnects
+const MAX_REPLAYED_TICKS: i64 = 300;
+
+/// Kind of sample that can be streamed, which is also the name of its events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamMetric {
+    /// Usage of each CPU core, sent as an array of CPU info
+    Cpu,
+    /// Memory usage, sent as an array of memory info
+    Memory,
+    /// Stats of the subscribed PIDs, sent as an array of process info
+    Process,
+}
+
+impl StreamMetric {
+    /// Name of the metric in requests and of its events
+    fn name(&self) -> &'static str {
+        match self {
+            StreamMetric::Cpu => "cpu",
+            StreamMetric::Memory => "memory",
+            StreamMetric::Process => "process",
+        }
+    }
+
+    /// Table that the samples of the metric are stored in
+    fn table(&self) -> &'static str {
+        match self {
+            StreamMetric::Cpu => "CPUSTAT",
+            StreamMetric::Memory => "MEMORY",
+            StreamMetric::Process => "PROCSTAT",
+        }
+    }
+}
+
+/// Query parameters for subscribing to the stream of samples
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamParams {
+    /// Comma separated metrics to send, which defaults to the process metric if
+    /// PIDs are given and to the CPU and memory metrics otherwise
+    pub metrics: Option<String>,
+    /// Comma separated PIDs to send the process metric for
+    pub pids: Option<String>,
+}
+
+/// What a client of the stream is subscribed to
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    /// Metrics to send for each tick
+    metrics: Vec<StreamMetric>,
+    /// PIDs to send the process metric for
+    pids: Vec<u32>,
+}
+
+impl StreamParams {
+    /// Turns the parameters into a subscription, making sure every metric and
+    /// PID is valid
+    pub fn subscription(&self) -> Result<Subscription, ApiError> {
+        let pids: Vec<u32> = match &self.pids {
+            Some(pids) => pids
+                .split(',')
+                .map(|pid| {
+                    pid.trim().parse::<u32>().map_err(|_| {
+                        ApiError::invalid_parameter(
+                            "pids",
+                            format!("pids must be comma separated PIDs, but got {}", pid),
+                        )
+                    })
+                })
+                .collect::<Result<Vec<u32>, ApiError>>()?,
+            None => Vec::new(),
+        };
+        let metrics: Vec<StreamMetric> = match &self.metrics {
+            Some(metrics) => metrics
+                .split(',')
+                .map(|metric| match metric.trim() {
+                    "cpu" => Ok(StreamMetric::Cpu),
+                    "memory" => Ok(StreamMetric::Memory),
+                    "process" => Ok(StreamMetric::Process),
+                    other => Err(ApiError::invalid_parameter(
+                        "metrics",
+                        format!("{} is not a metric that can be streamed", other),
+                    )),
+                })
+                .collect::<Result<Vec<StreamMetric>, ApiError>>()?,
+            None if !pids.is_empty() => vec![StreamMetric::Process],
+            None => vec![StreamMetric::Cpu, StreamMetric::Memory],
+        };
+        if metrics.contains(&StreamMetric::Process) && pids.is_empty() {
+            return Err(ApiError::invalid_parameter(
+                "pids",
+                "pids are required to stream the process metric",
+            ));
+        }
+        Ok(Subscription { metrics, pids })
+    }
+}
+
+/// Watches the database for new ticks from the monitor, publishing the
+/// timestamp of the latest one until every receiver is gone
+///
+/// The server only has read access to the database, so it polls for the
+/// latest sample rather than being told about commits.
+#[instrument(skip(conn, latest_tick))]
+pub async fn watch_ticks(conn: SqlitePool, latest_tick: watch::Sender<Option<i64>>) {
+    let mut interval: tokio::time::Interval = tokio::time::interval(TICK_POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = latest_tick.closed() => break,
+        }
+        let res: Result<Option<i64>, sqlx::Error> = sqlx::query_scalar(
+            "SELECT MAX(TIMESTAMP) FROM (
+                SELECT MAX(TIMESTAMP) AS TIMESTAMP FROM MEMORY
+                UNION ALL SELECT MAX(TIMESTAMP) FROM CPUSTAT
+                UNION ALL SELECT MAX(TIMESTAMP) FROM PROCSTAT
+            );",
+        )
+        .fetch_one(&conn)
+        .await;
+        match res {
+            Ok(tick) => {
+                latest_tick.send_if_modified(|latest: &mut Option<i64>| {
+                    let is_new: bool = tick > *latest;
+                    if is_new {
+                        *latest = tick;
+                    }
+                    is_new
+                });
+            }
+            Err(err) => event!(Level::WARN, "Unable to check for a new tick: {}", err),
+        }
+    }
+}
+
+/// Sends the samples of every tick that the client is subscribed to, until
+/// the client goes away
+///
+/// A client that reconnects with the ID of the last event it got is sent the
+/// ticks it missed, and a new client starts with the latest tick.
+#[instrument(skip(conn, latest_tick, events))]
+pub async fn send_ticks(
+    conn: SqlitePool,
+    subscription: Subscription,
+    last_event_id: Option<i64>,
+    mut latest_tick: watch::Receiver<Option<i64>>,
+    events: mpsc::Sender<Result<Event, Infallible>>,
+) {
+    if events
+        .send(Ok(Event::default().retry(RECONNECT_DELAY)))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let mut last_sent: i64 = last_event_id.unwrap_or(-1);
+    let mut max_ticks: i64 = match last_event_id {
+        Some(_) => MAX_REPLAYED_TICKS,
+        None => 1,
+    };
+    loop {
+        // Marks the latest tick as seen before looking for new ticks, so that a
+        // tick committed in the meantime wakes this loop up again
+        latest_tick.borrow_and_update();
+        let ticks: Vec<i64> =
+            match get_ticks_after(&conn, &subscription, last_sent, max_ticks).await {
+                Ok(ticks) => ticks,
+                Err(err) => {
+                    // Ending the stream makes the client reconnect where it left off
+                    event!(Level::ERROR, "Unable to get the new ticks: {}", err);
+                    return;
+                }
+            };
+        for tick in ticks {
+            for metric in subscription.metrics.iter() {
+                match get_tick_event(&conn, &subscription, *metric, tick).await {
+                    Ok(Some(tick_event)) => {
+                        if events.send(Ok(tick_event)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        event!(
+                            Level::ERROR,
+                            "Unable to get the {} samples of tick {}: {}",
+                            metric.name(),
+                            tick,
+                            err
+                        );
+                        return;
+                    }
+                }
+            }
+            last_sent = tick;
+        }
+        max_ticks = MAX_REPLAYED_TICKS;
+
+        tokio::select! {
+            res = latest_tick.changed() => {
+                if res.is_err() {
+                    return;
+                }
+            }
+            _ = events.closed() => return,
+        }
+    }
+}
+
+/// Gets the timestamps of the most recent ticks after the given one that have
+/// samples for the subscription, oldest first
+async fn get_ticks_after(
+    conn: &SqlitePool,
+    subscription: &Subscription,
+    after: i64,
+    max_ticks: i64,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let mut query: QueryBuilder<Sqlite> =
+        QueryBuilder::new("SELECT TIMESTAMP FROM (SELECT TIMESTAMP FROM ");
+    for (i, metric) in subscription.metrics.iter().enumerate() {
+        if i > 0 {
+            query.push(" UNION SELECT TIMESTAMP FROM ");
+        }
+        query.push(metric.table());
+        if *metric == StreamMetric::Process {
+            query.push(" WHERE ");
+            push_pid_filter(&mut query, "PID", &subscription.pids);
+        }
+    }
+    query
+        .push(") WHERE TIMESTAMP > ")
+        .push_bind(after)
+        .push(" ORDER BY TIMESTAMP DESC LIMIT ")
+        .push_bind(max_ticks)
+        .push(";");
+    let mut ticks: Vec<i64> = query.build_query_scalar::<i64>().fetch_all(conn).await?;
+    ticks.reverse();
+    Ok(ticks)
+}
+
+/// Gets the event with the samples of a metric at a tick, which is None if the
+/// metric was not collected during the tick
+async fn get_tick_event(
+    conn: &SqlitePool,
+    subscription: &Subscription,
+    metric: StreamMetric,
+    tick: i64,
+) -> Result<Option<Event>, sqlx::Error> {
+    let data: serde_json::Value = match metric {
+        StreamMetric::Cpu => {
+            let cpu_infos: Vec<CpuInfo> = sqlx::query_as::<_, CpuInfo>(
+                r#"
+                SELECT
+                    c.cpu_core,
+                    c.mhz,
+                    c.total_cache,
+                    cs.timestamp,
+                    cs.usage
+                FROM
+                    Cpu c
+                INNER JOIN
+                    CpuStat cs
+                ON
+                    c.cpu_core = cs.cpu_core
+                WHERE
+                    cs.timestamp = ?
+                ORDER BY
+                    c.cpu_core
+                "#,
+            )
+            .bind(tick)
+            .fetch_all(conn)
+            .await?;
+            serde_json::json!(cpu_infos)
+        }
+        StreamMetric::Memory => {
+            let memory_infos: Vec<MemoryInfo> = sqlx::query_as::<_, MemoryInfo>(&format!(
+                "SELECT {} FROM Memory m WHERE m.timestamp = ?",
+                MEMORY_INFO_COLUMNS
+            ))
+            .bind(tick)
+            .fetch_all(conn)
+            .await?;
+            serde_json::json!(memory_infos)
+        }
+        StreamMetric::Process => {
+            let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(format!(
+                r#"
+                SELECT
+                    {}
+                FROM
+                    PROCESS p
+                JOIN
+                    PROCSTAT ps ON p.PID = ps.PID AND p.START_TIME = ps.START_TIME
+                WHERE
+                    ps.TIMESTAMP =
+                "#,
+                PROCESS_INFO_COLUMNS
+            ));
+            query.push_bind(tick).push(" AND ");
+            push_pid_filter(&mut query, "ps.PID", &subscription.pids);
+            query.push(" ORDER BY p.PID, p.START_TIME;");
+            let process_infos: Vec<ProcessInfo> = query
+                .build_query_as::<ProcessInfo>()
+                .fetch_all(conn)
+                .await?;
+            serde_json::json!(process_infos)
+        }
+    };
+    if data.as_array().is_some_and(|samples| samples.is_empty()) {
+        return Ok(None);
+    }
+    Ok(Some(
+        Event::default()
+            .event(metric.name())
+            .id(tick.to_string())
+            .data(data.to_string()),
+    ))
+}
+
+/// Adds the condition for the PID column being one of the given PIDs
+fn push_pid_filter(query: &mut QueryBuilder<'_, Sqlite>, column: &str, pids: &[u32]) {
+    query.push(format!("{} IN (", column));
+    let mut separated = query.separated(", ");
+    for pid in pids {
+        separated.push_bind(*pid);
+    }
+    query.push(")");
+}
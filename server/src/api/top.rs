@@ -0,0 +1,237 @@
+use serde::Deserialize;
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+use utoipa::{IntoParams, ToSchema};
+
+use super::error::ApiError;
+use super::history::Aggregate;
+use super::response::{TopProcessInfo, TopSample};
+
+/// Number of processes returned when none is requested
+const DEFAULT_COUNT: u32 = 10;
+/// Most number of processes that can be requested
+const MAX_COUNT: u32 = 100;
+/// Length of the window when none is requested
+const DEFAULT_WINDOW: &str = "30m";
+
+/// Measurement that processes are ranked by
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TopMetric {
+    /// Fraction of a core used
+    Cpu,
+    /// Resident memory in bytes
+    Rss,
+    /// Bytes per second read from and written to storage
+    Io,
+}
+
+impl TopMetric {
+    /// Expression for the metric over the stats in the window, which are
+    /// aliased `ps` and have the running I/O total of the previous stat of the
+    /// same process
+    fn sql_expression(&self) -> &'static str {
+        match self {
+            TopMetric::Cpu => "ps.PERCENT_CPU",
+            TopMetric::Rss => "CAST(ps.RESIDENT_MEMORY AS REAL)",
+            TopMetric::Io => {
+                "CAST(ps.IO_READ_BYTES + ps.IO_WRITE_BYTES - ps.PREV_IO_BYTES AS REAL)
+                    / (ps.TIMESTAMP - ps.PREV_TIMESTAMP)"
+            }
+        }
+    }
+}
+
+/// Query parameters for ranking the processes that used the most of a resource
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TopParams {
+    /// Measurement to rank the processes by
+    pub metric: TopMetric,
+    /// How far back from the latest stat to look, as a number of seconds that
+    /// can end in `s`, `m`, `h`, or `d`
+    pub window: Option<String>,
+    /// Number of processes to return
+    pub n: Option<u32>,
+    /// Whether processes are ranked by their average or their peak
+    #[serde(default)]
+    pub agg: Aggregate,
+}
+
+impl TopParams {
+    /// Makes sure the parameters can be used, returning the length of the
+    /// window in seconds
+    pub fn validate(&self) -> Result<i64, ApiError> {
+        if self.agg == Aggregate::Min {
+            return Err(ApiError::invalid_parameter(
+                "agg",
+                "agg must be avg or max to rank processes",
+            ));
+        }
+        if matches!(self.n, Some(n) if n == 0 || n > MAX_COUNT) {
+            return Err(ApiError::invalid_parameter(
+                "n",
+                format!("n must be between 1 and {}", MAX_COUNT),
+            ));
+        }
+        let window: &str = self.window.as_deref().unwrap_or(DEFAULT_WINDOW);
+        parse_window(window).ok_or_else(|| {
+            ApiError::invalid_parameter(
+                "window",
+                format!(
+                    "window ({}) must be a positive duration like 90s or 30m",
+                    window
+                ),
+            )
+        })
+    }
+
+    /// Number of processes to return
+    pub fn count(&self) -> u32 {
+        self.n.unwrap_or(DEFAULT_COUNT)
+    }
+}
+
+/// Row of the top processes query, which is a single entry of a process
+#[derive(Debug, FromRow)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub struct TopRow {
+    /// The PID of the process
+    pid: u32,
+    /// Process start time as a Unix epoch timestamp
+    start_time: i64,
+    /// The name of the executable
+    exec: String,
+    /// Name of the user that owns the process
+    user_name: Option<String>,
+    /// Score the process is ranked by
+    score: f64,
+    /// Unix epoch timestamp at which the entry was recorded
+    timestamp: i64,
+    /// Value of the metric for the entry
+    value: Option<f64>,
+}
+
+/// Groups the rows of the top processes query by process, keeping the order
+/// of the rows
+pub fn group_top_rows(rows: Vec<TopRow>) -> Vec<TopProcessInfo> {
+    let mut top_processes: Vec<TopProcessInfo> = Vec::new();
+    for row in rows {
+        let sample: TopSample = TopSample {
+            timestamp: row.timestamp,
+            value: row.value,
+        };
+        match top_processes.last_mut() {
+            Some(last) if last.pid == row.pid && last.start_time == row.start_time => {
+                last.series.push(sample);
+            }
+            _ => top_processes.push(TopProcessInfo {
+                pid: row.pid,
+                start_time: row.start_time,
+                exec: row.exec,
+                user_name: row.user_name,
+                score: row.score,
+                series: vec![sample],
+            }),
+        }
+    }
+    top_processes
+}
+
+/// Parses a duration like `90`, `90s`, `30m`, `2h`, or `1d` into seconds
+fn parse_window(window: &str) -> Option<i64> {
+    let (number, unit): (&str, i64) = match window.chars().last()? {
+        's' => (&window[..window.len() - 1], 1),
+        'm' => (&window[..window.len() - 1], 60),
+        'h' => (&window[..window.len() - 1], 60 * 60),
+        'd' => (&window[..window.len() - 1], 24 * 60 * 60),
+        _ => (window, 1),
+    };
+    number
+        .parse::<i64>()
+        .ok()
+        .filter(|number| *number > 0)
+        .and_then(|number| number.checked_mul(unit))
+}
+
+/// Builds the query for the stats of the top processes within the window that
+/// ends at the latest stat, with one row per stat of each process
+///
+/// Rows are ordered from the highest ranked process down, and then by
+/// timestamp. Processes without any value for the metric in the window are
+/// left out, like processes whose I/O cannot be inspected. The I/O rate of the
+/// first stat of a process in the window is unknown, since the stat it would
+/// be compared to is outside of the window.
+pub fn top_query<'a>(params: &TopParams, window: i64) -> QueryBuilder<'a, Sqlite> {
+    let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+        r#"
+        WITH window_stats AS (
+            SELECT
+                *,
+                LAG(IO_READ_BYTES + IO_WRITE_BYTES) OVER previous AS PREV_IO_BYTES,
+                LAG(TIMESTAMP) OVER previous AS PREV_TIMESTAMP
+            FROM
+                PROCSTAT
+            WHERE
+                TIMESTAMP >= (SELECT MAX(TIMESTAMP) FROM PROCSTAT) - "#,
+    );
+    query.push_bind(window);
+    query.push(format!(
+        r#"
+            WINDOW previous AS (PARTITION BY PID, START_TIME ORDER BY TIMESTAMP)
+        ),
+        samples AS (
+            SELECT
+                ps.PID,
+                ps.START_TIME,
+                ps.TIMESTAMP,
+                {} AS VALUE
+            FROM
+                window_stats ps
+        ),
+        ranked AS (
+            SELECT
+                PID,
+                START_TIME,
+                {}(VALUE) AS SCORE
+            FROM
+                samples
+            GROUP BY
+                PID,
+                START_TIME
+            HAVING
+                SCORE IS NOT NULL
+            ORDER BY
+                SCORE DESC,
+                PID,
+                START_TIME
+            LIMIT "#,
+        params.metric.sql_expression(),
+        params.agg.sql_function()
+    ));
+    query.push_bind(params.count());
+    query.push(
+        r#"
+        )
+        SELECT
+            r.PID,
+            r.START_TIME,
+            p.EXEC,
+            p.USER_NAME,
+            r.SCORE,
+            s.TIMESTAMP,
+            s.VALUE
+        FROM
+            ranked r
+        JOIN
+            PROCESS p ON p.PID = r.PID AND p.START_TIME = r.START_TIME
+        JOIN
+            samples s ON s.PID = r.PID AND s.START_TIME = r.START_TIME
+        ORDER BY
+            r.SCORE DESC,
+            r.PID,
+            r.START_TIME,
+            s.TIMESTAMP;
+        "#,
+    );
+    query
+}
@@ -0,0 +1,154 @@
+use std::env;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Environment variable for the configuration file
+const CONFIG_VAR: &str = "NEBULA_CONFIG";
+/// Environment variable for the addresses to listen on, separated by commas
+const LISTEN_VAR: &str = "NEBULA_LISTEN";
+/// Environment variable for the Unix socket to listen on
+const UNIX_SOCKET_VAR: &str = "NEBULA_UNIX_SOCKET";
+/// Environment variable for the database that the monitor writes to
+const DB_FILE_VAR: &str = "NEBULA_DB_FILE";
+/// Environment variable for the directory with the dashboard's files
+const WEB_DIR_VAR: &str = "NEBULA_WEB_DIR";
+
+/// How to run the server, which is printed by `--help`
+pub const USAGE: &str = "\
+Usage: nebula-server [OPTIONS]
+       nebula-server hash-password
+
+Serves the API and the dashboard for the data collected by nebula-monitor.
+
+Options:
+  -c, --config <FILE>       Configuration file [env: NEBULA_CONFIG]
+                            [default: /etc/nebula/server.toml]
+  -l, --listen <ADDRESS>    Address to listen on, like 0.0.0.0:4242 or [::]:4242,
+                            which can be repeated or separated by commas
+                            [env: NEBULA_LISTEN]
+      --unix-socket <PATH>  Unix socket to listen on [env: NEBULA_UNIX_SOCKET]
+      --db-file <FILE>      Database written by nebula-monitor [env: NEBULA_DB_FILE]
+      --web-dir <DIR>       Directory with the dashboard's files [env: NEBULA_WEB_DIR]
+  -h, --help                Print this message
+
+Commands:
+  hash-password             Read a password from stdin and print its hash for a
+                            user in the configuration file
+";
+
+/// What the server was asked to do
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Serves the API and the dashboard
+    Serve(Overrides),
+    /// Prints the hash of a password read from stdin
+    HashPassword,
+    /// Prints how to run the server
+    Help,
+}
+
+/// Settings from the command line and the environment, which take precedence
+/// over the configuration file
+#[derive(Debug, Default, PartialEq)]
+pub struct Overrides {
+    /// Configuration file to read instead of the usual one
+    pub config_file: Option<PathBuf>,
+    /// TCP addresses to listen on
+    pub listen: Option<Vec<SocketAddr>>,
+    /// Unix socket to listen on
+    pub unix_socket: Option<PathBuf>,
+    /// Database that the monitor writes to
+    pub db_file: Option<PathBuf>,
+    /// Directory with the dashboard's files
+    pub web_dir: Option<PathBuf>,
+}
+
+/// Error from an argument or environment variable that cannot be used
+#[derive(Debug, PartialEq)]
+pub struct ArgsError(String);
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\nRun nebula-server --help to see the options", self.0)
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+/// Parses a list of addresses separated by commas, where an empty list means
+/// that no TCP address is listened on
+fn parse_addresses(source: &str, value: &str) -> Result<Vec<SocketAddr>, ArgsError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            address.parse().map_err(|_| {
+                ArgsError(format!(
+                    "{} for {} is not an IP address and port, like 0.0.0.0:4242 or [::]:4242",
+                    address, source
+                ))
+            })
+        })
+        .collect()
+}
+
+impl Command {
+    /// Parses the command from the process's arguments and environment
+    pub fn from_env() -> Result<Self, ArgsError> {
+        Command::parse(env::args().skip(1), |name| env::var(name).ok())
+    }
+
+    /// Parses the command from arguments (without the program name) and a
+    /// lookup for environment variables, where arguments win over variables
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ArgsError> {
+        let mut overrides: Overrides = Overrides {
+            config_file: var(CONFIG_VAR).map(PathBuf::from),
+            listen: var(LISTEN_VAR)
+                .map(|value| parse_addresses(LISTEN_VAR, &value))
+                .transpose()?,
+            unix_socket: var(UNIX_SOCKET_VAR).map(PathBuf::from),
+            db_file: var(DB_FILE_VAR).map(PathBuf::from),
+            web_dir: var(WEB_DIR_VAR).map(PathBuf::from),
+        };
+        // Repeated flags add to each other, but replace the variable
+        let mut listen_args: Option<Vec<SocketAddr>> = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value): (&str, Option<&str>) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || -> Result<String, ArgsError> {
+                match inline_value {
+                    Some(value) => Ok(value.to_string()),
+                    None => args
+                        .next()
+                        .ok_or_else(|| ArgsError(format!("{} needs a value", flag))),
+                }
+            };
+            match flag {
+                "hash-password" => return Ok(Command::HashPassword),
+                "-h" | "--help" => return Ok(Command::Help),
+                "-c" | "--config" => overrides.config_file = Some(PathBuf::from(value()?)),
+                "-l" | "--listen" => listen_args
+                    .get_or_insert_with(Vec::new)
+                    .extend(parse_addresses(flag, &value()?)?),
+                "--unix-socket" => overrides.unix_socket = Some(PathBuf::from(value()?)),
+                "--db-file" => overrides.db_file = Some(PathBuf::from(value()?)),
+                "--web-dir" => overrides.web_dir = Some(PathBuf::from(value()?)),
+                _ => return Err(ArgsError(format!("{} is not an option", arg))),
+            }
+        }
+        if listen_args.is_some() {
+            overrides.listen = listen_args;
+        }
+
+        Ok(Command::Serve(overrides))
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::{ConnectInfo, Form, OriginalUri, Query, Request, State};
+use axum::http::header::{AUTHORIZATION, COOKIE, RETRY_AFTER, SET_COOKIE};
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use tracing::{event, Level};
+use utoipa::ToSchema;
+
+/// Name of the cookie that holds the ID of a dashboard session
+const SESSION_COOKIE: &str = "nebula_session";
+/// Number of seconds a dashboard session lasts when the config does not say
+const DEFAULT_SESSION_TTL: u64 = 12 * 60 * 60;
+/// Fewest number of characters in a bearer token, so that tokens cannot be
+/// guessed
+const MIN_TOKEN_LENGTH: usize = 16;
+/// Number of characters in a session ID
+const SESSION_ID_LENGTH: usize = 32;
+/// Page for signing in to the dashboard
+const LOGIN_PAGE: &str = include_str!("login.html");
+/// Where the dashboard sends people after they sign in, when they were not
+/// on their way to another page
+const DEFAULT_LANDING_PAGE: &str = "/web";
+/// Hash that passwords for unknown users are checked against, made with the
+/// same parameters as `hash_password` so that it takes as long as a real one
+const DUMMY_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$gkDnL5LuJZ399XIStRt5OQ$RR4oveit0RT8/xTnnFIkcGrBA4/70tFcy1LsXBJJiEs";
+/// Number of failed attempts to sign in in a row before more attempts have to
+/// wait
+const FREE_LOGIN_FAILURES: u32 = 5;
+/// How long the first wait after too many failed attempts to sign in is, which
+/// doubles with every failure after it
+const LOGIN_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest wait between attempts to sign in, which is also how long failures
+/// are remembered for
+const MAX_LOGIN_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// What someone who is signed in is allowed to do
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Can read every metric, but cannot change anything
+    ReadOnly,
+    /// Can make any request
+    Admin,
+}
+
+/// Bearer token that can be sent in the `Authorization` header
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TokenConfig {
+    /// Name that the token's requests are made under, such as the name of the
+    /// scraper that uses it
+    pub name: String,
+    /// Secret value of the token
+    pub token: String,
+    /// What requests with the token are allowed to do
+    pub role: Role,
+}
+
+/// User that can sign in to the dashboard or use HTTP basic authentication
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    /// Name the user signs in with
+    pub name: String,
+    /// Argon2 hash of the user's password in the PHC string format, which is
+    /// printed by `nebula-server hash-password`
+    pub password_hash: String,
+    /// What the user is allowed to do
+    pub role: Role,
+}
+
+/// Settings for who can access the API and the dashboard, where anyone can
+/// access them if no tokens or users are configured
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Bearer tokens for machines
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    /// Users for people
+    #[serde(default)]
+    pub users: Vec<UserConfig>,
+    /// Number of seconds that a dashboard session lasts
+    pub session_ttl: Option<u64>,
+}
+
+impl AuthConfig {
+    /// Whether requests have to be authenticated
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty() || !self.users.is_empty()
+    }
+
+    /// Makes sure the tokens and users can be used
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names: HashSet<&str> = HashSet::new();
+        for token in self.tokens.iter() {
+            if !names.insert(&token.name) {
+                return Err(format!("{} is configured more than once", token.name));
+            }
+            if token.token.len() < MIN_TOKEN_LENGTH {
+                return Err(format!(
+                    "The token of {} must be at least {} characters",
+                    token.name, MIN_TOKEN_LENGTH
+                ));
+            }
+        }
+        for user in self.users.iter() {
+            if !names.insert(&user.name) {
+                return Err(format!("{} is configured more than once", user.name));
+            }
+            if PasswordHash::new(&user.password_hash).is_err() {
+                return Err(format!(
+                    "The password hash of {} is not a PHC string",
+                    user.name
+                ));
+            }
+        }
+        if self.session_ttl == Some(0) {
+            return Err("session_ttl must be a positive number of seconds".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Who made a request, which is added to the extensions of authenticated
+/// requests
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+pub struct Identity {
+    /// Name of the token or user
+    pub name: String,
+    /// What the token or user is allowed to do
+    pub role: Role,
+}
+
+impl Identity {
+    /// Whether the identity is allowed to make requests with the method, where
+    /// only admins can make requests that could change something
+    pub fn can_use(&self, method: &Method) -> bool {
+        method.is_safe() || self.role == Role::Admin
+    }
+}
+
+/// Dashboard session that was started by signing in
+struct Session {
+    /// Who signed in
+    identity: Identity,
+    /// When the session stops being accepted
+    expires_at: Instant,
+}
+
+/// Where attempts to sign in come from, which each have their own failures
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum LoginSource {
+    /// Name of the user that is signed in as, whether or not it is configured
+    User(String),
+    /// Address of the client
+    Address(IpAddr),
+}
+
+/// Failed attempts to sign in from one source
+struct LoginFailures {
+    /// Number of attempts in a row that failed
+    count: u32,
+    /// When the last attempt failed
+    last_failure: Instant,
+    /// When another attempt is allowed
+    retry_at: Instant,
+}
+
+/// Checks the credentials of requests and keeps track of dashboard sessions
+pub struct Authenticator {
+    /// Tokens and users that are accepted
+    config: AuthConfig,
+    /// Sessions that have been started, by their ID
+    sessions: Mutex<HashMap<String, Session>>,
+    /// Recent failed attempts to sign in to the dashboard, by where they came
+    /// from
+    login_failures: Mutex<HashMap<LoginSource, LoginFailures>>,
+    /// Whether session cookies are only sent over HTTPS
+    secure_cookies: bool,
+}
+
+impl Authenticator {
+    /// Creates an authenticator for the configured tokens and users, where
+    /// session cookies are marked as secure if the server uses HTTPS
+    pub fn new(config: AuthConfig, secure_cookies: bool) -> Self {
+        Authenticator {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+            login_failures: Mutex::new(HashMap::new()),
+            secure_cookies,
+        }
+    }
+
+    /// Attributes of the session cookie
+    fn cookie_attributes(&self) -> &'static str {
+        if self.secure_cookies {
+            "Path=/; HttpOnly; SameSite=Strict; Secure"
+        } else {
+            "Path=/; HttpOnly; SameSite=Strict"
+        }
+    }
+
+    /// Whether requests have to be authenticated
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    /// Finds who made a request from its `Authorization` header or from its
+    /// session cookie
+    pub async fn identify(&self, headers: &HeaderMap) -> Option<Identity> {
+        if let Some(authorization) = headers.get(AUTHORIZATION) {
+            let authorization: &str = authorization.to_str().ok()?;
+            let (scheme, credentials) = authorization.split_once(' ')?;
+            return match scheme.to_ascii_lowercase().as_str() {
+                "bearer" => self.check_token(credentials.trim()),
+                "basic" => {
+                    let decoded: Vec<u8> = BASE64.decode(credentials.trim()).ok()?;
+                    let decoded: String = String::from_utf8(decoded).ok()?;
+                    let (name, password) = decoded.split_once(':')?;
+                    self.check_password(name, password).await
+                }
+                _ => None,
+            };
+        }
+        self.check_session(&session_cookie(headers)?)
+    }
+
+    /// Finds the configured token that matches the given one
+    fn check_token(&self, token: &str) -> Option<Identity> {
+        // Every token is compared in constant time, so that the time taken
+        // does not tell how close a guess was
+        let mut found: Option<&TokenConfig> = None;
+        for config in self.config.tokens.iter() {
+            if bool::from(config.token.as_bytes().ct_eq(token.as_bytes())) {
+                found = Some(config);
+            }
+        }
+        found.map(|config| Identity {
+            name: config.name.clone(),
+            role: config.role,
+        })
+    }
+
+    /// Finds the configured user with the name and password
+    async fn check_password(&self, name: &str, password: &str) -> Option<Identity> {
+        let user: Option<&UserConfig> = self.config.users.iter().find(|user| user.name == name);
+        // Unknown users are still checked against a hash, so that how long the
+        // answer takes does not tell which users exist
+        let password_hash: String = user
+            .map_or(DUMMY_PASSWORD_HASH, |user| user.password_hash.as_str())
+            .to_string();
+        let password: String = password.to_string();
+        // Argon2 is slow on purpose, so it would hold up every other request
+        // on the runtime's thread
+        let verified: bool = tokio::task::spawn_blocking(move || {
+            PasswordHash::new(&password_hash).is_ok_and(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+        })
+        .await
+        .unwrap_or(false);
+        user.filter(|_| verified).map(|user| Identity {
+            name: user.name.clone(),
+            role: user.role,
+        })
+    }
+
+    /// How long until the sources are allowed to try signing in again, if they
+    /// have failed too many times
+    fn login_retry_after(&self, sources: &[LoginSource]) -> Option<Duration> {
+        let now: Instant = Instant::now();
+        let failures = self
+            .login_failures
+            .lock()
+            .expect("Login failures lock is not poisoned");
+        sources
+            .iter()
+            .filter_map(|source| failures.get(source))
+            .filter(|failures| failures.retry_at > now)
+            .map(|failures| failures.retry_at - now)
+            .max()
+    }
+
+    /// Records a failed attempt to sign in, making the sources wait longer
+    /// each time once they have failed too many times
+    fn record_login_failure(&self, sources: &[LoginSource]) {
+        let now: Instant = Instant::now();
+        let mut failures = self
+            .login_failures
+            .lock()
+            .expect("Login failures lock is not poisoned");
+        // Failures are only ever looked up by source, so the old ones are
+        // dropped here instead of by a task of their own
+        failures.retain(|_, failures| failures.last_failure + MAX_LOGIN_BACKOFF > now);
+        for source in sources {
+            let failures: &mut LoginFailures =
+                failures.entry(source.clone()).or_insert(LoginFailures {
+                    count: 0,
+                    last_failure: now,
+                    retry_at: now,
+                });
+            failures.count += 1;
+            failures.last_failure = now;
+            if failures.count >= FREE_LOGIN_FAILURES {
+                let doublings: u32 = (failures.count - FREE_LOGIN_FAILURES).min(16);
+                failures.retry_at = now + (LOGIN_BACKOFF * (1 << doublings)).min(MAX_LOGIN_BACKOFF);
+            }
+        }
+    }
+
+    /// Forgets the failed attempts to sign in of the sources, after one of
+    /// their attempts succeeded
+    fn clear_login_failures(&self, sources: &[LoginSource]) {
+        let mut failures = self
+            .login_failures
+            .lock()
+            .expect("Login failures lock is not poisoned");
+        for source in sources {
+            failures.remove(source);
+        }
+    }
+
+    /// Finds the session with the ID, if it has not expired
+    fn check_session(&self, session_id: &str) -> Option<Identity> {
+        let sessions = self.sessions.lock().expect("Sessions lock is not poisoned");
+        sessions
+            .get(session_id)
+            .filter(|session| session.expires_at > Instant::now())
+            .map(|session| session.identity.clone())
+    }
+
+    /// How long a dashboard session lasts
+    fn session_ttl(&self) -> Duration {
+        Duration::from_secs(self.config.session_ttl.unwrap_or(DEFAULT_SESSION_TTL))
+    }
+
+    /// Starts a dashboard session, returning its ID
+    fn start_session(&self, identity: Identity) -> String {
+        let session_id: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SESSION_ID_LENGTH)
+            .map(char::from)
+            .collect();
+        let now: Instant = Instant::now();
+        let mut sessions = self.sessions.lock().expect("Sessions lock is not poisoned");
+        // Sessions are only ever looked up by ID, so the expired ones are
+        // dropped here instead of by a task of their own
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(
+            session_id.clone(),
+            Session {
+                identity,
+                expires_at: now + self.session_ttl(),
+            },
+        );
+        session_id
+    }
+
+    /// Ends a dashboard session
+    fn end_session(&self, session_id: &str) {
+        let mut sessions = self.sessions.lock().expect("Sessions lock is not poisoned");
+        sessions.remove(session_id);
+    }
+}
+
+/// Hashes a password for the `password_hash` of a configured user
+pub fn hash_password(password: &str) -> String {
+    let salt: SaltString = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Default Argon2 parameters can hash any password")
+        .to_string()
+}
+
+/// Gets the ID of the dashboard session from the request's cookies
+fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|cookies| cookies.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Makes sure a page to go to after signing in is on this server, so that the
+/// login page cannot be used to send people to other websites
+fn landing_page(next: Option<&str>) -> &str {
+    match next {
+        Some(next) if next.starts_with('/') && !next.starts_with("//") && !next.contains('\\') => {
+            next
+        }
+        _ => DEFAULT_LANDING_PAGE,
+    }
+}
+
+/// Percent-encodes text for a query string
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Escapes text to be put inside an HTML attribute
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Sends requests to the dashboard that are not from anyone to the login page,
+/// when authentication is enabled
+pub async fn require_web_identity(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !auth.is_enabled() {
+        return next.run(request).await;
+    }
+    match auth.identify(request.headers()).await {
+        Some(identity) if identity.can_use(request.method()) => {
+            request.extensions_mut().insert(identity);
+            next.run(request).await
+        }
+        Some(_) => StatusCode::FORBIDDEN.into_response(),
+        None => {
+            // The router is nested, so its own URI is missing the prefix
+            let uri: &Uri = match request.extensions().get::<OriginalUri>() {
+                Some(OriginalUri(uri)) => uri,
+                None => request.uri(),
+            };
+            let path: &str = uri
+                .path_and_query()
+                .map_or(DEFAULT_LANDING_PAGE, |path| path.as_str());
+            Redirect::to(&format!("/login?next={}", encode_query_value(path))).into_response()
+        }
+    }
+}
+
+/// Creates the router for signing in to and out of the dashboard
+pub fn create_auth_router(auth: Arc<Authenticator>) -> Router {
+    Router::new()
+        .route("/login", get(get_login).post(post_login))
+        .route("/logout", post(post_logout))
+        .with_state(auth)
+}
+
+/// Query parameters of the login page
+#[derive(Debug, Deserialize)]
+struct LoginParams {
+    /// Page to go to after signing in
+    next: Option<String>,
+    /// Whether the previous attempt to sign in failed
+    #[serde(default)]
+    failed: bool,
+}
+
+/// Form that is submitted to sign in
+#[derive(Debug, Deserialize)]
+struct LoginForm {
+    /// Name of the user
+    username: String,
+    /// Password of the user
+    password: String,
+    /// Page to go to after signing in
+    next: Option<String>,
+}
+
+/// Fills in the login page with an error, which may be empty, and the page to
+/// go to after signing in
+fn login_page(error: &str, next: &str) -> Html<String> {
+    Html(
+        LOGIN_PAGE
+            .replace("{{error}}", error)
+            .replace("{{next}}", &escape_html(next)),
+    )
+}
+
+/// Returns the page for signing in to the dashboard
+async fn get_login(State(auth): State<Arc<Authenticator>>, params: Query<LoginParams>) -> Response {
+    let next: &str = landing_page(params.next.as_deref());
+    if !auth.is_enabled() {
+        return Redirect::to(next).into_response();
+    }
+    let error: &str = if params.failed {
+        r#"<p class="error">The username or password is incorrect.</p>"#
+    } else {
+        ""
+    };
+    login_page(error, next).into_response()
+}
+
+/// Signs in to the dashboard, setting the session cookie and going on to the
+/// requested page
+///
+/// Users and client addresses that fail too many times in a row have to wait
+/// before they can try again, which grows with every failure.
+async fn post_login(
+    State(auth): State<Arc<Authenticator>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let next: &str = landing_page(form.next.as_deref());
+    // Connections over the Unix socket have no address, so only the user
+    // counts for them
+    let mut sources: Vec<LoginSource> = vec![LoginSource::User(form.username.clone())];
+    if let Some(ConnectInfo(address)) = connect_info {
+        sources.push(LoginSource::Address(address.ip()));
+    }
+    if let Some(retry_after) = auth.login_retry_after(&sources) {
+        event!(
+            Level::WARN,
+            "Sign in for {} is waiting after too many failures",
+            form.username
+        );
+        let seconds: u64 = retry_after.as_secs_f64().ceil() as u64;
+        let error: String = format!(
+            r#"<p class="error">Too many attempts to sign in failed. Try again in {} seconds.</p>"#,
+            seconds
+        );
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, seconds.to_string())],
+            login_page(&error, next),
+        )
+            .into_response();
+    }
+    let identity: Identity = match auth.check_password(&form.username, &form.password).await {
+        Some(identity) => identity,
+        None => {
+            event!(Level::WARN, "Failed sign in for {}", form.username);
+            auth.record_login_failure(&sources);
+            return Redirect::to(&format!(
+                "/login?failed=true&next={}",
+                encode_query_value(next)
+            ))
+            .into_response();
+        }
+    };
+    event!(Level::INFO, "{} signed in", identity.name);
+    auth.clear_login_failures(&sources);
+    let session_id: String = auth.start_session(identity);
+    let cookie: String = format!(
+        "{}={}; Max-Age={}; {}",
+        SESSION_COOKIE,
+        session_id,
+        auth.session_ttl().as_secs(),
+        auth.cookie_attributes()
+    );
+    ([(SET_COOKIE, cookie)], Redirect::to(next)).into_response()
+}
+
+/// Signs out of the dashboard, ending the session and clearing its cookie
+async fn post_logout(State(auth): State<Arc<Authenticator>>, headers: HeaderMap) -> Response {
+    if let Some(session_id) = session_cookie(&headers) {
+        auth.end_session(&session_id);
+    }
+    let cookie: String = format!(
+        "{}=; Max-Age=0; {}",
+        SESSION_COOKIE,
+        auth.cookie_attributes()
+    );
+    ([(SET_COOKIE, cookie)], Redirect::to("/login")).into_response()
+}
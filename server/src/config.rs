@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use axum::http::HeaderValue;
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use crate::args::Overrides;
+use crate::auth::AuthConfig;
+use crate::tls::TlsConfig;
+
+/// Absolute path to the configuration file of the server
+pub const CONFIG_FILE: &str = "/etc/nebula/server.toml";
+/// Address that is listened on when neither addresses nor a Unix socket are set
+const DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0:4242";
+/// Absolute path to the database that the monitor writes to
+const DEFAULT_DB_FILE: &str = "/var/nebula/db/nebulaMetrics.db";
+/// Absolute path to the dashboard's files
+const DEFAULT_WEB_DIR: &str = "/var/nebula/web";
+/// Path to the dashboard's files in the repository, for tests
+const TEST_WEB_DIR: &str = "../assets/web";
+
+/// Settings of the server, where every section is optional
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Who can access the API and the dashboard
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Which other websites can call the API
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// Certificates for serving HTTPS, where plain HTTP is served if this is
+    /// not set
+    pub tls: Option<TlsConfig>,
+    /// Where the server accepts connections
+    #[serde(default)]
+    pub listen: ListenConfig,
+    /// Where the server finds its data
+    #[serde(default)]
+    pub paths: PathsConfig,
+}
+
+/// Settings for where the server accepts connections
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ListenConfig {
+    /// TCP addresses to listen on, like `0.0.0.0:4242` or `[::]:4242`
+    pub addresses: Option<Vec<SocketAddr>>,
+    /// Unix socket to listen on, for a reverse proxy on the same host
+    pub unix_socket: Option<PathBuf>,
+}
+
+impl ListenConfig {
+    /// TCP addresses to listen on, where `0.0.0.0:4242` is used if neither
+    /// addresses nor a Unix socket are set
+    pub fn addresses(&self) -> Vec<SocketAddr> {
+        match (&self.addresses, &self.unix_socket) {
+            (Some(addresses), _) => addresses.clone(),
+            (None, Some(_)) => Vec::new(),
+            (None, None) => vec![DEFAULT_LISTEN_ADDRESS
+                .parse()
+                .expect("The default address is valid")],
+        }
+    }
+}
+
+/// Settings for where the server finds its data
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PathsConfig {
+    /// Database that the monitor writes to
+    pub db_file: Option<PathBuf>,
+    /// Directory with the dashboard's files
+    pub web_dir: Option<PathBuf>,
+}
+
+impl PathsConfig {
+    /// Database that the monitor writes to, which is opened read-only
+    pub fn db_file(&self) -> PathBuf {
+        self.db_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DB_FILE))
+    }
+
+    /// Directory with the dashboard's files, which is the one in the
+    /// repository for tests that do not set it
+    pub fn web_dir(&self) -> PathBuf {
+        match &self.web_dir {
+            Some(web_dir) => web_dir.clone(),
+            None if cfg!(test) => {
+                event!(Level::TRACE, "Using the local assets folder for a test");
+                PathBuf::from(TEST_WEB_DIR)
+            }
+            None => {
+                event!(Level::TRACE, "Using the official install folder");
+                PathBuf::from(DEFAULT_WEB_DIR)
+            }
+        }
+    }
+}
+
+/// Settings for cross-origin requests to the API
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins that can call the API, like `https://grafana.example.com`,
+    /// where any origin can call it if this is not set
+    pub allowed_origins: Option<Vec<String>>,
+}
+
+impl CorsConfig {
+    /// Origins that can call the API as header values, which is None if any
+    /// origin can
+    pub fn origin_values(&self) -> Option<Vec<HeaderValue>> {
+        self.allowed_origins.as_ref().map(|origins| {
+            origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin).expect("Origins are validated"))
+                .collect()
+        })
+    }
+}
+
+/// Error from reading the configuration file
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file exists but could not be read
+    Io(io::Error),
+    /// The file is not valid TOML or has unknown settings
+    Parse(toml::de::Error),
+    /// A setting has a value that cannot be used
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "Unable to read the config: {}", err),
+            ConfigError::Parse(err) => write!(f, "Unable to parse the config: {}", err),
+            ConfigError::Invalid(message) => write!(f, "Invalid config: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ServerConfig {
+    /// Reads the configuration from a file, where a missing file leaves every
+    /// setting at its default
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents: String = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(ServerConfig::default()),
+            Err(err) => return Err(ConfigError::Io(err)),
+        };
+        ServerConfig::parse(&contents)
+    }
+
+    /// Parses the configuration from the contents of a file, making sure every
+    /// setting can be used
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let config: ServerConfig = toml::from_str(contents).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Replaces settings from the file with the ones from the command line and
+    /// the environment, making sure the result can still be used
+    pub fn with_overrides(mut self, overrides: &Overrides) -> Result<Self, ConfigError> {
+        if let Some(addresses) = &overrides.listen {
+            self.listen.addresses = Some(addresses.clone());
+        }
+        if let Some(unix_socket) = &overrides.unix_socket {
+            self.listen.unix_socket = Some(unix_socket.clone());
+        }
+        if let Some(db_file) = &overrides.db_file {
+            self.paths.db_file = Some(db_file.clone());
+        }
+        if let Some(web_dir) = &overrides.web_dir {
+            self.paths.web_dir = Some(web_dir.clone());
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Makes sure every setting can be used
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.auth.validate().map_err(ConfigError::Invalid)?;
+        for origin in self.cors.allowed_origins.iter().flatten() {
+            if HeaderValue::from_str(origin).is_err() || origin == "*" {
+                return Err(ConfigError::Invalid(format!(
+                    "{} is not an origin that can be allowed",
+                    origin
+                )));
+            }
+        }
+        let addresses: Vec<SocketAddr> = self.listen.addresses();
+        if addresses.is_empty() && self.listen.unix_socket.is_none() {
+            return Err(ConfigError::Invalid(String::from(
+                "There is nothing to listen on, since there are no addresses or Unix socket",
+            )));
+        }
+        let mut seen: HashSet<SocketAddr> = HashSet::new();
+        for address in addresses {
+            if !seen.insert(address) {
+                return Err(ConfigError::Invalid(format!(
+                    "{} is listened on more than once",
+                    address
+                )));
+            }
+        }
+        Ok(())
+    }
+}
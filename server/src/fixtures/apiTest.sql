@@ -1,87 +1,48 @@
-CREATE TABLE PROCESS (
-    PID            INT    NOT NULL CHECK(PID >= 1),
-    EXEC           TEXT   NOT NULL,
-    START_TIME     INT    NOT NULL CHECK(START_TIME >= 0),
-    IS_ALIVE       INT    NOT NULL CHECK(IS_ALIVE IN (0, 1)),
-    INIT_TOTAL_CPU REAL   NOT NULL CHECK(INIT_TOTAL_CPU >= 0),
-    PRIMARY KEY (PID)
-);
-
-CREATE TABLE CPU (
-    CPU_CORE     INT  NOT NULL CHECK(CPU_CORE >= 0),
-    MHZ          REAL NOT NULL CHECK(MHZ >= 0),
-    TOTAL_CACHE  INT  NOT NULL CHECK(TOTAL_CACHE >= 0),
-    PRIMARY KEY (CPU_CORE)
-);
-
-CREATE TABLE PROCSTAT (
-    PID              INT  NOT NULL,
-    TIMESTAMP        INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    TOTAL_CPU        REAL NOT NULL CHECK(TOTAL_CPU >= 0),
-    PERCENT_CPU      REAL          CHECK(PERCENT_CPU >= 0),
-    CPU_CORE         INT,
-    VIRTUAL_MEMORY   INT  NOT NULL CHECK(VIRTUAL_MEMORY >= 0),
-    RESIDENT_MEMORY  INT  NOT NULL CHECK(RESIDENT_MEMORY >= 0),
-    SHARED_MEMORY    INT  NOT NULL CHECK(SHARED_MEMORY >= 0),
-    PRIMARY KEY (PID, TIMESTAMP)
-    FOREIGN KEY (PID)       REFERENCES PROCESS(PID),
-    FOREIGN KEY (CPU_CORE)  REFERENCES CPU(CPU_CORE)
-);
-
-CREATE TABLE CPUSTAT (
-    CPU_CORE    INT  NOT NULL,
-    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    USAGE       REAL NOT NULL CHECK(USAGE >= 0),
-    PRIMARY KEY (CPU_CORE, TIMESTAMP)
-);
-
-CREATE TABLE MEMORY (
-    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    TOTAL       INT  NOT NULL CHECK(TOTAL >= 0),
-    FREE        INT  NOT NULL CHECK(FREE >= 0 AND FREE <= TOTAL),
-    SWAP_TOTAL  INT  NOT NULL CHECK(SWAP_TOTAL >= 0),
-    SWAP_FREE   INT  NOT NULL CHECK(SWAP_FREE >= 0 AND SWAP_FREE <= SWAP_TOTAL),
-    PRIMARY KEY (TIMESTAMP)
-);
-
-CREATE TABLE DISK (
-    DEVICE_NAME TEXT NOT NULL,
-    MOUNT       TEXT NOT NULL,
-    FS_TYPE     TEXT NOT NULL,
-    PRIMARY KEY (DEVICE_NAME)
-);
-
-CREATE TABLE DISKSTAT (
-    DEVICE_NAME TEXT NOT NULL,
-    TIMESTAMP   INT  NOT NULL CHECK(TIMESTAMP >= 0),
-    USED        INT  NOT NULL CHECK(USED >= 0),
-    AVAILABLE   INT  NOT NULL CHECK(AVAILABLE >= 0),
-    PRIMARY KEY (DEVICE_NAME, TIMESTAMP),
-    FOREIGN KEY (DEVICE_NAME) REFERENCES DISK(DEVICE_NAME)
-);
-
-INSERT INTO MEMORY VALUES(987654321, 2048, 1024, 256, 0);
-INSERT INTO MEMORY VALUES(987654322, 2048, 0, 256, 256);
-INSERT INTO MEMORY VALUES(987654323, 2048, 1024, 256, 128);
-
-INSERT INTO CPU VALUES (0, 5, 10);
-INSERT INTO PROCESS VALUES(1, "test-exe-1", 123456790, 1, 2048);
-INSERT INTO PROCSTAT VALUES(1, 987654321, 5000, 0.42, 0, 42, 42, 0);
-INSERT INTO PROCSTAT VALUES(1, 987654322, 5000, 0.42, 0, 42, 42, 0);
-INSERT INTO PROCESS VALUES(2, "test-exe-2", 123456790, 1, 2048);
-INSERT INTO PROCSTAT VALUES(2, 987654321, 5000, 0.42, 0, 42, 42, 0);
-INSERT INTO PROCSTAT VALUES(2, 987654322, 5000, 0.42, 0, 42, 42, 0);
-INSERT INTO PROCESS VALUES(3, "test-exe-3", 123456790, 1, 2048);
-INSERT INTO PROCSTAT VALUES(3, 987654321, 5000, 0.42, 0, 42, 42, 0);
-INSERT INTO PROCSTAT VALUES(3, 987654322, 5000, 0.42, 0, 42, 42, 0);
-
-INSERT INTO DISK VALUES("/dev1", "/mount1", "ext4");
-INSERT INTO DISK VALUES("/dev2", "/mount2", "ext4");
-INSERT INTO DISKSTAT VALUES("/dev1", 987654321, 42, 21);
-INSERT INTO DISKSTAT VALUES("/dev1", 987654322, 39, 24);
-INSERT INTO DISKSTAT VALUES("/dev2", 987654321, 42, 21);
-INSERT INTO DISKSTAT VALUES("/dev2", 987654322, 39, 24);
-
-
+INSERT INTO MEMORY VALUES(987654321, 2048000, 1024000, 256000, 0);
+INSERT INTO MEMORY VALUES(987654322, 2048000, 0, 256000, 256000);
+INSERT INTO MEMORY VALUES(987654323, 2048000, 1024000, 256000, 128000);
+
+INSERT INTO BOOT VALUES("boot-1", 123455000, 123456000, 987654300, NULL);
+INSERT INTO BOOT VALUES("boot-2", 987654310, 987654320, 987654322, 10);
+
+INSERT INTO CPU VALUES (0, 5, 10, TRUE);
+INSERT INTO PROCESS VALUES(1, "test-exe-1", 123456790, 1, 2048, "boot-2", 0, "root");
+INSERT INTO PROCSTAT VALUES(1, 123456790, 987654321, 5000, 0.42, 0, 42000, 42000, 0, 1000, 0);
+INSERT INTO PROCSTAT VALUES(1, 123456790, 987654322, 5000, 0.42, 0, 42000, 42000, 0, 5000, 1000);
+INSERT INTO PROCESS VALUES(2, "test-exe-2", 123456790, 1, 2048, "boot-2", 1000, "alice");
+INSERT INTO PROCSTAT VALUES(2, 123456790, 987654321, 5000, 0.42, 0, 42000, 42000, 0, NULL, NULL);
+INSERT INTO PROCSTAT VALUES(2, 123456790, 987654322, 5000, 0.42, 0, 42000, 42000, 0, NULL, NULL);
+INSERT INTO PROCESS VALUES(2, "old-exe-2", 123456000, 0, 10, "boot-1", NULL, NULL);
+INSERT INTO PROCSTAT VALUES(2, 123456000, 987654300, 20, 0.1, 0, 21000, 21000, 0, NULL, NULL);
+INSERT INTO PROCESS VALUES(3, "/usr/bin/test-exe-3", 123456790, 1, 2048, "boot-2", 1000, "alice");
+INSERT INTO PROCSTAT VALUES(3, 123456790, 987654321, 5000, 0.42, 0, 42000, 42000, 0, 0, 0);
+INSERT INTO PROCSTAT VALUES(3, 123456790, 987654322, 5000, 1.5, 0, 84000, 84000, 0, 100, 100);
+
+INSERT INTO DISK VALUES("/dev1", "/mount1", "ext4", TRUE, "rw,relatime", FALSE);
+INSERT INTO DISK VALUES("/dev2", "/mount2", "ext4", TRUE, "ro,relatime", TRUE);
+INSERT INTO DISK VALUES("/dev2", "/old-mount", "ext4", FALSE, "rw", FALSE);
+INSERT INTO DISKSTAT VALUES("/dev1", "/mount1", 987654321, 44040192, 22020096, 1000, 600, 400);
+INSERT INTO DISKSTAT VALUES("/dev1", "/mount1", 987654322, 40894464, 25165824, 1000, 990, 10);
+INSERT INTO DISKSTAT VALUES("/dev2", "/mount2", 987654321, 44040192, 22020096, 1000, 600, 400);
+INSERT INTO DISKSTAT VALUES("/dev2", "/mount2", 987654322, 40894464, 25165824, 1000, 990, 10);
+INSERT INTO DISKSTAT VALUES("/dev2", "/old-mount", 987654300, 44040192, 22020096, NULL, NULL, NULL);
+INSERT INTO DISKEVENT VALUES("/dev2", "/mount2", 987654322, "remounted_read_only", "ro,relatime");
+
+INSERT INTO NETWORKINTERFACE VALUES("eth0", "192.0.2.1", TRUE, "02:42:ac:11:00:02", 1500, "up", 1000);
+INSERT INTO NETWORKINTERFACE VALUES("eth1", NULL, FALSE, NULL, NULL, NULL, NULL);
+INSERT INTO NETWORKSTAT VALUES("eth0", 987654321, 4096, 2048, 8, 4, 0, 0, 3, 0, 1, 0, 0, 0, 0, 2, 0, 10000, 5000, 100, 50, 1, 0, 7, 0);
+INSERT INTO NETWORKSTAT VALUES("eth0", 987654322, 1024, 512, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 11024, 5512, 102, 51, 1, 0, 7, 0);
+INSERT INTO NETWORKSTAT VALUES("eth1", 987654300, 10, 10, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL);
+
+INSERT INTO PROTOCOLSTAT VALUES(987654321, 4, 8, 4, 0, 12, 2, 0, 0, 1, 0, 0);
+INSERT INTO PROTOCOLSTAT VALUES(987654322, 1, 3, 0, 1, 2, 2, 5, 5, 0, 0, 1);
+
+INSERT INTO CPU VALUES (1, 5, 10, FALSE);
 INSERT INTO CPUSTAT VALUES(0, 987654321, 0.42);
 INSERT INTO CPUSTAT VALUES(0, 987654322, 0.25);
+INSERT INTO CPUSTAT VALUES(1, 987654300, 0.5);
+
+INSERT INTO COLLECTOR_HEALTH VALUES("cpu", 4, 987654322, NULL, NULL, 0);
+INSERT INTO COLLECTOR_HEALTH VALUES("disk", 4, 987654318, 987654322, "io error: df not found", 2);
+
+INSERT INTO HOST VALUES(1, "test-host", "6.1.0-18-amd64", "debian", "Debian GNU/Linux", "12", "Debian GNU/Linux 12 (bookworm)", "Test CPU", "fpu sse2 hypervisor", 1, 4, 8, 2048000, "kvm", NULL, 987654320);
@@ -0,0 +1,135 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UnixListener;
+use tracing::{event, Level};
+
+/// Number of connections that can wait to be accepted
+const BACKLOG: i32 = 1024;
+
+/// Error from listening on a configured address
+#[derive(Debug)]
+pub enum ListenError {
+    /// The TCP address could not be listened on
+    Tcp(SocketAddr, io::Error),
+    /// The Unix socket could not be listened on
+    Unix(PathBuf, io::Error),
+}
+
+impl fmt::Display for ListenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: &io::Error = match self {
+            ListenError::Tcp(address, err) => {
+                write!(f, "Unable to listen on {}: {}", address, err)?;
+                err
+            }
+            ListenError::Unix(path, err) => {
+                write!(f, "Unable to listen on {}: {}", path.display(), err)?;
+                err
+            }
+        };
+        // Points at the usual fix, since the OS error alone does not
+        match (self, err.kind()) {
+            (_, io::ErrorKind::AddrInUse) => {
+                write!(f, " (another process is already listening there)")
+            }
+            (ListenError::Tcp(address, _), io::ErrorKind::PermissionDenied)
+                if address.port() < 1024 =>
+            {
+                write!(
+                    f,
+                    " (ports below 1024 need root or the CAP_NET_BIND_SERVICE capability)"
+                )
+            }
+            (ListenError::Tcp(..), io::ErrorKind::AddrNotAvailable) => {
+                write!(f, " (the IP address does not belong to this host)")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ListenError {}
+
+/// Listens on a TCP address, returning a non-blocking listener
+///
+/// IPv6 addresses only accept IPv6 connections, so that `0.0.0.0:4242` and
+/// `[::]:4242` can both be listened on instead of the second failing because
+/// the first one is already in use.
+pub fn bind_tcp(address: SocketAddr) -> Result<TcpListener, ListenError> {
+    let to_error = |err: io::Error| ListenError::Tcp(address, err);
+    let socket: Socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )
+    .map_err(to_error)?;
+    if address.is_ipv6() {
+        socket.set_only_v6(true).map_err(to_error)?;
+    }
+    // Lets a restarted server listen while connections from the last one are
+    // still closing
+    socket.set_reuse_address(true).map_err(to_error)?;
+    socket.set_nonblocking(true).map_err(to_error)?;
+    socket.bind(&address.into()).map_err(to_error)?;
+    socket.listen(BACKLOG).map_err(to_error)?;
+    Ok(socket.into())
+}
+
+/// Listens on a Unix socket
+///
+/// A socket left behind by a server that did not shut down cleanly is replaced,
+/// but one that another server is still listening on is not.
+pub fn bind_unix(path: &Path) -> Result<UnixListener, ListenError> {
+    let to_error = |err: io::Error| ListenError::Unix(path.to_path_buf(), err);
+    let is_socket: bool = fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_socket())
+        .unwrap_or(false);
+    if is_socket {
+        if UnixStream::connect(path).is_ok() {
+            return Err(to_error(io::Error::from(io::ErrorKind::AddrInUse)));
+        }
+        event!(Level::INFO, "Replacing the stale socket at {:?}", path);
+        fs::remove_file(path).map_err(to_error)?;
+    }
+    UnixListener::bind(path).map_err(to_error)
+}
+
+/// Serves the app on a Unix socket, for a reverse proxy on the same host
+///
+/// The socket always speaks plain HTTP, since it cannot be reached from the
+/// network.
+pub async fn serve_unix(listener: UnixListener, app: Router) -> io::Result<()> {
+    loop {
+        let stream: tokio::net::UnixStream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                // Running out of file descriptors clears up as connections
+                // close, so the server waits instead of stopping
+                event!(Level::ERROR, "Unable to accept a connection: {}", err);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let service: TowerToHyperService<Router> = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            if let Err(err) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                event!(Level::DEBUG, "Connection closed with an error: {}", err);
+            }
+        });
+    }
+}
@@ -1,54 +1,222 @@
 mod api;
+mod args;
+mod auth;
+mod config;
+mod listen;
+mod tls;
 mod web;
 
+use std::error::Error;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use args::{Command, Overrides};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
 use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use config::{ServerConfig, CONFIG_FILE};
 use sqlx::SqlitePool;
 use tokio::net::TcpListener;
+use tokio::task::JoinSet;
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::Level;
+use tracing::{event, Level};
 
 /// Creates the router for the application
-async fn create_app(test_sql_conn: Option<SqlitePool>) -> Result<Router, sqlx::Error> {
+async fn create_app(
+    config: &ServerConfig,
+    test_sql_conn: Option<SqlitePool>,
+) -> Result<Router, sqlx::Error> {
+    let auth: Arc<auth::Authenticator> = Arc::new(auth::Authenticator::new(
+        config.auth.clone(),
+        config.tls.is_some(),
+    ));
+    let cors: CorsLayer = match config.cors.origin_values() {
+        // Only listed origins may send the session cookie along
+        Some(origins) => CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_credentials(true),
+        None => CorsLayer::new().allow_origin(AllowOrigin::any()),
+    };
+    let state: api::AppState =
+        api::create_app_state(&config.paths.db_file(), test_sql_conn, auth.clone()).await?;
     let router: Router = Router::new()
-        .nest("/web", web::create_web_router())
-        .nest("/api", api::create_api_router(test_sql_conn).await?)
+        .nest(
+            "/web",
+            web::create_web_router(&config.paths.web_dir(), auth.clone()),
+        )
+        .nest("/api", api::create_api_router(state.clone()))
+        .merge(api::create_metrics_router(state))
+        .merge(auth::create_auth_router(auth))
         .layer(TraceLayer::new_for_http())
-        .layer(CorsLayer::new().allow_origin(AllowOrigin::any()));
+        .layer(
+            cors.allow_headers([AUTHORIZATION, CONTENT_TYPE])
+                // Lets cross-origin clients read the total when paging through processes
+                .expose_headers([api::TOTAL_COUNT_HEADER]),
+        );
 
     Ok(router)
 }
 
-#[tokio::main]
-async fn main() -> Result<(), sqlx::Error> {
+/// Starts serving on every configured address, returning once any of them
+/// stops
+async fn serve(config: ServerConfig, app: Router) -> Result<(), Box<dyn Error>> {
+    let rustls_config: Option<RustlsConfig> = match &config.tls {
+        Some(tls_config) => {
+            let rustls_config: RustlsConfig =
+                RustlsConfig::from_config(Arc::new(tls::load_server_config(tls_config)?));
+            tokio::spawn(tls::watch_certificates(
+                tls_config.clone(),
+                rustls_config.clone(),
+            ));
+            Some(rustls_config)
+        }
+        None => None,
+    };
+
+    // Everything is bound before anything is served, so that a bad address
+    // stops the server from starting instead of leaving it half up
+    let mut servers: JoinSet<io::Result<()>> = JoinSet::new();
+    for address in config.listen.addresses() {
+        let listener: std::net::TcpListener = listen::bind_tcp(address)?;
+        let app: Router = app.clone();
+        match &rustls_config {
+            Some(rustls_config) => {
+                event!(Level::INFO, "Listening on https://{}", address);
+                let server = axum_server::from_tcp_rustls(listener, rustls_config.clone())
+                    .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+                servers.spawn(server);
+            }
+            None => {
+                event!(Level::INFO, "Listening on http://{}", address);
+                let listener: TcpListener = TcpListener::from_std(listener)?;
+                // Client addresses are needed to slow down guessing passwords
+                servers.spawn(async move {
+                    axum::serve(
+                        listener,
+                        app.into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .await
+                });
+            }
+        }
+    }
+    if let Some(unix_socket) = &config.listen.unix_socket {
+        let listener: tokio::net::UnixListener = listen::bind_unix(unix_socket)?;
+        event!(Level::INFO, "Listening on {:?}", unix_socket);
+        servers.spawn(listen::serve_unix(listener, app));
+    }
+
+    while let Some(result) = servers.join_next().await {
+        result??;
+    }
+    Ok(())
+}
+
+/// Runs the command that the server was started with
+async fn run() -> Result<(), Box<dyn Error>> {
+    let overrides: Overrides = match Command::from_env()? {
+        Command::Serve(overrides) => overrides,
+        // Prints the hash of a password for a user in the config file
+        Command::HashPassword => {
+            let mut password: String = String::new();
+            io::stdin().read_line(&mut password)?;
+            println!(
+                "{}",
+                auth::hash_password(password.trim_end_matches(['\r', '\n']))
+            );
+            return Ok(());
+        }
+        Command::Help => {
+            print!("{}", args::USAGE);
+            return Ok(());
+        }
+    };
+
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
         .with_max_level(Level::TRACE)
         .init();
 
+    let config_file: PathBuf = overrides
+        .config_file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(CONFIG_FILE));
+    let config: ServerConfig = ServerConfig::load(&config_file)
+        .map_err(|err| format!("{}: {}", config_file.display(), err))?
+        .with_overrides(&overrides)?;
+    let web_dir: PathBuf = config.paths.web_dir();
+    if !web_dir.is_dir() {
+        return Err(format!("The web directory {} does not exist", web_dir.display()).into());
+    }
+
     // This is an executable, so we want to connect to the actual database
-    let app: Router = create_app(None).await?;
-    let listener: TcpListener = tokio::net::TcpListener::bind("0.0.0.0:4242").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let app: Router = create_app(&config, None).await.map_err(|err| {
+        format!(
+            "Unable to open the database at {}: {}",
+            config.paths.db_file().display(),
+            err
+        )
+    })?;
+    serve(config, app).await
+}
 
-    Ok(())
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("nebula-server: {}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::fs;
+    use std::future::IntoFuture;
     use std::io;
     use std::path::PathBuf;
+    use std::time::Duration;
 
     use super::*;
-    use crate::api::response::{CpuInfo, DiskInfo, ProcessInfo};
+    use crate::api::response::{
+        BootInfo, CollectorHealthInfo, CpuInfo, DiskEventInfo, DiskInfo, HostInfo, MemoryInfo,
+        NetworkInfo, ProcessInfo, ProtocolStatInfo, TopProcessInfo,
+    };
     use axum::body::Body;
     use axum::extract::Request;
     use axum::http::StatusCode;
     use axum::response::Response;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
     use http_body_util::BodyExt;
-    use models::tables::Memory;
+    use regex::Regex;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
     use tower::util::ServiceExt;
+    use utoipa::OpenApi;
+
+    /// Reads the events of a stream until one of them contains the given text,
+    /// returning everything that was read
+    async fn read_stream_until(body: &mut Body, text: &str) -> String {
+        let mut events: String = String::new();
+        while !events.contains(text) {
+            let frame = tokio::time::timeout(Duration::from_secs(10), body.frame())
+                .await
+                .expect("Should get an event before timing out")
+                .expect("Stream should not end")
+                .expect("Should be able to read the stream");
+            if let Ok(data) = frame.into_data() {
+                events.push_str(&String::from_utf8_lossy(&data));
+            }
+        }
+        events
+    }
 
     #[sqlx::test]
     async fn test_index_html(pool: SqlitePool) -> Result<(), sqlx::Error> {
@@ -57,7 +225,7 @@ mod tests {
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(Request::builder().uri("/web").body(Body::empty()).unwrap())
@@ -88,7 +256,7 @@ mod tests {
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(
@@ -124,7 +292,7 @@ mod tests {
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(
@@ -160,7 +328,7 @@ mod tests {
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(
@@ -196,7 +364,7 @@ mod tests {
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
@@ -208,14 +376,14 @@ mod tests {
         Ok(())
     }
 
-    #[sqlx::test(fixtures("apiTest"))]
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
     async fn test_api_memory(pool: SqlitePool) -> Result<(), sqlx::Error> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(
@@ -239,33 +407,39 @@ mod tests {
         )
         .expect("Should be able to convert to a string");
 
-        let res_vec: Vec<Memory> =
+        let res_vec: Vec<MemoryInfo> =
             serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
         assert_eq!(res_vec.len(), 3);
+        // Sizes are still reported in KB alongside the stored bytes
+        assert_eq!(res_vec[0].total, 2048);
+        assert_eq!(res_vec[0].total_bytes, 2048000);
+        assert_eq!(res_vec[0].free, 1024);
+        assert_eq!(res_vec[0].swap_total_bytes, 256000);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("apiTest"))]
-    async fn test_api_processes(pool: SqlitePool) -> Result<(), sqlx::Error> {
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_memory_history(pool: SqlitePool) -> Result<(), sqlx::Error> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
+        // Entries outside of the range are left out
         let response: Response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/api/allProcesses")
+                    .uri("/api/memory?from=987654322&to=987654323")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-
         let res_string: String = String::from_utf8(
             response
                 .into_body()
@@ -276,39 +450,78 @@ mod tests {
                 .to_vec(),
         )
         .expect("Should be able to convert to a string");
+        let res_vec: Vec<MemoryInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
+        assert_eq!(res_vec.len(), 2);
+        assert_eq!(res_vec[0].timestamp, 987654322);
 
-        let res_vec: Vec<ProcessInfo> = serde_json::from_str(&res_string)
-            .expect("Should be able to convert to a process info vec");
-
-        // Check if at least one item (ProcessInfo) is returned
-        assert!(
-            !res_vec.is_empty(),
-            "Expected at least one item in the response"
-        );
-
-        Ok(())
-    }
-
-    #[sqlx::test(fixtures("apiTest"))]
-    async fn test_api_existing_process(pool: SqlitePool) -> Result<(), sqlx::Error> {
-        let _ = tracing_subscriber::fmt()
-            .with_writer(io::stderr)
-            .with_max_level(Level::TRACE)
-            .try_init();
-
-        let app: Router = create_app(Some(pool)).await?;
+        // Every entry falls in the same bucket, which is stamped with its start
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/memory?step=10")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+        let res_vec: Vec<MemoryInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].timestamp, 987654320);
+        assert_eq!(res_vec[0].free_bytes, 682667);
+        assert_eq!(res_vec[0].swap_free_bytes, 128000);
 
         let response: Response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/api/process/1")
+                    .uri("/api/memory?step=10&agg=min")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+        let res_vec: Vec<MemoryInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
+        assert_eq!(res_vec[0].free_bytes, 0);
+        assert_eq!(res_vec[0].swap_free_bytes, 0);
 
+        // The range applies to the entries rather than their buckets
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/memory?from=987654322&step=10")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
         let res_string: String = String::from_utf8(
             response
                 .into_body()
@@ -319,69 +532,182 @@ mod tests {
                 .to_vec(),
         )
         .expect("Should be able to convert to a string");
+        let res_vec: Vec<MemoryInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].free_bytes, 512000);
 
-        let res_vec: Vec<ProcessInfo> = serde_json::from_str(&res_string)
-            .expect("Should be able to convert to a process info vec");
+        // The limit keeps the most recent entries
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/memory?limit=2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+        let res_vec: Vec<MemoryInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
         assert_eq!(res_vec.len(), 2);
+        assert_eq!(res_vec[0].timestamp, 987654322);
+        assert_eq!(res_vec[1].timestamp, 987654323);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("apiTest"))]
-    async fn test_api_nonexistent_process(pool: SqlitePool) -> Result<(), sqlx::Error> {
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_history_invalid_params(pool: SqlitePool) -> Result<(), sqlx::Error> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        for uri in [
+            "/api/memory?from=20&to=10",
+            "/api/cpu-info?step=0",
+            "/api/network?limit=-1",
+            "/api/protocol-stats?agg=median",
+            "/api/process/1?step=abc",
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
 
+        // The parameter that is not valid is named in the error
         let response: Response = app
             .oneshot(
                 Request::builder()
-                    .uri("/api/process/7")
+                    .uri("/api/memory?limit=0")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
-        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_json: serde_json::Value =
+            serde_json::from_slice(&res_bytes).expect("Should be able to convert to JSON");
+        assert_eq!(res_json["code"], "bad_request");
+        assert_eq!(res_json["details"]["parameter"], "limit");
 
-        let res_string: String = String::from_utf8(
-            response
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_errors(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool.clone())).await?;
+
+        // Each request with the status and code of its error
+        for (uri, status, code) in [
+            ("/api/not-an-endpoint", StatusCode::NOT_FOUND, "not_found"),
+            ("/api/process/abc", StatusCode::BAD_REQUEST, "bad_request"),
+            (
+                "/api/memory?agg=median",
+                StatusCode::BAD_REQUEST,
+                "bad_request",
+            ),
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+            assert_eq!(response.headers()["content-type"], "application/json");
+            let res_bytes: Vec<u8> = response
                 .into_body()
                 .collect()
                 .await
                 .unwrap()
                 .to_bytes()
-                .to_vec(),
-        )
-        .expect("Should be able to convert to a string");
-        assert_eq!(res_string, "Process 7 not found");
+                .to_vec();
+            let res_json: serde_json::Value =
+                serde_json::from_slice(&res_bytes).expect("Should be able to convert to JSON");
+            assert_eq!(res_json["code"], code, "{}", uri);
+        }
+
+        // The database going away is reported without the database's error
+        pool.close().await;
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/memory")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_json: serde_json::Value =
+            serde_json::from_slice(&res_bytes).expect("Should be able to convert to JSON");
+        assert_eq!(
+            res_json,
+            serde_json::json!({
+                "code": "database_unavailable",
+                "message": "Error fetching memory data",
+            })
+        );
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("apiTest"))]
-    async fn test_api_disks(pool: SqlitePool) -> Result<(), sqlx::Error> {
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_process_history(pool: SqlitePool) -> Result<(), sqlx::Error> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
+        // Processes that reused the PID are kept in separate buckets
         let response: Response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/api/disks")
+                    .uri("/api/process/2?step=100")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-
         let res_string: String = String::from_utf8(
             response
                 .into_body()
@@ -392,39 +718,26 @@ mod tests {
                 .to_vec(),
         )
         .expect("Should be able to convert to a string");
+        let res_vec: Vec<ProcessInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a process info vec");
+        assert_eq!(res_vec.len(), 2);
+        assert!(res_vec.iter().all(|info| info.timestamp == 987654300));
+        assert!(res_vec.iter().any(|info| info.exec == "old-exe-2"));
+        assert!(res_vec
+            .iter()
+            .any(|info| info.exec == "test-exe-2" && info.resident_memory == 42));
 
-        let res_vec: Vec<DiskInfo> = serde_json::from_str(&res_string)
-            .expect("Should be able to convert to a disk info vec");
-
-        // Check if at least one item (DiskInfo) is returned
-        assert!(
-            !res_vec.is_empty(),
-            "Expected at least one item in the response"
-        );
-
-        Ok(())
-    }
-
-    #[sqlx::test(fixtures("apiTest"))]
-    async fn test_api_cpus(pool: SqlitePool) -> Result<(), sqlx::Error> {
-        let _ = tracing_subscriber::fmt()
-            .with_writer(io::stderr)
-            .with_max_level(Level::TRACE)
-            .try_init();
-
-        let app: Router = create_app(Some(pool)).await?;
-
+        // A process without history in the range is still found
         let response: Response = app
             .oneshot(
                 Request::builder()
-                    .uri("/api/cpu-info")
+                    .uri("/api/process/1/123456790?from=999999999")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-
         let res_string: String = String::from_utf8(
             response
                 .into_body()
@@ -435,33 +748,33 @@ mod tests {
                 .to_vec(),
         )
         .expect("Should be able to convert to a string");
-
-        let res_vec: Vec<CpuInfo> =
-            serde_json::from_str(&res_string).expect("Should be able to convert to a cpu info vec");
-        assert_eq!(res_vec.len(), 2);
+        let res_vec: Vec<ProcessInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a process info vec");
+        assert!(res_vec.is_empty());
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("apiTest"))]
-    async fn test_api_cpu_info_current(pool: SqlitePool) -> Result<(), sqlx::Error> {
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_processes(pool: SqlitePool) -> Result<(), sqlx::Error> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(
                 Request::builder()
-                    .uri("/api/cpu-info-current")
+                    .uri("/api/allProcesses")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "4");
 
         let res_string: String = String::from_utf8(
             response
@@ -474,31 +787,35 @@ mod tests {
         )
         .expect("Should be able to convert to a string");
 
-        let res_vec: Vec<CpuInfo> =
-            serde_json::from_str(&res_string).expect("Should be able to convert to a CPU info vec");
+        let res_vec: Vec<ProcessInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a process info vec");
 
-        // Check if at least one item (CpuInfo) is returned
-        assert!(
-            !res_vec.is_empty(),
-            "Expected at least one item in the response"
-        );
+        // PID 2 has been reused, so both of its processes are returned
+        assert_eq!(res_vec.len(), 4);
+        let old_process: &ProcessInfo = res_vec
+            .iter()
+            .find(|info| info.start_time == 123456000)
+            .expect("Old process should be returned");
+        assert_eq!(old_process.pid, 2);
+        assert_eq!(old_process.timestamp, 987654300);
+        assert!(!old_process.is_alive);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures("apiTest"))]
-    async fn test_api_memory_current(pool: SqlitePool) -> Result<(), sqlx::Error> {
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_processes_by_boot(pool: SqlitePool) -> Result<(), sqlx::Error> {
         let _ = tracing_subscriber::fmt()
             .with_writer(io::stderr)
             .with_max_level(Level::TRACE)
             .try_init();
 
-        let app: Router = create_app(Some(pool)).await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
 
         let response: Response = app
             .oneshot(
                 Request::builder()
-                    .uri("/api/memory-current")
+                    .uri("/api/allProcesses?boot_id=boot-1")
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -506,25 +823,2396 @@ mod tests {
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
 
-        let res_string: String = String::from_utf8(
-            response
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_vec: Vec<ProcessInfo> = serde_json::from_slice(&res_bytes)
+            .expect("Should be able to convert to a process info vec");
+        // Only the process from before the reboot is returned
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].exec, "old-exe-2");
+        assert_eq!(res_vec[0].boot_id, Some("boot-1".to_string()));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_processes_filtered(
+        pool_options: SqlitePoolOptions,
+        connect_options: SqliteConnectOptions,
+    ) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // The executable filter needs REGEXP, like the server's own connection
+        let pool: SqlitePool = pool_options
+            .connect_with(connect_options.with_regexp())
+            .await?;
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        // Each request with the processes it returns and the number that matched
+        for (uri, expected, total_count) in [
+            ("/api/allProcesses?alive=false", vec![(2, 123456000)], "1"),
+            (
+                "/api/allProcesses?exec=test-exe",
+                vec![(1, 123456790), (2, 123456790), (3, 123456790)],
+                "3",
+            ),
+            (
+                "/api/allProcesses?exec_regex=^/usr/bin/",
+                vec![(3, 123456790)],
+                "1",
+            ),
+            (
+                "/api/allProcesses?user=alice",
+                vec![(2, 123456790), (3, 123456790)],
+                "2",
+            ),
+            ("/api/allProcesses?user=0", vec![(1, 123456790)], "1"),
+            // CPU usage is a fraction of a core rather than a percent
+            ("/api/allProcesses?min_cpu=1", vec![(3, 123456790)], "1"),
+            (
+                "/api/allProcesses?min_cpu=0.42",
+                vec![(1, 123456790), (2, 123456790), (3, 123456790)],
+                "3",
+            ),
+            ("/api/allProcesses?min_cpu=42", vec![], "0"),
+            (
+                "/api/allProcesses?min_memory=42000&alive=true&sort=memory&order=desc",
+                vec![(3, 123456790), (2, 123456790), (1, 123456790)],
+                "3",
+            ),
+            // Processes without an owner come last in either direction
+            (
+                "/api/allProcesses?sort=user&order=desc",
+                vec![
+                    (1, 123456790),
+                    (3, 123456790),
+                    (2, 123456790),
+                    (2, 123456000),
+                ],
+                "4",
+            ),
+            (
+                "/api/allProcesses?order=desc&offset=1&limit=2",
+                vec![(2, 123456790), (2, 123456000)],
+                "4",
+            ),
+            ("/api/allProcesses?offset=10", vec![], "4"),
+            // Pages of a regular expression are cut out after it is checked
+            (
+                "/api/allProcesses?exec_regex=test-exe-[0-9]$&offset=1&limit=1",
+                vec![(2, 123456790)],
+                "3",
+            ),
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(response.headers()["x-total-count"], total_count, "{}", uri);
+
+            let res_bytes: Vec<u8> = response
                 .into_body()
                 .collect()
                 .await
                 .unwrap()
                 .to_bytes()
-                .to_vec(),
-        )
-        .expect("Should be able to convert to a string");
+                .to_vec();
+            let res_vec: Vec<ProcessInfo> = serde_json::from_slice(&res_bytes)
+                .expect("Should be able to convert to a process info vec");
+            let processes: Vec<(u32, i64)> = res_vec
+                .iter()
+                .map(|info| (info.pid, info.start_time))
+                .collect();
+            assert_eq!(processes, expected, "{}", uri);
+        }
+
+        for uri in [
+            "/api/allProcesses?exec_regex=(",
+            "/api/allProcesses?limit=0",
+            "/api/allProcesses?sort=name",
+            "/api/allProcesses?alive=maybe",
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
 
-        let res_vec: Vec<Memory> =
-            serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
+        Ok(())
+    }
 
-        // Check if at least one item (Memory) is returned
-        assert!(
-            !res_vec.is_empty(),
-            "Expected at least one item in the response"
-        );
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_top(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        // Each request with the processes it ranks first and their scores
+        for (uri, expected) in [
+            (
+                "/api/top?metric=cpu",
+                vec![(3, 0.96), (1, 0.42), (2, 0.42), (2, 0.1)],
+            ),
+            (
+                "/api/top?metric=cpu&window=10s&n=2",
+                vec![(3, 0.96), (1, 0.42)],
+            ),
+            ("/api/top?metric=rss&agg=max&n=1", vec![(3, 84000.0)]),
+            // PID 2 cannot be inspected, and the rates need two entries
+            (
+                "/api/top?metric=io&window=1h",
+                vec![(1, 5000.0), (3, 200.0)],
+            ),
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+
+            let res_bytes: Vec<u8> = response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec();
+            let res_vec: Vec<TopProcessInfo> = serde_json::from_slice(&res_bytes)
+                .expect("Should be able to convert to a top process vec");
+            assert_eq!(res_vec.len(), expected.len(), "{}", uri);
+            for (info, (pid, score)) in res_vec.iter().zip(expected) {
+                assert_eq!(info.pid, pid, "{}", uri);
+                assert!((info.score - score).abs() < 1e-9, "{}", uri);
+            }
+        }
+
+        // The series of a process covers every entry in the window
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/top?metric=io&n=1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_vec: Vec<TopProcessInfo> = serde_json::from_slice(&res_bytes)
+            .expect("Should be able to convert to a top process vec");
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].exec, "test-exe-1");
+        assert_eq!(res_vec[0].user_name, Some("root".to_string()));
+        assert_eq!(res_vec[0].series.len(), 2);
+        assert_eq!(res_vec[0].series[0].timestamp, 987654321);
+        assert_eq!(res_vec[0].series[0].value, None);
+        assert_eq!(res_vec[0].series[1].value, Some(5000.0));
+
+        for uri in [
+            "/api/top",
+            "/api/top?metric=disk",
+            "/api/top?metric=cpu&window=abc",
+            "/api/top?metric=cpu&window=0m",
+            "/api/top?metric=cpu&n=0",
+            "/api/top?metric=cpu&n=101",
+            "/api/top?metric=cpu&agg=min",
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_stream(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool.clone())).await?;
+
+        // A client that reconnects is sent the ticks after its last event
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/stream?metrics=memory")
+                    .header("Last-Event-ID", "987654321")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let mut body: Body = response.into_body();
+        let events: String = read_stream_until(&mut body, "id: 987654323").await;
+        assert!(events.starts_with("retry:3000"));
+        assert!(events.contains("event: memory\nid: 987654322\ndata: [{"));
+        assert!(!events.contains("id: 987654321"));
+
+        // A new client starts with the latest tick of its PIDs, and is then sent
+        // every tick the monitor commits
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/stream?pids=1,3")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body: Body = response.into_body();
+        let events: String = read_stream_until(&mut body, "id: 987654322").await;
+        assert!(events.contains("event: process"));
+        assert!(events.contains("\"exec\":\"/usr/bin/test-exe-3\""));
+        assert!(!events.contains("id: 987654321"));
+
+        sqlx::query(
+            "INSERT INTO PROCSTAT VALUES(1, 123456790, 987654400, 5000, 0.5, 0, 42000, 42000, 0, NULL, NULL);",
+        )
+        .execute(&pool)
+        .await?;
+        let events: String = read_stream_until(&mut body, "id: 987654400").await;
+        assert!(events.contains("\"resident_memory_bytes\":42000"));
+        assert!(!events.contains("test-exe-3"));
+
+        for (uri, last_event_id) in [
+            ("/api/stream?metrics=disk", None),
+            ("/api/stream?metrics=cpu,process", None),
+            ("/api/stream?pids=abc", None),
+            ("/api/stream", Some("not-an-id")),
+        ] {
+            let mut request = Request::builder().uri(uri);
+            if let Some(last_event_id) = last_event_id {
+                request = request.header("Last-Event-ID", last_event_id);
+            }
+            let response: Response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_boots(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/boots")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_vec: Vec<BootInfo> =
+            serde_json::from_slice(&res_bytes).expect("Should be able to convert to a boot vec");
+        assert_eq!(res_vec.len(), 2);
+        assert_eq!(res_vec[0].boot_id, "boot-1");
+        assert_eq!(res_vec[0].downtime, None);
+        assert_eq!(res_vec[1].boot_id, "boot-2");
+        assert_eq!(res_vec[1].last_sample, Some(987654322));
+        assert_eq!(res_vec[1].downtime, Some(10));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_host(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/host")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let host: HostInfo =
+            serde_json::from_slice(&res_bytes).expect("Should be able to convert to host info");
+        assert_eq!(host.hostname, "test-host");
+        assert_eq!(host.os_id.as_deref(), Some("debian"));
+        assert_eq!((host.sockets, host.cores, host.threads), (1, 4, 8));
+        assert_eq!(host.virtualization.as_deref(), Some("kvm"));
+        assert_eq!(host.container, None);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR")]
+    async fn test_api_host_not_collected(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/host")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_existing_process(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/process/1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<ProcessInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a process info vec");
+        assert_eq!(res_vec.len(), 2);
+        assert_eq!(res_vec[0].resident_memory, 42);
+        assert_eq!(res_vec[0].resident_memory_bytes, 42000);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_nonexistent_process(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/process/7")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+        let res_json: serde_json::Value =
+            serde_json::from_str(&res_string).expect("Should be able to convert to JSON");
+        assert_eq!(
+            res_json,
+            serde_json::json!({
+                "code": "not_found",
+                "message": "Process 7 not found",
+                "details": { "pid": 7 },
+            })
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_process_instance(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        // All processes that have had the PID are returned
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/process/2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_vec: Vec<ProcessInfo> = serde_json::from_slice(&res_bytes)
+            .expect("Should be able to convert to a process info vec");
+        assert_eq!(res_vec.len(), 3);
+
+        // Only the old process is returned when asking for its start time
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/process/2/123456000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_vec: Vec<ProcessInfo> = serde_json::from_slice(&res_bytes)
+            .expect("Should be able to convert to a process info vec");
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].exec, "old-exe-2");
+        assert_eq!(res_vec[0].resident_memory_bytes, 21000);
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/process/2/42")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_disks(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/disks")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<DiskInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a disk info vec");
+
+        // Disks that are no longer mounted are left out
+        assert_eq!(res_vec.len(), 2);
+        assert!(res_vec.iter().all(|disk| disk.mount != "/old-mount"));
+        // Sizes are still reported in MB alongside the stored bytes
+        assert_eq!(res_vec[0].used, 39);
+        assert_eq!(res_vec[0].used_bytes, 39 * 1024 * 1024);
+        assert_eq!(res_vec[0].available, 24);
+        assert_eq!(res_vec[0].inodes_used, Some(990));
+        assert_eq!(res_vec[0].inodes_free, Some(10));
+        assert!(!res_vec[0].is_read_only);
+        assert!(res_vec[1].is_read_only);
+        assert_eq!(res_vec[1].mount_options, "ro,relatime");
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_disk_events(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/disk-events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<DiskEventInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a disk event info vec");
+
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].device_name, "/dev2");
+        assert_eq!(res_vec[0].mount, "/mount2");
+        assert_eq!(res_vec[0].event, "remounted_read_only");
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_network(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/network")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<NetworkInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a network info vec");
+
+        // Removed interfaces keep their history
+        assert_eq!(res_vec.len(), 3);
+        assert_eq!(res_vec[0].name, "eth0");
+        assert_eq!(res_vec[0].timestamp, 987654321);
+        assert_eq!(res_vec[0].bytes_recv, 4096);
+        assert_eq!(res_vec[0].drop_recv, 3);
+        assert_eq!(res_vec[0].fifo_recv, 1);
+        assert_eq!(res_vec[0].multicast_recv, 2);
+        assert_eq!(res_vec[0].speed, Some(1000));
+        assert_eq!(res_vec[1].timestamp, 987654322);
+        assert_eq!(res_vec[2].name, "eth1");
+        assert!(!res_vec[2].is_present);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_protocol_stats(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/protocol-stats")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<ProtocolStatInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a protocol stat info vec");
+
+        assert_eq!(res_vec.len(), 2);
+        // Counters are turned into rates over the time since the previous entry
+        assert_eq!(res_vec[0].timestamp, 987654321);
+        assert_eq!(res_vec[0].elapsed, 4);
+        assert_eq!(res_vec[0].tcp_retrans_segs_per_sec, 2.0);
+        assert_eq!(res_vec[0].tcp_active_opens_per_sec, 3.0);
+        assert_eq!(res_vec[0].udp_rcvbuf_errors_per_sec, 0.25);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_protocol_stats_current(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/protocol-stats-current")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<ProtocolStatInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a protocol stat info vec");
+
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].timestamp, 987654322);
+        assert_eq!(res_vec[0].tcp_retrans_segs_per_sec, 3.0);
+        assert_eq!(res_vec[0].tcp_listen_overflows_per_sec, 5.0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_cpus(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/cpu-info")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<CpuInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a cpu info vec");
+        // The history of the offline core is included
+        assert_eq!(res_vec.len(), 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_cpu_info_current(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/cpu-info-current")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<CpuInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a CPU info vec");
+
+        // Only the online core is returned
+        assert_eq!(res_vec.len(), 1);
+        assert_eq!(res_vec[0].cpu_core, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_memory_current(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/memory-current")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<MemoryInfo> =
+            serde_json::from_str(&res_string).expect("Should be able to convert to a memory vec");
+
+        // Check if at least one item (MemoryInfo) is returned
+        assert!(
+            !res_vec.is_empty(),
+            "Expected at least one item in the response"
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_collector_health(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/collector-health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+
+        let res_vec: Vec<CollectorHealthInfo> = serde_json::from_str(&res_string)
+            .expect("Should be able to convert to a collector health vec");
+        assert_eq!(res_vec.len(), 2);
+
+        let disk_health: &CollectorHealthInfo = &res_vec[1];
+        assert_eq!(disk_health.name, "disk");
+        assert_eq!(disk_health.consecutive_failures, 2);
+        assert_eq!(
+            disk_health.last_error,
+            Some("io error: df not found".to_string())
+        );
+        // All of the fixture data is from long ago
+        assert!(disk_health.is_stale);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_openapi_matches_routes() {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // The router starts path parameters with a colon, while OpenAPI wraps
+        // them in braces
+        let mut route_paths: Vec<String> = api::route_paths()
+            .iter()
+            .map(|path| {
+                path.split('/')
+                    .map(|segment| match segment.strip_prefix(':') {
+                        Some(name) => format!("{{{}}}", name),
+                        None => segment.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("/")
+            })
+            .collect();
+        route_paths.sort();
+        let spec: utoipa::openapi::OpenApi = api::ApiDoc::openapi();
+        let mut spec_paths: Vec<String> = spec.paths.paths.keys().cloned().collect();
+        spec_paths.sort();
+        assert_eq!(route_paths, spec_paths);
+
+        // Every endpoint only answers GET requests
+        for (path, item) in spec.paths.paths.iter() {
+            assert!(
+                item.operations.len() == 1
+                    && item
+                        .operations
+                        .contains_key(&utoipa::openapi::PathItemType::Get),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_openapi_docs_file() {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // The documentation site renders a copy of the generated document,
+        // which is rewritten when NEBULA_WRITE_OPENAPI_SPEC is set
+        let spec_path: &str = "../docs/static/resources/openapi-spec.yml";
+        let spec_yaml: String = api::ApiDoc::openapi()
+            .to_yaml()
+            .expect("Should be able to convert the spec to YAML");
+        if std::env::var_os("NEBULA_WRITE_OPENAPI_SPEC").is_some() {
+            fs::write(spec_path, &spec_yaml).expect("Should be able to write the spec");
+        }
+        let file_yaml: String = fs::read_to_string(spec_path).expect("Spec should exist");
+        assert!(
+            file_yaml == spec_yaml,
+            "{} is out of date, run the tests with NEBULA_WRITE_OPENAPI_SPEC=1 to update it",
+            spec_path
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_api_openapi(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_json: serde_json::Value =
+            serde_json::from_slice(&res_bytes).expect("Should be able to convert to JSON");
+        assert_eq!(res_json, serde_json::json!(api::ApiDoc::openapi()));
+
+        // The viewer loads the document relative to its own path
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/docs")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/html; charset=utf-8"
+        );
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+        assert!(res_string.contains(r#"url: "openapi.json""#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_config() {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Nothing is required, which leaves authentication off
+        let config: ServerConfig = ServerConfig::parse("").expect("Empty config should parse");
+        assert!(!config.auth.is_enabled());
+        assert!(config.cors.origin_values().is_none());
+        assert_eq!(
+            config.listen.addresses(),
+            vec!["0.0.0.0:4242".parse().unwrap()]
+        );
+        assert_eq!(
+            config.paths.db_file(),
+            PathBuf::from("/var/nebula/db/nebulaMetrics.db")
+        );
+
+        let password_hash: String = auth::hash_password("hunter2");
+        let config: ServerConfig = ServerConfig::parse(&format!(
+            r#"
+            [auth]
+            session_ttl = 3600
+
+            [[auth.tokens]]
+            name = "scraper"
+            token = "0123456789abcdef"
+            role = "read_only"
+
+            [[auth.users]]
+            name = "alice"
+            password_hash = "{}"
+            role = "admin"
+
+            [cors]
+            allowed_origins = ["https://grafana.example.com"]
+
+            [tls]
+            cert_file = "/etc/nebula/server.crt"
+            key_file = "/etc/nebula/server.key"
+            client_ca_file = "/etc/nebula/clients.crt"
+            client_auth = "optional"
+
+            [listen]
+            addresses = ["0.0.0.0:4242", "[::]:4242"]
+            unix_socket = "/run/nebula/server.sock"
+
+            [paths]
+            db_file = "/srv/nebula/nebulaMetrics.db"
+            web_dir = "/srv/nebula/web"
+            "#,
+            password_hash
+        ))
+        .expect("Config should parse");
+        assert!(config.auth.is_enabled());
+        assert_eq!(config.auth.tokens[0].role, auth::Role::ReadOnly);
+        assert_eq!(config.auth.users[0].role, auth::Role::Admin);
+        assert_eq!(config.cors.origin_values().unwrap().len(), 1);
+        let tls_config: tls::TlsConfig = config.tls.expect("TLS should be configured");
+        assert_eq!(tls_config.client_auth, tls::ClientAuth::Optional);
+        assert!(tls_config.reload_interval.is_none());
+        assert_eq!(config.listen.addresses().len(), 2);
+        assert!(config.listen.addresses()[1].is_ipv6());
+        assert_eq!(
+            config.listen.unix_socket,
+            Some(PathBuf::from("/run/nebula/server.sock"))
+        );
+        assert_eq!(config.paths.web_dir(), PathBuf::from("/srv/nebula/web"));
+
+        // Settings that cannot be used are rejected when the server starts
+        for contents in [
+            "[auth]\nunknown = true",
+            "[[auth.tokens]]\nname = \"a\"\ntoken = \"short\"\nrole = \"admin\"",
+            "[[auth.tokens]]\nname = \"a\"\ntoken = \"0123456789abcdef\"\nrole = \"owner\"",
+            "[[auth.users]]\nname = \"a\"\npassword_hash = \"hunter2\"\nrole = \"admin\"",
+            "[auth]\nsession_ttl = 0",
+            "[cors]\nallowed_origins = [\"*\"]",
+            "[tls]\ncert_file = \"server.crt\"",
+            "[tls]\ncert_file = \"a\"\nkey_file = \"b\"\nclient_auth = \"never\"",
+            "[listen]\naddresses = [\"localhost:4242\"]",
+            "[listen]\naddresses = [\"4242\"]",
+            "[listen]\naddresses = []",
+            "[listen]\naddresses = [\"[::]:4242\", \"[::]:4242\"]",
+            "[listen]\nport = 4242",
+            "[paths]\ndb = \"/tmp/nebula.db\"",
+        ] {
+            assert!(ServerConfig::parse(contents).is_err(), "{}", contents);
+        }
+    }
+
+    /// Creates the config for the authentication tests, with a read-only
+    /// token, an admin token, and an admin user alice whose password is
+    /// hunter2
+    fn auth_test_config() -> ServerConfig {
+        ServerConfig::parse(&format!(
+            r#"
+            [[auth.tokens]]
+            name = "scraper"
+            token = "scraper-0123456789"
+            role = "read_only"
+
+            [[auth.tokens]]
+            name = "deployer"
+            token = "deployer-0123456789"
+            role = "admin"
+
+            [[auth.users]]
+            name = "alice"
+            password_hash = "{}"
+            role = "admin"
+            "#,
+            auth::hash_password("hunter2")
+        ))
+        .expect("Config should parse")
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_api_auth(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Anyone can make requests when there are no tokens or users
+        let app: Router = create_app(&ServerConfig::default(), Some(pool.clone())).await?;
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/session")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_json: serde_json::Value =
+            serde_json::from_slice(&res_bytes).expect("Should be able to convert to JSON");
+        assert_eq!(
+            res_json,
+            serde_json::json!({ "auth_enabled": false, "name": null, "role": null })
+        );
+
+        let app: Router = create_app(&auth_test_config(), Some(pool)).await?;
+        let basic = |credentials: &str| format!("Basic {}", BASE64.encode(credentials));
+        // Each request with its method, Authorization header, and expected status
+        for (method, authorization, status) in [
+            ("GET", None, StatusCode::UNAUTHORIZED),
+            (
+                "GET",
+                Some("Bearer wrong-0123456789".to_string()),
+                StatusCode::UNAUTHORIZED,
+            ),
+            ("GET", Some(basic("alice:wrong")), StatusCode::UNAUTHORIZED),
+            ("GET", Some(basic("bob:hunter2")), StatusCode::UNAUTHORIZED),
+            (
+                "GET",
+                Some("Bearer scraper-0123456789".to_string()),
+                StatusCode::OK,
+            ),
+            ("GET", Some(basic("alice:hunter2")), StatusCode::OK),
+            // Read-only tokens cannot make requests that could change anything,
+            // while admins get through to the router
+            (
+                "POST",
+                Some("Bearer scraper-0123456789".to_string()),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                "POST",
+                Some("Bearer deployer-0123456789".to_string()),
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+        ] {
+            let mut request = Request::builder().method(method).uri("/api/memory");
+            if let Some(authorization) = &authorization {
+                request = request.header("authorization", authorization);
+            }
+            let response: Response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{} {:?}", method, authorization);
+            if status == StatusCode::UNAUTHORIZED {
+                assert_eq!(
+                    response.headers()["www-authenticate"],
+                    r#"Bearer realm="Nebula Metrics""#
+                );
+                let res_bytes: Vec<u8> = response
+                    .into_body()
+                    .collect()
+                    .await
+                    .unwrap()
+                    .to_bytes()
+                    .to_vec();
+                let res_json: serde_json::Value =
+                    serde_json::from_slice(&res_bytes).expect("Should be able to convert to JSON");
+                assert_eq!(res_json["code"], "unauthorized");
+            }
+        }
+
+        // The session endpoint says who made the request
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/session")
+                    .header("authorization", "Bearer scraper-0123456789")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let res_json: serde_json::Value =
+            serde_json::from_slice(&res_bytes).expect("Should be able to convert to JSON");
+        assert_eq!(
+            res_json,
+            serde_json::json!({ "auth_enabled": true, "name": "scraper", "role": "read_only" })
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_web_login(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&auth_test_config(), Some(pool)).await?;
+
+        // The dashboard sends people to the login page first
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/web/system")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()["location"],
+            "/login?next=%2Fweb%2Fsystem"
+        );
+
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/login?next=%2Fweb%2Fsystem")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+        assert!(res_string.contains(r#"name="next" value="/web/system""#));
+        assert!(!res_string.contains("incorrect"));
+
+        // Each form with where it should go, where pages on other sites are
+        // replaced with the dashboard
+        for (form, location) in [
+            (
+                "username=alice&password=wrong&next=%2Fweb%2Fsystem",
+                "/login?failed=true&next=%2Fweb%2Fsystem",
+            ),
+            (
+                "username=alice&password=hunter2&next=%2Fweb%2Fsystem",
+                "/web/system",
+            ),
+            (
+                "username=alice&password=hunter2&next=%2F%2Fexample.com",
+                "/web",
+            ),
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/login")
+                        .header("content-type", "application/x-www-form-urlencoded")
+                        .body(Body::from(form))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::SEE_OTHER, "{}", form);
+            assert_eq!(response.headers()["location"], location, "{}", form);
+            assert_eq!(
+                response.headers().contains_key("set-cookie"),
+                !location.starts_with("/login"),
+                "{}",
+                form
+            );
+        }
+
+        // The session cookie lets the dashboard and the API through
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/login")
+                    .header("content-type", "application/x-www-form-urlencoded")
+                    .body(Body::from("username=alice&password=hunter2"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let set_cookie: &str = response.headers()["set-cookie"].to_str().unwrap();
+        assert!(set_cookie.contains("HttpOnly"));
+        // The cookie can be sent over plain HTTP, since that is all the server
+        // speaks without TLS
+        assert!(!set_cookie.contains("Secure"));
+        let cookie: String = set_cookie.split(';').next().unwrap().to_string();
+        for uri in ["/web", "/api/memory"] {
+            let response: Response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header("cookie", &cookie)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        }
+
+        // Signing out ends the session
+        let response: Response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/logout")
+                    .header("cookie", &cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()["location"], "/login");
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/memory")
+                    .header("cookie", &cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_web_login_backoff(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&auth_test_config(), Some(pool)).await?;
+        // Signs in with the form from the client address
+        let login = |form: &'static str, address: &str| {
+            let mut request: Request = Request::builder()
+                .method("POST")
+                .uri("/login")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(form))
+                .unwrap();
+            let address: SocketAddr = address.parse().unwrap();
+            request
+                .extensions_mut()
+                .insert(axum::extract::ConnectInfo(address));
+            app.clone().oneshot(request)
+        };
+
+        for _ in 0..5 {
+            let response: Response = login("username=alice&password=wrong", "10.0.0.1:4000")
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+            assert_eq!(
+                response.headers()["location"],
+                "/login?failed=true&next=%2Fweb"
+            );
+        }
+
+        // Too many failures make both the user and the address wait, even with
+        // the right password
+        let response: Response = login("username=alice&password=hunter2", "10.0.0.1:4000")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "1");
+        assert!(!response.headers().contains_key("set-cookie"));
+        let res_string: String = String::from_utf8(
+            response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .expect("Should be able to convert to a string");
+        assert!(res_string.contains("Too many attempts to sign in failed"));
+        // Each form with the address it is sent from and the status it gets
+        for (form, address, status) in [
+            (
+                "username=alice&password=hunter2",
+                "10.0.0.2:4000",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                "username=bob&password=wrong",
+                "10.0.0.1:4001",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                "username=bob&password=wrong",
+                "10.0.0.2:4000",
+                StatusCode::SEE_OTHER,
+            ),
+        ] {
+            let response: Response = login(form, address).await.unwrap();
+            assert_eq!(response.status(), status, "{} from {}", form, address);
+        }
+
+        // Signing in works again after the wait, and forgets the failures
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let response: Response = login("username=alice&password=hunter2", "10.0.0.2:4000")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()["location"], "/web");
+        assert!(response.headers().contains_key("set-cookie"));
+        let response: Response = login("username=alice&password=wrong", "10.0.0.2:4000")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()["location"],
+            "/login?failed=true&next=%2Fweb"
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_cors_allowed_origins(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let config: ServerConfig =
+            ServerConfig::parse("[cors]\nallowed_origins = [\"https://grafana.example.com\"]")
+                .expect("Config should parse");
+        let app: Router = create_app(&config, Some(pool)).await?;
+
+        // Each origin with whether it is allowed
+        for (origin, is_allowed) in [
+            ("https://grafana.example.com", true),
+            ("https://example.com", false),
+        ] {
+            let response: Response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/api/memory")
+                        .header("origin", origin)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response
+                    .headers()
+                    .get("access-control-allow-origin")
+                    .map(|value| value.to_str().unwrap()),
+                is_allowed.then_some(origin),
+                "{}",
+                origin
+            );
+            if is_allowed {
+                assert_eq!(
+                    response.headers()["access-control-allow-credentials"],
+                    "true"
+                );
+                // Browsers only show the total to scripts when it is exposed
+                assert_eq!(
+                    response.headers()["access-control-expose-headers"],
+                    "x-total-count"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Certificates for the TLS tests, written as PEM files into a directory
+    /// that is only used by one test
+    struct TlsTestFiles {
+        /// Directory that the files are in
+        dir: PathBuf,
+        /// CA that signs the server certificates
+        server_ca: (rcgen::Certificate, rcgen::KeyPair),
+        /// CA that signs the client certificates
+        client_ca: (rcgen::Certificate, rcgen::KeyPair),
+    }
+
+    impl TlsTestFiles {
+        /// Creates a new server CA and client CA, and writes a server
+        /// certificate for localhost signed by the server CA
+        fn new(name: &str) -> Self {
+            let dir: PathBuf =
+                std::env::temp_dir().join(format!("nebula-tls-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let files: TlsTestFiles = TlsTestFiles {
+                dir,
+                server_ca: TlsTestFiles::new_ca("Nebula Test Server CA"),
+                client_ca: TlsTestFiles::new_ca("Nebula Test Client CA"),
+            };
+            files.rotate_server_certificate();
+            fs::write(files.path("clients.crt"), files.client_ca.0.pem()).unwrap();
+            files
+        }
+
+        /// Creates a self-signed CA
+        fn new_ca(common_name: &str) -> (rcgen::Certificate, rcgen::KeyPair) {
+            let mut params: rcgen::CertificateParams = rcgen::CertificateParams::default();
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, common_name);
+            let key: rcgen::KeyPair = rcgen::KeyPair::generate().unwrap();
+            (params.self_signed(&key).unwrap(), key)
+        }
+
+        /// Creates a certificate signed by a CA, returning the certificate
+        /// and key as PEM
+        fn new_leaf(name: &str, ca: &(rcgen::Certificate, rcgen::KeyPair)) -> (String, String) {
+            let params: rcgen::CertificateParams =
+                rcgen::CertificateParams::new(vec![name.to_string()]).unwrap();
+            let key: rcgen::KeyPair = rcgen::KeyPair::generate().unwrap();
+            let certificate: rcgen::Certificate = params.signed_by(&key, &ca.0, &ca.1).unwrap();
+            (certificate.pem(), key.serialize_pem())
+        }
+
+        /// Path of a file in the directory
+        fn path(&self, file_name: &str) -> PathBuf {
+            self.dir.join(file_name)
+        }
+
+        /// Writes a new server certificate and key, like a certificate renewal
+        /// would
+        fn rotate_server_certificate(&self) {
+            let (certificate, key): (String, String) =
+                TlsTestFiles::new_leaf("localhost", &self.server_ca);
+            fs::write(self.path("server.crt"), certificate).unwrap();
+            fs::write(self.path("server.key"), key).unwrap();
+        }
+
+        /// Replaces the server CA, so that clients that only trust the old CA
+        /// can tell when the new certificate is being served, and returns the
+        /// old CA
+        fn replace_server_ca(&mut self) -> (rcgen::Certificate, rcgen::KeyPair) {
+            let old_server_ca: (rcgen::Certificate, rcgen::KeyPair) = std::mem::replace(
+                &mut self.server_ca,
+                TlsTestFiles::new_ca("Nebula Test Server CA"),
+            );
+            self.rotate_server_certificate();
+            old_server_ca
+        }
+
+        /// Creates the server config for the files, with the given extra
+        /// settings for the tls section
+        fn server_config(&self, extra: &str) -> ServerConfig {
+            ServerConfig::parse(&format!(
+                "[tls]\ncert_file = \"{}\"\nkey_file = \"{}\"\n{}",
+                self.path("server.crt").display(),
+                self.path("server.key").display(),
+                extra
+            ))
+            .expect("TLS config should parse")
+        }
+    }
+
+    impl Drop for TlsTestFiles {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Serves the app over HTTPS on a random local port
+    async fn serve_tls(app: Router, tls_config: &tls::TlsConfig) -> std::net::SocketAddr {
+        let rustls_config: RustlsConfig = RustlsConfig::from_config(Arc::new(
+            tls::load_server_config(tls_config).expect("TLS files should load"),
+        ));
+        tokio::spawn(tls::watch_certificates(
+            tls_config.clone(),
+            rustls_config.clone(),
+        ));
+        let handle: axum_server::Handle = axum_server::Handle::new();
+        tokio::spawn(
+            axum_server::bind_rustls("127.0.0.1:0".parse().unwrap(), rustls_config)
+                .handle(handle.clone())
+                .serve(app.into_make_service()),
+        );
+        handle.listening().await.expect("Server should listen")
+    }
+
+    /// Sends a GET request over a connection that is closed afterward, and
+    /// returns the whole response
+    async fn raw_get(
+        mut stream: impl tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+        uri: &str,
+    ) -> io::Result<String> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        stream
+            .write_all(
+                format!(
+                    "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                    uri
+                )
+                .as_bytes(),
+            )
+            .await?;
+        let mut response: String = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    /// Sends a GET request over HTTPS, trusting only the given server CA and
+    /// presenting the given client certificate and key, and returns the
+    /// whole response
+    async fn tls_get(
+        addr: std::net::SocketAddr,
+        uri: &str,
+        server_ca: &rcgen::Certificate,
+        client: Option<(String, String)>,
+    ) -> io::Result<String> {
+        let mut roots: rustls::RootCertStore = rustls::RootCertStore::empty();
+        roots.add(server_ca.der().clone()).unwrap();
+        let builder = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots);
+        let client_config: rustls::ClientConfig = match client {
+            Some((certificate, key)) => builder
+                .with_client_auth_cert(
+                    rustls_pemfile::certs(&mut certificate.as_bytes())
+                        .collect::<Result<Vec<_>, io::Error>>()?,
+                    rustls_pemfile::private_key(&mut key.as_bytes())?.unwrap(),
+                )
+                .unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+        let connector: tokio_rustls::TlsConnector =
+            tokio_rustls::TlsConnector::from(Arc::new(client_config));
+        let stream: tokio::net::TcpStream = tokio::net::TcpStream::connect(addr).await?;
+        let stream = connector
+            .connect("localhost".try_into().unwrap(), stream)
+            .await?;
+        raw_get(stream, uri).await
+    }
+
+    #[test]
+    fn test_tls_load_errors() {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let files: TlsTestFiles = TlsTestFiles::new("load-errors");
+        let config: ServerConfig = files.server_config("");
+        assert!(tls::load_server_config(&config.tls.unwrap()).is_ok());
+
+        // Each file has to exist and hold what it is supposed to, so that a
+        // mistake stops the server from starting instead of failing every
+        // connection
+        for (cert_file, key_file, client_ca_file) in [
+            ("missing.crt", "server.key", None),
+            ("server.crt", "missing.key", None),
+            ("server.key", "server.key", None),
+            ("server.crt", "server.crt", None),
+            ("server.crt", "server.key", Some("missing.crt")),
+            ("server.crt", "server.key", Some("server.key")),
+        ] {
+            let tls_config: tls::TlsConfig = tls::TlsConfig {
+                cert_file: files.path(cert_file),
+                key_file: files.path(key_file),
+                client_ca_file: client_ca_file.map(|file| files.path(file)),
+                client_auth: tls::ClientAuth::Required,
+                reload_interval: None,
+            };
+            let err: tls::TlsError = tls::load_server_config(&tls_config)
+                .expect_err(&format!("{} {}", cert_file, key_file));
+            assert!(!err.to_string().is_empty());
+        }
+
+        // A key that does not belong to the certificate is not accepted
+        fs::write(
+            files.path("other.key"),
+            rcgen::KeyPair::generate().unwrap().serialize_pem(),
+        )
+        .unwrap();
+        let tls_config: tls::TlsConfig = tls::TlsConfig {
+            key_file: files.path("other.key"),
+            ..files.server_config("").tls.unwrap()
+        };
+        assert!(tls::load_server_config(&tls_config).is_err());
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_tls_serve_and_reload(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let mut files: TlsTestFiles = TlsTestFiles::new("reload");
+        let config: ServerConfig = files.server_config("reload_interval = 1");
+        let app: Router = create_app(&config, Some(pool)).await?;
+        let addr: std::net::SocketAddr = serve_tls(app, config.tls.as_ref().unwrap()).await;
+
+        let response: String = tls_get(addr, "/api/memory", &files.server_ca.0, None)
+            .await
+            .expect("Request should succeed");
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+        // A renewed certificate is picked up without restarting the server
+        let old_server_ca: (rcgen::Certificate, rcgen::KeyPair) = files.replace_server_ca();
+        let mut reloaded: bool = false;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if tls_get(addr, "/api/memory", &files.server_ca.0, None)
+                .await
+                .is_ok()
+            {
+                reloaded = true;
+                break;
+            }
+        }
+        assert!(reloaded, "The new certificate should be served");
+        assert!(tls_get(addr, "/api/memory", &old_server_ca.0, None)
+            .await
+            .is_err());
+
+        // Files that cannot be used keep the current certificate in place
+        fs::write(files.path("server.key"), "not a key").unwrap();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let response: String = tls_get(addr, "/api/memory", &files.server_ca.0, None)
+            .await
+            .expect("Request should still succeed");
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_tls_client_certificates(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let files: TlsTestFiles = TlsTestFiles::new("client-auth");
+        let client_ca_file: String = format!(
+            "client_ca_file = \"{}\"",
+            files.path("clients.crt").display()
+        );
+        let other_ca: (rcgen::Certificate, rcgen::KeyPair) =
+            TlsTestFiles::new_ca("Nebula Test Other CA");
+
+        for (client_auth, without_certificate) in [("required", false), ("optional", true)] {
+            let config: ServerConfig = files.server_config(&format!(
+                "{}\nclient_auth = \"{}\"",
+                client_ca_file, client_auth
+            ));
+            let app: Router = create_app(&config, Some(pool.clone())).await?;
+            let addr: std::net::SocketAddr = serve_tls(app, config.tls.as_ref().unwrap()).await;
+
+            // Certificates signed by the client CA are always accepted
+            let response: String = tls_get(
+                addr,
+                "/api/memory",
+                &files.server_ca.0,
+                Some(TlsTestFiles::new_leaf("scraper", &files.client_ca)),
+            )
+            .await
+            .expect("Request should succeed");
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", client_auth);
+
+            // Certificates signed by anyone else never are
+            assert!(
+                tls_get(
+                    addr,
+                    "/api/memory",
+                    &files.server_ca.0,
+                    Some(TlsTestFiles::new_leaf("scraper", &other_ca)),
+                )
+                .await
+                .is_err(),
+                "{}",
+                client_auth
+            );
+
+            let response: io::Result<String> =
+                tls_get(addr, "/api/memory", &files.server_ca.0, None).await;
+            assert_eq!(
+                response.is_ok_and(|response| response.starts_with("HTTP/1.1 200")),
+                without_certificate,
+                "{}",
+                client_auth
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Parses the server's arguments with the given environment variables
+    fn parse_command(args: &[&str], vars: &[(&str, &str)]) -> Result<Command, args::ArgsError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Command::parse(args.iter().map(|arg| arg.to_string()), |name| {
+            vars.get(name).cloned()
+        })
+    }
+
+    #[test]
+    fn test_command_args() {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        assert_eq!(
+            parse_command(&[], &[]),
+            Ok(Command::Serve(Overrides::default()))
+        );
+        assert_eq!(
+            parse_command(&["hash-password"], &[]),
+            Ok(Command::HashPassword)
+        );
+        assert_eq!(parse_command(&["--help"], &[]), Ok(Command::Help));
+        assert_eq!(
+            parse_command(
+                &[
+                    "--listen",
+                    "127.0.0.1:4242",
+                    "--listen=[::1]:4242",
+                    "--unix-socket=/run/nebula/server.sock",
+                    "--db-file",
+                    "/srv/nebula/nebulaMetrics.db",
+                    "--web-dir",
+                    "/srv/nebula/web",
+                    "-c",
+                    "/srv/nebula/server.toml",
+                ],
+                &[]
+            ),
+            Ok(Command::Serve(Overrides {
+                config_file: Some(PathBuf::from("/srv/nebula/server.toml")),
+                listen: Some(vec![
+                    "127.0.0.1:4242".parse().unwrap(),
+                    "[::1]:4242".parse().unwrap()
+                ]),
+                unix_socket: Some(PathBuf::from("/run/nebula/server.sock")),
+                db_file: Some(PathBuf::from("/srv/nebula/nebulaMetrics.db")),
+                web_dir: Some(PathBuf::from("/srv/nebula/web")),
+            }))
+        );
+
+        // Mistakes are explained instead of being ignored
+        for args in [
+            vec!["--port", "4242"],
+            vec!["--listen"],
+            vec!["--listen", "4242"],
+            vec!["--listen", "localhost:4242"],
+            vec!["--listen", "[::]"],
+        ] {
+            let err: args::ArgsError = parse_command(&args, &[]).expect_err(&args.join(" "));
+            assert!(err.to_string().contains("--help"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_command_env() {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let vars: [(&str, &str); 4] = [
+            ("NEBULA_LISTEN", "0.0.0.0:4242, [::]:4242"),
+            ("NEBULA_UNIX_SOCKET", "/run/nebula/server.sock"),
+            ("NEBULA_DB_FILE", "/srv/nebula/nebulaMetrics.db"),
+            ("NEBULA_CONFIG", "/srv/nebula/server.toml"),
+        ];
+        let Ok(Command::Serve(overrides)) = parse_command(&[], &vars) else {
+            panic!("The variables should parse");
+        };
+        assert_eq!(overrides.listen.map(|listen| listen.len()), Some(2));
+        assert_eq!(
+            overrides.unix_socket,
+            Some(PathBuf::from("/run/nebula/server.sock"))
+        );
+        assert_eq!(
+            overrides.config_file,
+            Some(PathBuf::from("/srv/nebula/server.toml"))
+        );
+
+        // Arguments win over variables
+        let Ok(Command::Serve(overrides)) = parse_command(
+            &["--listen", "127.0.0.1:8080", "--db-file", "/tmp/nebula.db"],
+            &vars,
+        ) else {
+            panic!("The arguments should parse");
+        };
+        assert_eq!(
+            overrides.listen,
+            Some(vec!["127.0.0.1:8080".parse().unwrap()])
+        );
+        assert_eq!(overrides.db_file, Some(PathBuf::from("/tmp/nebula.db")));
+
+        // An empty list turns off TCP, for only listening on the Unix socket
+        let Ok(Command::Serve(overrides)) = parse_command(&[], &[("NEBULA_LISTEN", "")]) else {
+            panic!("The variables should parse");
+        };
+        assert_eq!(overrides.listen, Some(vec![]));
+
+        assert!(parse_command(&[], &[("NEBULA_LISTEN", "0.0.0.0")]).is_err());
+    }
+
+    #[test]
+    fn test_server_config_overrides() {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let file_config: ServerConfig = ServerConfig::parse(
+            "[listen]\naddresses = [\"0.0.0.0:4242\"]\n[paths]\ndb_file = \"/srv/nebula.db\"",
+        )
+        .expect("Config should parse");
+
+        // Only the settings that are given replace the ones from the file
+        let config: ServerConfig = file_config
+            .clone()
+            .with_overrides(&Overrides {
+                unix_socket: Some(PathBuf::from("/run/nebula/server.sock")),
+                web_dir: Some(PathBuf::from("/srv/nebula/web")),
+                ..Overrides::default()
+            })
+            .expect("Overrides should apply");
+        assert_eq!(
+            config.listen.addresses(),
+            vec!["0.0.0.0:4242".parse().unwrap()]
+        );
+        assert!(config.listen.unix_socket.is_some());
+        assert_eq!(config.paths.db_file(), PathBuf::from("/srv/nebula.db"));
+        assert_eq!(config.paths.web_dir(), PathBuf::from("/srv/nebula/web"));
+
+        // A Unix socket on its own does not open the default TCP address
+        let config: ServerConfig = ServerConfig::default()
+            .with_overrides(&Overrides {
+                unix_socket: Some(PathBuf::from("/run/nebula/server.sock")),
+                ..Overrides::default()
+            })
+            .expect("Overrides should apply");
+        assert!(config.listen.addresses().is_empty());
+
+        // There has to be something left to listen on
+        let err: config::ConfigError = file_config
+            .with_overrides(&Overrides {
+                listen: Some(vec![]),
+                ..Overrides::default()
+            })
+            .expect_err("Nothing to listen on should be rejected");
+        assert!(err.to_string().contains("nothing to listen on"), "{}", err);
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_listen_tcp(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+        let ipv4_listener: std::net::TcpListener =
+            listen::bind_tcp("127.0.0.1:0".parse().unwrap()).expect("IPv4 should bind");
+        let port: u16 = ipv4_listener.local_addr().unwrap().port();
+
+        // The same port can be listened on over IPv4 and IPv6
+        let ipv6_address: std::net::SocketAddr = format!("[::1]:{}", port).parse().unwrap();
+        let ipv6_listener: std::net::TcpListener =
+            listen::bind_tcp(ipv6_address).expect("IPv6 should bind next to IPv4");
+        for listener in [ipv4_listener, ipv6_listener] {
+            let address: std::net::SocketAddr = listener.local_addr().unwrap();
+            let listener: TcpListener = TcpListener::from_std(listener).unwrap();
+            tokio::spawn(axum::serve(listener, app.clone()).into_future());
+            let stream: tokio::net::TcpStream =
+                tokio::net::TcpStream::connect(address).await.unwrap();
+            let response: String = raw_get(stream, "/api/memory").await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", address);
+        }
+
+        // An address in use gets an error that says so
+        let address: std::net::SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+        let err: listen::ListenError =
+            listen::bind_tcp(address).expect_err("The port should be in use");
+        assert!(
+            err.to_string()
+                .starts_with(&format!("Unable to listen on {}", address)),
+            "{}",
+            err
+        );
+        assert!(err.to_string().contains("already listening"), "{}", err);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_listen_unix_socket(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("nebula-unix-socket-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let socket_path: PathBuf = dir.join("server.sock");
+
+        // A socket left behind by a server that was killed is replaced
+        drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+        let listener: tokio::net::UnixListener =
+            listen::bind_unix(&socket_path).expect("The stale socket should be replaced");
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+        tokio::spawn(listen::serve_unix(listener, app));
+
+        let stream: tokio::net::UnixStream =
+            tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+        let response: String = raw_get(stream, "/api/memory").await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+        // A socket that a server is listening on is left alone
+        let err: listen::ListenError =
+            listen::bind_unix(&socket_path).expect_err("The socket should be in use");
+        assert!(err.to_string().contains("already listening"), "{}", err);
+        let stream: tokio::net::UnixStream =
+            tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+        assert!(raw_get(stream, "/api/memory").await.is_ok());
+
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_metrics(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Label values are escaped, whatever the executable is called
+        sqlx::query(
+            r#"INSERT INTO PROCESS VALUES(4, 'say "hi"\now', 123456790, 1, 2048, "boot-2", 0, "root");
+            INSERT INTO PROCSTAT VALUES(4, 123456790, 987654322, 10, NULL, NULL, 1000, 1000, 0, NULL, NULL);"#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let app: Router = create_app(&ServerConfig::default(), Some(pool)).await?;
+        let response: Response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/plain; version=0.0.4; charset=utf-8"
+        );
+        let res_bytes: Vec<u8> = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        let text: String = String::from_utf8(res_bytes).unwrap();
+
+        let lines: Vec<&str> = text.lines().collect();
+        for expected in [
+            "nebula_last_sample_timestamp_seconds 987654323",
+            "# TYPE nebula_host_info gauge",
+            "nebula_host_info{hostname=\"test-host\",kernel_release=\"6.1.0-18-amd64\",os_id=\"debian\",cpu_model=\"Test CPU\",virtualization=\"kvm\",container=\"\"} 1",
+            "# HELP nebula_cpu_usage_ratio CPU time per second of the processes that last ran on the core in the latest tick, which is above 1 when their threads also ran on other cores.",
+            "# TYPE nebula_cpu_usage_ratio gauge",
+            "nebula_cpu_usage_ratio{core=\"0\"} 0.25",
+            "nebula_cpu_frequency_hertz{core=\"0\"} 5000000",
+            "nebula_cpu_cache_bytes{core=\"0\"} 10240",
+            "nebula_memory_total_bytes 2048000",
+            "nebula_memory_free_bytes 1024000",
+            "nebula_memory_swap_total_bytes 256000",
+            "nebula_memory_swap_free_bytes 128000",
+            "nebula_disk_used_bytes{device=\"/dev1\",mount=\"/mount1\"} 40894464",
+            "nebula_disk_available_bytes{device=\"/dev1\",mount=\"/mount1\"} 25165824",
+            "nebula_disk_read_only{device=\"/dev1\",mount=\"/mount1\"} 0",
+            "nebula_disk_read_only{device=\"/dev2\",mount=\"/mount2\"} 1",
+            "nebula_disk_inodes_free{device=\"/dev1\",mount=\"/mount1\"} 10",
+            "# TYPE nebula_network_receive_bytes_total counter",
+            "nebula_network_receive_bytes_total{interface=\"eth0\"} 11024",
+            "nebula_network_transmit_bytes_total{interface=\"eth0\"} 5512",
+            "nebula_network_receive_drops_total{interface=\"eth0\"} 7",
+            "# TYPE nebula_network_receive_bytes_per_second gauge",
+            "nebula_network_receive_bytes_per_second{interface=\"eth0\"} 1024",
+            "nebula_network_transmit_bytes_per_second{interface=\"eth0\"} 512",
+            "nebula_network_receive_packets_per_second{interface=\"eth0\"} 2",
+            "nebula_process_cpu_usage_ratio{pid=\"3\",exec=\"/usr/bin/test-exe-3\"} 1.5",
+            "nebula_process_resident_memory_bytes{pid=\"3\",exec=\"/usr/bin/test-exe-3\"} 84000",
+            "nebula_process_resident_memory_bytes{pid=\"4\",exec=\"say \\\"hi\\\"\\\\now\"} 1000",
+            "# TYPE nebula_process_io_read_bytes_total counter",
+            "nebula_process_io_read_bytes_total{pid=\"1\",exec=\"test-exe-1\"} 5000",
+        ] {
+            assert!(lines.contains(&expected), "{}\n{}", expected, text);
+        }
+
+        // Removed interfaces, offline cores, unmounted disks, and older stats
+        // are not current, and unknown values are left out instead of being 0
+        for unexpected in [
+            "interface=\"eth1\"",
+            "core=\"1\"",
+            "mount=\"/old-mount\"",
+            "exec=\"old-exe-2\"",
+            "nebula_process_cpu_usage_ratio{pid=\"4\"",
+            "nebula_process_io_read_bytes_total{pid=\"2\"",
+        ] {
+            assert!(!text.contains(unexpected), "{}\n{}", unexpected, text);
+        }
+
+        // Every sample follows the HELP and TYPE lines of its metric
+        let sample_line: Regex = Regex::new(
+            r#"^([a-z_]+)(\{[a-z_]+="(\\.|[^"\\])*"(,[a-z_]+="(\\.|[^"\\])*")*\})? \S+$"#,
+        )
+        .unwrap();
+        let mut family: Option<&str> = None;
+        for (i, line) in lines.iter().enumerate() {
+            if let Some(help) = line.strip_prefix("# HELP ") {
+                let name: &str = help.split(' ').next().unwrap();
+                assert!(
+                    lines[i + 1].starts_with(&format!("# TYPE {} ", name)),
+                    "{}",
+                    line
+                );
+                family = Some(name);
+            } else if !line.starts_with("# TYPE ") {
+                let captures = sample_line.captures(line).expect(line);
+                assert_eq!(Some(&captures[1]), family, "{}", line);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_metrics_network_totals(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Each change to the database with the bytes received that are
+        // reported afterwards, where every scrape is from a newly started
+        // server
+        for (sql, expected) in [
+            ("SELECT 1;", "11024"),
+            // Pruning old samples does not change the totals
+            (
+                r#"DELETE FROM NETWORKSTAT WHERE TIMESTAMP < 987654330;
+                INSERT INTO NETWORKSTAT VALUES("eth0", 987654330, 100, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 11124, 5512, 103, 51, 1, 0, 7, 0);"#,
+                "11124",
+            ),
+            ("SELECT 1;", "11124"),
+            // The totals start over when the kernel's do
+            (
+                r#"INSERT INTO NETWORKSTAT VALUES("eth0", 987654331, 50, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 50, 0, 1, 0, 0, 0, 0, 0);"#,
+                "50",
+            ),
+        ] {
+            sqlx::query(sql).execute(&pool).await?;
+            let app: Router = create_app(&ServerConfig::default(), Some(pool.clone())).await?;
+            let response: Response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/metrics")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let res_bytes: Vec<u8> = response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec();
+            let text: String = String::from_utf8(res_bytes).unwrap();
+            let expected_line: String = format!(
+                "nebula_network_receive_bytes_total{{interface=\"eth0\"}} {}",
+                expected
+            );
+            assert!(
+                text.lines().any(|line| line == expected_line),
+                "{}\n{}",
+                expected_line,
+                text
+            );
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "models::MIGRATOR", fixtures("apiTest"))]
+    async fn test_metrics_auth(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let _ = tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(Level::TRACE)
+            .try_init();
+
+        // Prometheus scrapes with the same tokens as the API
+        let app: Router = create_app(&auth_test_config(), Some(pool)).await?;
+        for (authorization, status) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("Bearer wrong-0123456789"), StatusCode::UNAUTHORIZED),
+            (Some("Bearer scraper-0123456789"), StatusCode::OK),
+        ] {
+            let mut request = Request::builder().uri("/metrics");
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            let response: Response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{:?}", authorization);
+        }
 
         Ok(())
     }
@@ -0,0 +1,204 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientCertVerifierBuilder, WebPkiClientVerifier};
+use rustls::RootCertStore;
+use serde::Deserialize;
+use tracing::{event, instrument, Level};
+
+/// Number of seconds between checks for rotated certificates when the config
+/// does not say
+const DEFAULT_RELOAD_INTERVAL: u64 = 60;
+
+/// Whether clients have to present a certificate
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuth {
+    /// Every client has to present a certificate signed by the client CA
+    #[default]
+    Required,
+    /// Clients can connect without a certificate, but any certificate that is
+    /// presented has to be signed by the client CA
+    Optional,
+}
+
+/// Settings for serving HTTPS instead of HTTP
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file with the server's certificate chain, leaf first
+    pub cert_file: PathBuf,
+    /// PEM file with the server's private key
+    pub key_file: PathBuf,
+    /// PEM file with the CAs that client certificates have to be signed by,
+    /// where client certificates are not asked for if this is not set
+    pub client_ca_file: Option<PathBuf>,
+    /// Whether clients have to present a certificate when there is a client CA
+    #[serde(default)]
+    pub client_auth: ClientAuth,
+    /// Number of seconds between checks for rotated certificates
+    pub reload_interval: Option<u64>,
+}
+
+impl TlsConfig {
+    /// Files that are read to set up TLS, which are watched for changes
+    fn files(&self) -> Vec<&Path> {
+        let mut files: Vec<&Path> = vec![&self.cert_file, &self.key_file];
+        if let Some(client_ca_file) = &self.client_ca_file {
+            files.push(client_ca_file);
+        }
+        files
+    }
+
+    /// How often the files are checked for changes
+    fn reload_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.reload_interval
+                .unwrap_or(DEFAULT_RELOAD_INTERVAL)
+                .max(1),
+        )
+    }
+}
+
+/// Error from setting up TLS with the configured files
+#[derive(Debug)]
+pub enum TlsError {
+    /// A file could not be read
+    Io(PathBuf, io::Error),
+    /// The certificate file does not have any certificates
+    NoCertificates(PathBuf),
+    /// The key file does not have a private key
+    NoPrivateKey(PathBuf),
+    /// The files were read, but rustls could not use them
+    Rustls(String),
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::Io(path, err) => write!(f, "Unable to read {}: {}", path.display(), err),
+            TlsError::NoCertificates(path) => {
+                write!(f, "{} does not have any PEM certificates", path.display())
+            }
+            TlsError::NoPrivateKey(path) => {
+                write!(f, "{} does not have a PEM private key", path.display())
+            }
+            TlsError::Rustls(message) => write!(f, "Unable to set up TLS: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+/// Reads the certificates in a PEM file
+fn read_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let file: File = File::open(path).map_err(|err| TlsError::Io(path.to_path_buf(), err))?;
+    let certificates: Vec<CertificateDer<'static>> =
+        rustls_pemfile::certs(&mut BufReader::new(file))
+            .collect::<Result<Vec<CertificateDer<'static>>, io::Error>>()
+            .map_err(|err| TlsError::Io(path.to_path_buf(), err))?;
+    if certificates.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+    Ok(certificates)
+}
+
+/// Reads the first private key in a PEM file
+fn read_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, TlsError> {
+    let file: File = File::open(path).map_err(|err| TlsError::Io(path.to_path_buf(), err))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|err| TlsError::Io(path.to_path_buf(), err))?
+        .ok_or_else(|| TlsError::NoPrivateKey(path.to_path_buf()))
+}
+
+/// Builds the rustls config from the configured files
+pub fn load_server_config(config: &TlsConfig) -> Result<rustls::ServerConfig, TlsError> {
+    // The provider is passed along explicitly, so that nothing depends on a
+    // process-wide default being installed
+    let provider: Arc<CryptoProvider> = Arc::new(ring::default_provider());
+    let certificates: Vec<CertificateDer<'static>> = read_certificates(&config.cert_file)?;
+    let private_key: PrivateKeyDer<'static> = read_private_key(&config.key_file)?;
+
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| TlsError::Rustls(err.to_string()))?;
+    let builder = match &config.client_ca_file {
+        Some(client_ca_file) => {
+            let mut roots: RootCertStore = RootCertStore::empty();
+            for certificate in read_certificates(client_ca_file)? {
+                roots
+                    .add(certificate)
+                    .map_err(|err| TlsError::Rustls(err.to_string()))?;
+            }
+            let mut verifier: ClientCertVerifierBuilder =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            if config.client_auth == ClientAuth::Optional {
+                verifier = verifier.allow_unauthenticated();
+            }
+            builder.with_client_cert_verifier(
+                verifier
+                    .build()
+                    .map_err(|err| TlsError::Rustls(err.to_string()))?,
+            )
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config: rustls::ServerConfig = builder
+        .with_single_cert(certificates, private_key)
+        .map_err(|err| TlsError::Rustls(err.to_string()))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+/// Gets when each of the files was last changed, which is None for a file that
+/// cannot be read right now
+fn modified_times(config: &TlsConfig) -> Vec<Option<SystemTime>> {
+    config
+        .files()
+        .into_iter()
+        .map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// Watches the configured files, swapping in the new certificates whenever
+/// they change so that rotating them does not need a restart
+///
+/// Connections that are already open keep the certificates they started
+/// with. If the new files cannot be used, such as while only some of them
+/// have been replaced, the old certificates are kept until the next change.
+#[instrument(skip_all)]
+pub async fn watch_certificates(config: TlsConfig, rustls_config: RustlsConfig) {
+    let mut interval: tokio::time::Interval = tokio::time::interval(config.reload_interval());
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_modified: Vec<Option<SystemTime>> = modified_times(&config);
+    loop {
+        interval.tick().await;
+        let modified: Vec<Option<SystemTime>> = modified_times(&config);
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+        match load_server_config(&config) {
+            Ok(server_config) => {
+                rustls_config.reload_from_config(Arc::new(server_config));
+                event!(Level::INFO, "Reloaded the TLS certificates");
+            }
+            Err(err) => event!(
+                Level::ERROR,
+                "Keeping the current TLS certificates: {}",
+                err
+            ),
+        }
+    }
+}
@@ -1,23 +1,17 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use axum::Router;
+use axum::{middleware, Router};
 use tower_http::services::{ServeDir, ServeFile};
-use tracing::{event, Level};
 
-const WEB_FILES_DIR: &str = "/var/nebula/web";
-const TEST_WEB_FILES_DIR: &str = "../assets/web";
+use crate::auth::{require_web_identity, Authenticator};
 
-/// Creates the router for the web routes
-pub fn create_web_router() -> Router {
-    let files_path_str: &str = if cfg!(test) {
-        event!(Level::TRACE, "Using the local assets folder for a test");
-        TEST_WEB_FILES_DIR
-    } else {
-        event!(Level::TRACE, "Using the official install folder");
-        WEB_FILES_DIR
-    };
-    let files_dir: PathBuf = fs::canonicalize(files_path_str).expect("Files should exist");
+/// Creates the router for the web routes, which serves the files in a
+/// directory and sends people to the login page first when authentication is
+/// enabled
+pub fn create_web_router(files_path: &Path, auth: Arc<Authenticator>) -> Router {
+    let files_dir: PathBuf = fs::canonicalize(files_path).expect("Files should exist");
 
     Router::new()
         // This will implicitly call / to be index.html
@@ -40,4 +34,5 @@ pub fn create_web_router() -> Router {
             "{}/404.html",
             files_dir.to_str().unwrap()
         )))
+        .layer(middleware::from_fn_with_state(auth, require_web_identity))
 }